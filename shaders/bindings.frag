@@ -0,0 +1,13 @@
+#version 460 core
+
+layout(location = 0) out vec4 fragColor;
+
+layout(binding = 2) uniform sampler2D uTex;
+
+layout(binding = 1) uniform Params {
+    vec4 color;
+} params;
+
+void main() {
+    fragColor = texture(uTex, vec2(0.0)) * params.color;
+}
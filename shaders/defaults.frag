@@ -0,0 +1,17 @@
+#version 460 core
+
+layout(location = 0) out vec4 fragColor;
+
+// @range(0, 10) @label("Exposure")
+layout(location = 0) uniform float uExposure = 1.5;
+layout(location = 1) uniform vec3 uTint = vec3(1.0, 0.5, 0.25); // @label("Tint color")
+layout(location = 2) uniform mat2 uRotation = mat2(1.0);
+#pragma @range(1, 8)
+layout(location = 4) uniform int uSteps = 3;
+layout(location = 5) uniform bool uEnabled = true;
+layout(location = 6) uniform float uAlpha;
+
+void main() {
+    vec2 v = uRotation * vec2(uSteps);
+    fragColor = uEnabled ? vec4(uTint * uExposure, uAlpha) + v.xyxy : vec4(0.0);
+}
@@ -0,0 +1,20 @@
+#version 460 core
+
+layout(location = 0) uniform float uAlpha;
+
+#pragma stage(vertex)
+layout(location = 0) in vec2 position;
+layout(location = 0) out vec2 uv;
+
+void main() {
+    uv = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
+
+#pragma stage(fragment)
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 fragColor;
+
+void main() {
+    fragColor = vec4(uv, 0.0, uAlpha);
+}
@@ -0,0 +1,15 @@
+#version 460 core
+
+layout(location = 0) out vec4 fragColor;
+
+layout(location = 0) uniform float uAlpha;
+layout(location = 1) uniform float uUnused;
+layout(location = 2) uniform vec4 uTint;
+
+vec4 tint() {
+    return uTint;
+}
+
+void main() {
+    fragColor = tint() * uAlpha;
+}
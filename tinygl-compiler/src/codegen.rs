@@ -1,23 +1,150 @@
 mod compiler;
 mod types;
 
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::prelude::*;
 use std::io::BufWriter;
 use std::path::Path;
 
-use crate::Result;
+use quote::{format_ident, quote};
+
+use crate::{Error, Result};
 
 pub use compiler::WrappedItem;
 
+/// Generated code for a module and its submodules
+#[derive(Default)]
+struct Module {
+    items: Vec<proc_macro2::TokenStream>,
+    children: BTreeMap<String, Module>,
+}
+
+impl Module {
+    fn build(items: &[&dyn WrappedItem]) -> Result<Self> {
+        let mut root = Self::default();
+        let mut seen = std::collections::HashSet::new();
+
+        for item in items {
+            let path = item.module_path();
+
+            // Every module contains the code of a single shader
+            if !path.is_empty() && !seen.insert(path) {
+                return Err(Error::DuplicateModule(path.join("::")));
+            }
+
+            let module = path.iter().fold(&mut root, |module, name| {
+                module.children.entry(name.clone()).or_default()
+            });
+
+            module.items.push(item.generate()?);
+        }
+
+        Ok(root)
+    }
+
+    /// Write this module and its submodules inline
+    fn write_inline(&self, wr: &mut impl Write) -> Result<()> {
+        for item in &self.items {
+            writeln!(wr, "{}", item)?;
+        }
+
+        for (name, child) in &self.children {
+            writeln!(wr, "pub mod {} {{", name)?;
+            child.write_inline(wr)?;
+            writeln!(wr, "}}")?;
+        }
+
+        Ok(())
+    }
+
+    /// Write submodules which contain items to their own files in `dir`, and return their
+    /// declarations
+    fn write_children(&self, dir: &Path) -> Result<proc_macro2::TokenStream> {
+        let mut decls = Vec::new();
+
+        for (name, child) in &self.children {
+            let ident = format_ident!("{}", name);
+
+            if child.items.is_empty() {
+                // Only a directory, declare it inline
+                let inner = child.write_children(&dir.join(name))?;
+
+                decls.push(quote! {
+                    pub mod #ident {
+                        #inner
+                    }
+                });
+            } else {
+                // Shader module, written to its own file
+                std::fs::create_dir_all(dir)?;
+                let path = dir.join(format!("{}.rs", name));
+                let inner = child.write_children(&dir.join(name))?;
+
+                let mut wr = BufWriter::new(File::create(&path)?);
+                for item in &child.items {
+                    writeln!(wr, "{}", item)?;
+                }
+                writeln!(wr, "{}", inner)?;
+
+                // Absolute paths, so the root module can be used with include!
+                let path = path.to_string_lossy();
+                decls.push(quote! {
+                    #[path = #path]
+                    pub mod #ident;
+                });
+            }
+        }
+
+        Ok(quote! { #(#decls)* })
+    }
+}
+
+/// Write generated code to a single file
+///
+/// Shaders wrapped with a shader root are placed in inline modules, following their module path.
+/// Other shaders are written at the root.
+///
+/// # Parameters
+///
+/// * `dest`: path to the output file
+/// * `items`: items to generate code for
 pub fn write<'a>(dest: impl AsRef<Path>, items: &[&'a dyn WrappedItem]) -> Result<()> {
+    let root = Module::build(items)?;
+
     // Write master shaders.rs file
     let output_rs = File::create(dest.as_ref())?;
     let mut wr = BufWriter::new(output_rs);
 
-    for item in items {
-        writeln!(wr, "{}", item.generate()?)?;
+    root.write_inline(&mut wr)
+}
+
+/// Write generated code to a module tree
+///
+/// The generated code for each shader wrapped with a shader root is written to its own file, in
+/// directories following its module path. Programs, uniform sets and shaders wrapped without a
+/// shader root are written to a root `mod.rs` file, which can be included using `include!`.
+///
+/// # Parameters
+///
+/// * `dest_dir`: path to the output directory
+/// * `items`: items to generate code for
+pub fn write_tree(dest_dir: impl AsRef<Path>, items: &[&dyn WrappedItem]) -> Result<()> {
+    let root = Module::build(items)?;
+
+    std::fs::create_dir_all(dest_dir.as_ref())?;
+    let dest_dir = std::fs::canonicalize(dest_dir.as_ref())?;
+
+    let decls = root.write_children(&dest_dir)?;
+
+    // Write root mod.rs file
+    let output_rs = File::create(dest_dir.join("mod.rs"))?;
+    let mut wr = BufWriter::new(output_rs);
+
+    for item in &root.items {
+        writeln!(wr, "{}", item)?;
     }
+    writeln!(wr, "{}", decls)?;
 
     Ok(())
 }
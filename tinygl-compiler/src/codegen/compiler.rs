@@ -1,5 +1,38 @@
 pub trait WrappedItem {
     fn generate(&self) -> crate::Result<proc_macro2::TokenStream>;
+
+    /// Path of the module this item should be written to, relative to the root of the generated
+    /// code. Items with an empty path are written at the root.
+    fn module_path(&self) -> &[String] {
+        &[]
+    }
+}
+
+/// Path to an item defined in the module of a wrapped shader, from the root of the generated code
+fn shader_item_path(
+    shader: &dyn crate::WrappedShaderDetails,
+    name: &str,
+) -> proc_macro2::TokenStream {
+    let module = shader
+        .module_path()
+        .iter()
+        .map(|m| quote::format_ident!("{}", m));
+    let name = quote::format_ident!("{}", name);
+
+    quote::quote! { self #(::#module)* :: #name }
+}
+
+/// `#[deprecated]` attribute for the setter of an unused uniform, if the shader requests it
+fn deprecated_attr(
+    shader: &dyn crate::WrappedShaderDetails,
+    uniform: &crate::reflect::FoundUniform,
+) -> proc_macro2::TokenStream {
+    if uniform.is_unused() && shader.unused_uniform_mode() == crate::UnusedUniformMode::Deprecate {
+        let note = format!("{} is not used by the shader", uniform.name);
+        quote::quote! { #[deprecated(note = #note)] }
+    } else {
+        quote::quote! {}
+    }
 }
 
 mod uniform_set;
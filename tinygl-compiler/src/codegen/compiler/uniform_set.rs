@@ -4,7 +4,11 @@ use std::iter::FromIterator;
 use heck::{CamelCase, SnakeCase};
 use quote::{format_ident, quote};
 
-use crate::{codegen::types::CodegenExt, reflect::FoundUniform, WrappedUniformSet};
+use crate::{
+    codegen::types::{CodegenExt, WebUniformExt},
+    reflect::FoundUniform,
+    WrappedUniformSet,
+};
 
 use super::WrappedItem;
 
@@ -36,23 +40,44 @@ impl WrappedItem for WrappedUniformSet<'_, '_> {
         let mut unified: Vec<_> = unified.into_iter().collect();
         unified.sort_by_key(|f| &f.name);
 
+        // Find the binding of a uniform in every program of the set
+        let bindings = |uniform: &FoundUniform| -> Vec<Option<i32>> {
+            self.programs()
+                .iter()
+                .map(|program| {
+                    program
+                        .shaders_with_uniforms()
+                        .flat_map(|shader| shader.uniforms())
+                        .find(|u| u.name == uniform.name)
+                        .and_then(|u| u.binding)
+                })
+                .collect()
+        };
+
         // Write trait declaration
         let mut methods = Vec::new();
 
         // Write methods
         //
-        // TODO: Some uniforms might have bindings and other might not. How should this be
-        // represented in this API?
+        // Binding getters return the binding directly if all programs in the set declare one, and
+        // an Option if only some of them do.
         for uniform in &unified {
             let ty = uniform.ty.unwrap();
             let sc = uniform.name.to_snake_case();
             let type_name: syn::Type = syn::parse_str(&ty.rust_value_type()).unwrap();
+            let bindings = bindings(uniform);
 
-            if uniform.binding.is_some() {
+            if bindings.iter().all(Option::is_some) {
+                let ident = format_ident!("get_{}_binding", sc);
+
+                methods.push(quote! {
+                    fn #ident(&self) -> u32;
+                });
+            } else if bindings.iter().any(Option::is_some) {
                 let ident = format_ident!("get_{}_binding", sc);
 
                 methods.push(quote! {
-                    fn #ident(&self) -> #type_name;
+                    fn #ident(&self) -> Option<u32>;
                 });
             }
 
@@ -66,16 +91,12 @@ impl WrappedItem for WrappedUniformSet<'_, '_> {
 
             let ident = format_ident!("set_{}", sc);
             let extra = ty.uniform_method_extra_args_with_ty().into_iter();
+            let target_cfg = ty.target_cfg();
 
-            if extra.clone().next().is_some() {
-                methods.push(quote! {
-                    fn #ident(&self, gl: &::tinygl::Context, #(#extra),* value: #type_name);
-                });
-            } else {
-                methods.push(quote! {
-                    fn #ident(&self, gl: &::tinygl::Context, value: #type_name);
-                });
-            }
+            methods.push(quote! {
+                #target_cfg
+                fn #ident(&self, gl: &::tinygl::Context, #(#extra,)* value: #type_name);
+            });
         }
 
         let trait_name = format_ident!("{}UniformSet", self.id().to_camel_case());
@@ -88,23 +109,40 @@ impl WrappedItem for WrappedUniformSet<'_, '_> {
         // Write implementations for the known programs
         let mut set_impl = Vec::new();
 
-        for program in self.programs().iter() {
+        for (program_index, program) in self.programs().iter().enumerate() {
             let mut methods = Vec::new();
+            let struct_name = format_ident!("{}", program.struct_name());
 
             for uniform in &unified {
                 let sc = uniform.name.to_snake_case();
                 let ty = uniform.ty.unwrap();
                 let type_name: syn::Type = syn::parse_str(&ty.rust_value_type()).unwrap();
-                let struct_name = format_ident!("{}", program.struct_name());
+                let bindings = bindings(uniform);
 
-                if uniform.binding.is_some() {
+                if bindings.iter().all(Option::is_some) {
                     let ident = format_ident!("get_{}_binding", sc);
 
                     methods.push(quote! {
-                        fn #ident(&self) -> #type_name {
+                        fn #ident(&self) -> u32 {
                             #struct_name::#ident(self)
                         }
                     });
+                } else if bindings.iter().any(Option::is_some) {
+                    let ident = format_ident!("get_{}_binding", sc);
+
+                    if bindings[program_index].is_some() {
+                        methods.push(quote! {
+                            fn #ident(&self) -> Option<u32> {
+                                Some(#struct_name::#ident(self))
+                            }
+                        });
+                    } else {
+                        methods.push(quote! {
+                            fn #ident(&self) -> Option<u32> {
+                                None
+                            }
+                        });
+                    }
                 }
 
                 if uniform.format().is_some() {
@@ -121,34 +159,124 @@ impl WrappedItem for WrappedUniformSet<'_, '_> {
 
                 let extra_args = ty.uniform_method_extra_args_with_ty().into_iter();
                 let extra_values = ty.uniform_method_extra_args_no_ty().into_iter();
+                let target_cfg = ty.target_cfg();
 
-                if extra_args.clone().next().is_some() {
-                    methods.push(quote! {
-                        fn #ident(&self, gl: &::tinygl::Context, #(#extra_args),*, value: #type_name) {
-                            #struct_name::#ident(self, gl, #(#extra_values),*, value)
-                        }
-                    });
+                methods.push(quote! {
+                    #target_cfg
+                    fn #ident(&self, gl: &::tinygl::Context, #(#extra_args,)* value: #type_name) {
+                        #struct_name::#ident(self, gl, #(#extra_values,)* value)
+                    }
+                });
+            }
+
+            set_impl.push(quote! {
+                // Setters of uniforms unused by some programs may be deprecated
+                #[allow(deprecated)]
+                impl #trait_name for #struct_name {
+                    #(#methods)*
+                }
+            });
+        }
+
+        // Write an enum over the programs of the set, for holding any of them without generics
+        let enum_name = format_ident!("{}UniformSetProgram", self.id().to_camel_case());
+        let variant: Vec<_> = self
+            .programs()
+            .iter()
+            .map(|program| format_ident!("{}", program.struct_name()))
+            .collect();
+
+        let mut dispatch = Vec::new();
+
+        for uniform in &unified {
+            let sc = uniform.name.to_snake_case();
+            let ty = uniform.ty.unwrap();
+            let type_name: syn::Type = syn::parse_str(&ty.rust_value_type()).unwrap();
+            let bindings = bindings(uniform);
+
+            if bindings.iter().any(Option::is_some) {
+                let ident = format_ident!("get_{}_binding", sc);
+                let return_type = if bindings.iter().all(Option::is_some) {
+                    quote! { u32 }
                 } else {
-                    methods.push(quote! {
-                        fn #ident(&self, gl: &::tinygl::Context, value: #type_name) {
-                            #struct_name::#ident(self, gl, value)
+                    quote! { Option<u32> }
+                };
+
+                dispatch.push(quote! {
+                    fn #ident(&self) -> #return_type {
+                        match self {
+                            #(Self::#variant(program) => #trait_name::#ident(program),)*
                         }
-                    });
-                }
+                    }
+                });
             }
 
-            let program_struct_name = format_ident!("{}", program.struct_name());
+            if uniform.format().is_some() {
+                let ident = format_ident!("get_{}_format", sc);
 
-            set_impl.push(quote! {
-                impl #trait_name for #program_struct_name {
-                    #(#methods)*
+                dispatch.push(quote! {
+                    fn #ident(&self) -> u32 {
+                        match self {
+                            #(Self::#variant(program) => #trait_name::#ident(program),)*
+                        }
+                    }
+                });
+            }
+
+            let ident = format_ident!("set_{}", sc);
+            let extra_args = ty.uniform_method_extra_args_with_ty().into_iter();
+            let extra_values = ty
+                .uniform_method_extra_args_no_ty()
+                .map(|values| quote! { #values, })
+                .unwrap_or_default();
+            let target_cfg = ty.target_cfg();
+
+            dispatch.push(quote! {
+                #target_cfg
+                fn #ident(&self, gl: &::tinygl::Context, #(#extra_args,)* value: #type_name) {
+                    match self {
+                        #(Self::#variant(program) => #trait_name::#ident(program, gl, #extra_values value),)*
+                    }
                 }
             });
         }
 
+        let set_enum = quote! {
+            pub enum #enum_name {
+                #(#variant(#variant),)*
+            }
+
+            #(impl From<#variant> for #enum_name {
+                fn from(program: #variant) -> Self {
+                    Self::#variant(program)
+                }
+            })*
+
+            impl #trait_name for #enum_name {
+                #(#dispatch)*
+            }
+
+            impl ::tinygl::wrappers::ProgramCommon for #enum_name {
+                fn name(&self) -> ::tinygl::gl::ProgramName {
+                    match self {
+                        #(Self::#variant(program) => ::tinygl::wrappers::ProgramCommon::name(program),)*
+                    }
+                }
+            }
+
+            impl ::tinygl::wrappers::GlDrop for #enum_name {
+                unsafe fn drop(&mut self, gl: &::tinygl::Context) {
+                    match self {
+                        #(Self::#variant(program) => ::tinygl::wrappers::GlDrop::drop(program, gl),)*
+                    }
+                }
+            }
+        };
+
         Ok(quote! {
             #set_trait
             #(#set_impl)*
+            #set_enum
         })
     }
 }
@@ -1,9 +1,14 @@
 use heck::SnakeCase;
 use quote::{format_ident, quote};
 
-use crate::{codegen::types::CodegenExt, WrappedProgram};
+use crate::{
+    codegen::types::{uniform_components, CodegenExt, ConstantValueExt, WebUniformExt},
+    model::RemovedBinding,
+    types::{AtomType, GenericType, ItemOrArrayType},
+    WrappedProgram,
+};
 
-use super::WrappedItem;
+use super::{deprecated_attr, shader_item_path, WrappedItem};
 
 impl WrappedItem for WrappedProgram<'_> {
     fn generate(&self) -> crate::Result<proc_macro2::TokenStream> {
@@ -15,7 +20,7 @@ impl WrappedItem for WrappedProgram<'_> {
             .collect();
         let uniform_struct_name: Vec<_> = self
             .shaders_with_uniforms()
-            .map(|shader| format_ident!("{}", shader.uniform_struct_name()))
+            .map(|shader| shader_item_path(*shader, shader.uniform_struct_name()))
             .collect();
 
         let prog_struct = quote! {
@@ -35,95 +40,205 @@ impl WrappedItem for WrappedProgram<'_> {
             .collect();
         let shader_struct_name: Vec<_> = self
             .shaders()
-            .map(|s| format_ident!("{}", s.shader_struct_name()))
+            .map(|s| shader_item_path(*s, s.shader_struct_name()))
             .collect();
 
+        // Bindings which couldn't be declared in the shader sources are set after linking
+        let restore_bindings = generate_restore_bindings(self);
+
         // Constructor function
         methods.push(quote! {
             pub fn new(gl: &::tinygl::Context, #(#shader_variable_name: &#shader_struct_name),*) -> ::tinygl::Result<Self> {
-                let program_name = ::tinygl::wrappers::RuntimeProgramBuilder::new(gl)
+                let program = ::tinygl::wrappers::RuntimeProgramBuilder::new(gl)
                     #(.shader(#shader_variable_name))*
-                    .build()?
-                    .into_inner();
+                    .build()?;
+
+                Ok(Self::from_program(gl, program))
+            }
 
-                Ok(Self {
-                    name: program_name,
-                    #(#uniform_locations_name: #uniform_struct_name::new(gl, program_name)),*
-                })
+            fn from_program(gl: &::tinygl::Context, program: ::tinygl::wrappers::RuntimeProgram) -> Self {
+                #restore_bindings
+
+                Self {
+                    #(#uniform_locations_name: #uniform_struct_name::new(gl, ::tinygl::wrappers::ProgramCommon::name(&program)),)*
+                    name: program.into_inner(),
+                }
             }
         });
 
-        // Write builder (constructs shaders and then calls the constructor)
+        // Write builders (constructs shaders and links them, optionally through a binary cache)
         methods.push(quote! {
+            /// Hashes of the shaders of this program, used as a key by program binary caches
+            pub const SOURCE_HASHES: &'static [u64] = &[#(#shader_struct_name::SOURCE_HASH),*];
+
             pub fn build(gl: &::tinygl::Context) -> ::tinygl::Result<Self> {
                 #(let #shader_variable_name = ::tinygl::wrappers::GlRefHandle::new(gl, #shader_struct_name::build(gl)?);)*
 
-                Ok(Self::new(
+                Self::new(
                     gl,
                     #(#shader_variable_name.as_ref()),*
-                )?)
+                )
             }
-        });
 
-        // List of seen uniforms, since uniform names are unique
-        let mut known = std::collections::HashSet::new();
+            /// Build this program, loading its binary from `cache` if possible and storing the
+            /// linked binary otherwise
+            #[cfg(not(target_arch = "wasm32"))]
+            pub fn build_with_cache(gl: &::tinygl::Context, cache: &::tinygl::wrappers::ProgramBinaryCache) -> ::tinygl::Result<Self> {
+                // Shaders only need to be compiled if there is no usable binary
+                if let Some(program) = cache.load(gl, Self::SOURCE_HASHES) {
+                    return Ok(Self::from_program(gl, program));
+                }
 
-        // Uniform getters/setters for the included shaders
-        for shader in self.shaders_with_uniforms() {
-            for uniform in shader.uniforms() {
-                let ty = uniform.ty.unwrap();
-                let sc = uniform.name.to_snake_case();
-                let type_name: syn::Type = syn::parse_str(&ty.rust_value_type()).unwrap();
+                if !cache.is_supported() {
+                    return Self::build(gl);
+                }
 
-                // Skip this uniform if it has been added already
-                if known.contains(&uniform.name) {
-                    continue;
-                } else {
-                    known.insert(&uniform.name);
+                #(let #shader_variable_name = ::tinygl::wrappers::GlRefHandle::new(gl, #shader_struct_name::build(gl)?);)*
+
+                let program = ::tinygl::wrappers::RuntimeProgramBuilder::new(gl)
+                    #(.shader(#shader_variable_name.as_ref()))*
+                    .binary_retrievable_hint(true)
+                    .build()?;
+
+                cache.store(gl, Self::SOURCE_HASHES, &program);
+                Ok(Self::from_program(gl, program))
+            }
+        });
+
+        // Reload method for debug builds, only if all shaders can be reloaded
+        if self.shaders().all(|s| s.reload_info().is_some()) {
+            methods.push(quote! {
+                /// Return the paths to all the files the shaders of this program are built from,
+                /// for watching them for changes
+                #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+                pub fn source_paths() -> impl Iterator<Item = &'static str> {
+                    ::std::iter::empty()
+                        #(.chain(#shader_struct_name::source_paths()))*
                 }
 
-                let ident = format_ident!("set_{}", sc);
-                let extra_args = ty.uniform_method_extra_args_with_ty().into_iter();
-                let extra_values = ty.uniform_method_extra_args_no_ty().into_iter();
-                let location_name = format_ident!("{}", shader.uniform_locations_name());
+                #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+                pub fn reload(&mut self, gl: &::tinygl::Context) -> ::tinygl::Result<()> {
+                    #(let #shader_variable_name = ::tinygl::wrappers::GlRefHandle::new(gl, #shader_struct_name::reload(gl)?);)*
 
-                if extra_args.clone().next().is_some() {
-                    methods.push(quote! {
-                        pub fn #ident(&self, gl: &::tinygl::Context, #(#extra_args),* value: #type_name) {
-                            self.#location_name.#ident(gl, self.name, #(#extra_values)*,, value);
-                        }
-                    });
-                } else {
-                    methods.push(quote! {
-                        pub fn #ident(&self, gl: &::tinygl::Context, value: #type_name) {
-                            self.#location_name.#ident(gl, self.name, value);
-                        }
-                    });
+                    // Only replace the current program if the new one linked successfully
+                    let program = ::tinygl::wrappers::RuntimeProgramBuilder::new(gl)
+                        #(.shader(&*#shader_variable_name))*
+                        .build()?;
+
+                    let program = Self::from_program(gl, program);
+
+                    unsafe {
+                        gl.delete_program(self.name);
+                    }
+
+                    *self = program;
+
+                    Ok(())
                 }
+            });
+        }
 
-                if let Some(binding) = uniform.binding {
-                    let ident = format_ident!("get_{}_binding", sc);
-                    let binding = binding as u32;
+        // Uniform descriptions for user interfaces
+        let mut metadata = Vec::new();
 
-                    methods.push(quote! {
-                        pub fn #ident(&self) -> #type_name {
-                            #binding
-                        }
-                    });
+        // Uniform getters/setters for the included shaders, since uniform names are unique
+        for (shader, uniform) in self.uniforms() {
+            let ty = uniform.ty.unwrap();
+            let sc = uniform.name.to_snake_case();
+            let type_name: syn::Type = syn::parse_str(&ty.rust_value_type()).unwrap();
+
+            let ident = format_ident!("set_{}", sc);
+            let extra_args = ty.uniform_method_extra_args_with_ty().into_iter();
+            let extra_values = ty.uniform_method_extra_args_no_ty().into_iter();
+            let location_name = format_ident!("{}", shader.uniform_locations_name());
+            let target_cfg = ty.target_cfg();
+            let deprecated = deprecated_attr(shader, uniform);
+
+            methods.push(quote! {
+                #target_cfg
+                #deprecated
+                #[allow(deprecated)]
+                pub fn #ident(&self, gl: &::tinygl::Context, #(#extra_args,)* value: #type_name) {
+                    self.#location_name.#ident(gl, ::tinygl::wrappers::ProgramCommon::name(self), #(#extra_values,)* value);
                 }
+            });
 
-                if let Some(format) = uniform.format() {
-                    let ident = format_ident!("get_{}_format", sc);
+            let name = &uniform.name;
+            let glsl_type = ty.to_string();
+            let range = match uniform.annotations.range {
+                Some((min, max)) => {
+                    let (min, max) = (
+                        proc_macro2::Literal::f64_suffixed(min),
+                        proc_macro2::Literal::f64_suffixed(max),
+                    );
+                    quote! { Some((#min, #max)) }
+                }
+                None => quote! { None },
+            };
+            let label = match &uniform.annotations.label {
+                Some(label) => quote! { Some(#label) },
+                None => quote! { None },
+            };
+            let default = match &uniform.default {
+                Some(default) => {
+                    let components = uniform_components(default);
+                    quote! { Some(#components) }
+                }
+                None => quote! { None },
+            };
 
-                    methods.push(quote! {
-                        pub fn #ident(&self) -> u32 {
-                            #format
-                        }
-                    });
+            metadata.push(quote! {
+                ::tinygl::wrappers::UniformMetadata {
+                    name: #name,
+                    ty: #glsl_type,
+                    range: #range,
+                    label: #label,
+                    default: #default,
                 }
+            });
+
+            // Default value from the initializer of the uniform
+            if let Some(value) = uniform
+                .default
+                .as_ref()
+                .and_then(|default| ty.constant_value(default))
+            {
+                let default_ident = format_ident!("default_{}", sc);
+                let storage: syn::Type = syn::parse_str(&ty.rust_storage_type()).unwrap();
+                let doc = format!(" Default value of `{}`, from its initializer", uniform.name);
+
+                methods.push(quote! {
+                    #[doc = #doc]
+                    pub const fn #default_ident() -> #storage {
+                        #value
+                    }
+                });
+            }
+
+            if let Some(binding) = uniform.binding {
+                let ident = format_ident!("get_{}_binding", sc);
+                let binding = binding as u32;
+
+                methods.push(quote! {
+                    pub fn #ident(&self) -> #type_name {
+                        #binding
+                    }
+                });
+            }
+
+            if let Some(format) = uniform.format() {
+                let ident = format_ident!("get_{}_format", sc);
+
+                methods.push(quote! {
+                    pub fn #ident(&self) -> u32 {
+                        #format
+                    }
+                });
             }
         }
 
+        let uniform_values = generate_uniform_values(self);
+
         Ok(quote! {
             #prog_struct
 
@@ -131,18 +246,299 @@ impl WrappedItem for WrappedProgram<'_> {
                 #(#methods)*
             }
 
+            #uniform_values
+
+            impl ::tinygl::wrappers::ProgramUniformMetadata for #struct_name {
+                const UNIFORMS: &'static [::tinygl::wrappers::UniformMetadata] = &[
+                    #(#metadata),*
+                ];
+            }
+
             impl ::tinygl::wrappers::ProgramCommon for #struct_name {
-                fn name(&self) -> ::tinygl::gl::Program {
+                #[cfg(not(target_arch = "wasm32"))]
+                fn name(&self) -> ::tinygl::gl::ProgramName {
                     self.name
                 }
+
+                #[cfg(target_arch = "wasm32")]
+                fn name(&self) -> ::tinygl::gl::ProgramName {
+                    &self.name
+                }
             }
 
             impl ::tinygl::wrappers::GlDrop for #struct_name {
+                #[cfg(not(target_arch = "wasm32"))]
+                unsafe fn drop(&mut self, gl: &::tinygl::Context) {
+                    gl.delete_program(self.name);
+                }
+
+                #[cfg(target_arch = "wasm32")]
                 unsafe fn drop(&mut self, gl: &::tinygl::Context) {
-                    use ::tinygl::wrappers::ProgramCommon;
-                    gl.delete_program(self.name());
+                    gl.delete_program(Some(&self.name));
                 }
             }
         })
     }
 }
+
+/// Generate the statements restoring the bindings removed from the sources of a program
+fn generate_restore_bindings(program: &WrappedProgram) -> proc_macro2::TokenStream {
+    let mut known = std::collections::HashSet::new();
+    let mut restores = Vec::new();
+
+    for binding in program.shaders().flat_map(|s| s.removed_bindings()) {
+        // Stages may share uniforms
+        if !known.insert(binding) {
+            continue;
+        }
+
+        restores.push(match binding {
+            RemovedBinding::Sampler { name, binding } => {
+                let binding = *binding as i32;
+                quote! {
+                    // Setting uniforms is only unsafe on WebGL
+                    #[allow(unused_unsafe)]
+                    unsafe { program.get_uniform_location(gl, #name).set_i32(gl, #binding); }
+                }
+            }
+            RemovedBinding::UniformBlock { name, binding } => quote! {
+                if let Some(block) = program.get_uniform_block_index(gl, #name) {
+                    block.block_binding(gl, #binding);
+                }
+            },
+        });
+    }
+
+    if restores.is_empty() {
+        return quote! {};
+    }
+
+    quote! {
+        {
+            use ::tinygl::wrappers::ProgramCommonExt;
+
+            let restore = || {
+                #(#restores)*
+            };
+
+            // WebGL sets uniforms on the current program
+            #[cfg(target_arch = "wasm32")]
+            gl.with_program(::tinygl::wrappers::ProgramCommon::name(&program), restore);
+
+            #[cfg(not(target_arch = "wasm32"))]
+            restore();
+        }
+    }
+}
+
+/// Generate the batched uniform value struct for a program
+///
+/// The generated struct holds one public field per uniform of the program, and keeps track of
+/// the last values uploaded by `apply` so unchanged uniforms don't result in GL calls.
+fn generate_uniform_values(program: &WrappedProgram) -> proc_macro2::TokenStream {
+    let struct_name = format_ident!("{}", program.struct_name());
+    let values_name = format_ident!("{}Uniforms", program.struct_name());
+    let state_name = format_ident!("{}UniformsState", program.struct_name());
+
+    let mut fields = Vec::new();
+    let mut defaults = Vec::new();
+    let mut state_fields = Vec::new();
+    let mut uploads = Vec::new();
+    let mut resets = Vec::new();
+
+    let mut known = std::collections::HashSet::new();
+
+    for shader in program.shaders_with_uniforms() {
+        for uniform in shader.uniforms() {
+            let ty = uniform.ty.unwrap();
+
+            // Uniform arrays are not supported by the value struct
+            if let ItemOrArrayType::Array(_, _) = ty {
+                continue;
+            }
+
+            if !known.insert(&uniform.name) {
+                continue;
+            }
+
+            let field = format_ident!("{}", uniform.name.to_snake_case());
+            let storage: syn::Type = syn::parse_str(&ty.rust_storage_type()).unwrap();
+            let location_name = format_ident!("{}", shader.uniform_locations_name());
+            let location = format_ident!("{}", uniform.location_name());
+            let program_uniform = format_ident!("program_uniform{}", ty.uniform_method_name());
+
+            fields.push(quote! { pub #field: #storage });
+            state_fields.push(quote! { #field: Option<#storage> });
+
+            // Uniforms default to their initializer, and samplers and images to their declared
+            // binding
+            let initializer = uniform
+                .default
+                .as_ref()
+                .and_then(|default| ty.constant_value(default));
+
+            defaults.push(match (&initializer, uniform.binding) {
+                (Some(value), _) => quote! { #field: #value },
+                (None, Some(binding)) => {
+                    let binding = binding as u32;
+                    quote! { #field: #binding }
+                }
+                (None, None) => quote! { #field: ::std::default::Default::default() },
+            });
+
+            // Upload of a value stored as `storage` to the uniform of `program`
+            let upload = |value: proc_macro2::TokenStream| {
+                let mut call_args = Vec::new();
+
+                if let Some(count) = ty.uniform_count_arg() {
+                    let count =
+                        syn::LitInt::new(&format!("{}", count), proc_macro2::Span::call_site());
+                    call_args.push(quote! { #count });
+                }
+
+                call_args.push(match ty {
+                    ItemOrArrayType::Item(GenericType::Atom(AtomType::Bool))
+                    | ItemOrArrayType::Image { .. } => {
+                        quote! { #value as i32 }
+                    }
+                    ItemOrArrayType::Item(GenericType::Vector(_)) => {
+                        quote! { #value.as_ptr() as *const _ }
+                    }
+                    ItemOrArrayType::Item(GenericType::Matrix(_)) => {
+                        // Values are stored column-major, no need to transpose
+                        quote! { 0, #value.as_ptr() as *const _ }
+                    }
+                    _ => quote! { #value },
+                });
+
+                let web_value = match ty {
+                    ItemOrArrayType::Item(GenericType::Vector(_))
+                    | ItemOrArrayType::Item(GenericType::Matrix(_)) => quote! { &#value },
+                    _ => quote! { #value },
+                };
+
+                let web_upload = ty
+                    .web_uniform_call(quote! { Some(location) }, web_value, quote! { false })
+                    .map(|call| {
+                        quote! {
+                            #[cfg(target_arch = "wasm32")]
+                            if let Some(location) = &program.#location_name.#location {
+                                gl.#call;
+                            }
+                        }
+                    });
+
+                quote! {
+                    #[cfg(not(target_arch = "wasm32"))]
+                    if let Some(location) = program.#location_name.#location {
+                        unsafe {
+                            gl.#program_uniform(program.name, location, #(#call_args),*);
+                        }
+                    }
+
+                    #web_upload
+                }
+            };
+
+            // Uniforms with an initializer can be reset to it
+            if initializer.is_some() {
+                let default_ident = format_ident!("default_{}", uniform.name.to_snake_case());
+                let upload = upload(quote! { value });
+
+                resets.push(quote! {
+                    {
+                        let value = #struct_name::#default_ident();
+                        #upload
+                    }
+                });
+            }
+
+            let upload = upload(quote! { self.#field });
+
+            uploads.push(quote! {
+                if self.__applied.#field != Some(self.#field) {
+                    #upload
+
+                    self.__applied.#field = Some(self.#field);
+                }
+            });
+        }
+    }
+
+    // Uniforms with initializers can be restored without a value struct
+    let reset = if resets.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            impl #struct_name {
+                /// Upload the default values of the uniforms which declare an initializer
+                ///
+                /// Initializers are applied when the program is linked, so this is only needed to
+                /// restore them after they have been changed. On WebGL, the program is bound while
+                /// uploading values, and the previously used program is restored afterwards.
+                pub fn reset_uniforms(&self, gl: &::tinygl::Context) {
+                    let program = self;
+                    let reset = || {
+                        #(#resets)*
+                    };
+
+                    #[cfg(target_arch = "wasm32")]
+                    gl.with_program(&program.name, reset);
+
+                    #[cfg(not(target_arch = "wasm32"))]
+                    reset();
+                }
+            }
+        }
+    };
+
+    quote! {
+        #reset
+
+        #[derive(Clone)]
+        pub struct #values_name {
+            #(#fields,)*
+            // Values uploaded by the last call to apply
+            __applied: #state_name,
+        }
+
+        #[derive(Clone, Default)]
+        struct #state_name {
+            #(#state_fields,)*
+        }
+
+        impl ::std::default::Default for #values_name {
+            fn default() -> Self {
+                Self {
+                    #(#defaults,)*
+                    __applied: ::std::default::Default::default(),
+                }
+            }
+        }
+
+        impl #values_name {
+            /// Upload the values that changed since the last call to apply
+            ///
+            /// The values are assumed to be applied to the same program every time. Call
+            /// `invalidate` before applying to another program, or after reloading it. On WebGL,
+            /// the program is bound while uploading values, and the previously used program is
+            /// restored afterwards.
+            pub fn apply(&mut self, gl: &::tinygl::Context, program: &#struct_name) {
+                #[cfg(target_arch = "wasm32")]
+                gl.with_program(&program.name, || self.upload(gl, program));
+
+                #[cfg(not(target_arch = "wasm32"))]
+                self.upload(gl, program);
+            }
+
+            fn upload(&mut self, gl: &::tinygl::Context, program: &#struct_name) {
+                #(#uploads)*
+            }
+
+            /// Forget about previously uploaded values, so the next call to apply uploads all of them
+            pub fn invalidate(&mut self) {
+                self.__applied = ::std::default::Default::default();
+            }
+        }
+    }
+}
@@ -2,14 +2,15 @@ use heck::SnakeCase;
 use quote::{format_ident, quote};
 
 use crate::{
-    codegen::types::{CodegenExt, UniformValueExt},
-    model::AsOutputFormat,
+    codegen::types::{uniform_array, CodegenExt, UniformValueExt, WebUniformExt},
+    model::{AsOutputFormat, GlslModule},
+    types::{GenericType, ItemOrArrayType},
     Error, WrappedShader, WrappedShaderDetails,
 };
 
-use super::WrappedItem;
+use super::{deprecated_attr, WrappedItem};
 
-fn filter_src<T: AsOutputFormat>(this: &WrappedShader<T>) -> String {
+fn filter_src(source: &GlslModule<'_>) -> String {
     use std::fmt::Write;
 
     // We skipped SPIR-V generation so just fix invalid stuff for OpenGL ES targets
@@ -17,7 +18,7 @@ fn filter_src<T: AsOutputFormat>(this: &WrappedShader<T>) -> String {
     // TODO: This is an ugly hack, maybe forbid skip_spirv + ES 3.00?
     let mut output = String::new();
 
-    for l in this.result().object().as_source().unwrap().as_str().lines() {
+    for l in source.as_str().lines() {
         if l.starts_with("#extension GL_GOOGLE_include_directive") {
             continue;
         } else if l.starts_with("#line") {
@@ -35,19 +36,24 @@ fn get_shader_tokens<T: AsOutputFormat>(
     this: &WrappedShader<T>,
 ) -> crate::Result<proc_macro2::TokenStream> {
     if this.prefer_spirv() && this.result().as_spirv().is_some() {
-        // Just write spv file
-        let res = syn::LitByteStr::new(
-            &this
-                .result()
-                .object()
-                .as_spirv()
-                .ok_or(Error::SpirVObjectRequired)?
-                .as_bytes_u8(),
-            proc_macro2::Span::call_site(),
-        );
+        // Just write spv file, without debug info if it was stripped
+        let res = if let Some(stripped) = this.stripped_spirv() {
+            let bytes: Vec<u8> = stripped.iter().flat_map(|w| w.to_ne_bytes()).collect();
+            syn::LitByteStr::new(&bytes, proc_macro2::Span::call_site())
+        } else {
+            syn::LitByteStr::new(
+                &this
+                    .result()
+                    .object()
+                    .as_spirv()
+                    .ok_or(Error::SpirVObjectRequired)?
+                    .as_bytes_u8(),
+                proc_macro2::Span::call_site(),
+            )
+        };
         Ok(quote! { #res })
     } else {
-        let out = filter_src(this);
+        let out = filter_src(this.source().ok_or(Error::SourceObjectRequired)?);
         Ok(quote! { #out })
     }
 }
@@ -56,10 +62,20 @@ fn get_shader_tokens<T: AsOutputFormat>(
 fn get_shader_tokens<T: AsOutputFormat>(
     this: &WrappedShader<T>,
 ) -> crate::Result<proc_macro2::TokenStream> {
-    let out = filter_src(this);
+    let out = filter_src(this.source().ok_or(Error::SourceObjectRequired)?);
     Ok(quote! { #out })
 }
 
+/// FNV-1a hash of the given parts, which unlike the std hashers is stable across Rust versions
+fn source_hash(parts: &[&str]) -> u64 {
+    parts
+        .iter()
+        .flat_map(|part| part.bytes().chain(std::iter::once(0)))
+        .fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+}
+
 fn to_cstr(s: &str) -> proc_macro2::TokenStream {
     let mut s_null_terminated = s.to_string();
     s_null_terminated.push('\0');
@@ -69,9 +85,17 @@ fn to_cstr(s: &str) -> proc_macro2::TokenStream {
 }
 
 impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
+    fn module_path(&self) -> &[String] {
+        WrappedShaderDetails::module_path(self)
+    }
+
     fn generate(&self) -> crate::Result<proc_macro2::TokenStream> {
         let shader_tokens = get_shader_tokens(self)?;
-        let is_source = !self.prefer_spirv() && self.result().as_source().is_some();
+        let is_source = !self.prefer_spirv() && self.source().is_some();
+        let fallback_source = self
+            .fallback_source()
+            .filter(|_| !is_source)
+            .map(filter_src);
 
         // Shader resource structure
         let struct_name = format_ident!("{}", self.shader_struct_name());
@@ -79,19 +103,31 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
             format_ident!("{}", self.result().object().info().kind.constant_name());
         let st: syn::Type = syn::parse_str(if is_source {
             "::tinygl::wrappers::SourceShader"
+        } else if fallback_source.is_some() {
+            "::tinygl::wrappers::FallbackShader"
         } else {
             "::tinygl::wrappers::BinaryShader"
         })
         .unwrap();
 
+        // Hash of everything that ends up in the shader object, for program binary caches
+        let source_hash = source_hash(&[
+            &kind_constant_name.to_string(),
+            &shader_tokens.to_string(),
+            fallback_source.as_deref().unwrap_or(""),
+        ]);
+
         let mut parts = Vec::new();
 
         parts.push(quote! {
             pub struct #struct_name {
-                name: tinygl::gl::Shader,
+                name: ::tinygl::gl::Shader,
             }
 
             impl #struct_name {
+                /// Hash of the embedded shader, used as a key by program binary caches
+                pub const SOURCE_HASH: u64 = #source_hash;
+
                 pub fn build(gl: &::tinygl::Context) -> ::tinygl::Result<Self> {
                     Ok(Self {
                         name: <Self as #st>::build(gl, ::tinygl::gl::#kind_constant_name)?
@@ -104,15 +140,26 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
                     ::tinygl::gl::#kind_constant_name
                 }
 
-                fn name(&self) -> ::tinygl::gl::Shader {
+                #[cfg(not(target_arch = "wasm32"))]
+                fn name(&self) -> ::tinygl::gl::ShaderName {
                     self.name
                 }
+
+                #[cfg(target_arch = "wasm32")]
+                fn name(&self) -> ::tinygl::gl::ShaderName {
+                    &self.name
+                }
             }
 
             impl ::tinygl::wrappers::GlDrop for #struct_name {
+                #[cfg(not(target_arch = "wasm32"))]
+                unsafe fn drop(&mut self, gl: &::tinygl::Context) {
+                    gl.delete_shader(self.name);
+                }
+
+                #[cfg(target_arch = "wasm32")]
                 unsafe fn drop(&mut self, gl: &::tinygl::Context) {
-                    use ::tinygl::wrappers::ShaderCommon;
-                    gl.delete_shader(self.name());
+                    gl.delete_shader(Some(&self.name));
                 }
             }
         });
@@ -126,6 +173,19 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
                     }
                 }
             });
+        } else if let Some(fallback_source) = fallback_source {
+            // The fallback source declares the same uniform locations as the binary
+            parts.push(quote! {
+                impl ::tinygl::wrappers::FallbackShader<'static> for #struct_name {
+                    fn get_binary() -> &'static [u8] {
+                        #shader_tokens
+                    }
+
+                    fn get_source() -> &'static str {
+                        #fallback_source
+                    }
+                }
+            });
         } else {
             parts.push(quote! {
                 impl ::tinygl::wrappers::BinaryShader<'static> for #struct_name {
@@ -136,6 +196,32 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
             });
         }
 
+        // Write the reload method for debug builds
+        if let Some(reload_info) = self.reload_info() {
+            let source_path = reload_info.source_path.to_string_lossy();
+            let includes = reload_info.includes.iter().map(|p| p.to_string_lossy());
+
+            parts.push(quote! {
+                #[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+                impl #struct_name {
+                    /// Path to the source of this shader
+                    pub const SOURCE_PATH: &'static str = #source_path;
+                    /// Paths to the files included by the source of this shader
+                    pub const INCLUDES: &'static [&'static str] = &[#(#includes),*];
+
+                    /// Return the paths to all the files this shader is built from, for watching
+                    /// them for changes
+                    pub fn source_paths() -> impl Iterator<Item = &'static str> {
+                        ::std::iter::once(Self::SOURCE_PATH).chain(Self::INCLUDES.iter().copied())
+                    }
+
+                    pub fn reload(gl: &::tinygl::Context) -> ::tinygl::Result<::tinygl::wrappers::RuntimeShader> {
+                        ::tinygl::wrappers::RuntimeShader::build_path(gl, Self::SOURCE_PATH, ::tinygl::gl::#kind_constant_name)
+                    }
+                }
+            });
+        }
+
         // Write struct for holding uniform locations
         let struct_name = format_ident!("{}", self.uniform_struct_name());
         let uniform_location_name: Vec<_> = self
@@ -147,7 +233,7 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
         parts.push(quote! {
             #[derive(Default)]
             pub struct #struct_name {
-                #(#uniform_location_name: Option<::tinygl::gl::UniformLocation>,)*
+                #(pub(crate) #uniform_location_name: Option<::tinygl::gl::UniformLocation>,)*
             }
         });
 
@@ -174,13 +260,28 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
             .collect::<Vec<_>>();
 
         methods.push(quote! {
-            pub fn new(gl: &::tinygl::Context, program: ::tinygl::gl::Program) -> Self {
+            #[cfg(not(target_arch = "wasm32"))]
+            pub fn new(gl: &::tinygl::Context, program: ::tinygl::gl::ProgramName) -> Self {
                 Self {
                     #(#uset),*
                 }
             }
         });
 
+        // WebGL only supports source shaders, and has no explicit uniform locations
+        if is_source {
+            let uniform_name = self.uniforms().iter().map(|uniform| uniform.name.as_str());
+
+            methods.push(quote! {
+                #[cfg(target_arch = "wasm32")]
+                pub fn new(gl: &::tinygl::Context, program: ::tinygl::gl::ProgramName) -> Self {
+                    Self {
+                        #(#uniform_location_name: gl.get_uniform_location(program, #uniform_name)),*
+                    }
+                }
+            });
+        }
+
         // Write getter/setter methods
         methods.extend(self.uniforms().iter().flat_map(|uniform| {
             let mut res = Vec::new();
@@ -211,6 +312,7 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
 
             let meth_ident = format_ident!("set_{}", uniform.name.to_snake_case());
             let location = format_ident!("{}", uniform.location_name());
+            let deprecated = deprecated_attr(self, uniform);
             let program_uniform = format_ident!("program_uniform{}", ty.uniform_method_name());
             let mut meth_args = vec![
                 quote! { value: #type_name }
@@ -227,13 +329,29 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
                 call_args.push(quote! { #count });
             }
 
-            if let Some(extra) = ty.uniform_method_extra_args_no_ty() {
-                call_args.push(extra);
-            }
+            // Extra arguments need to be converted to their GL type
+            call_args.extend(ty.uniform_method_extra_args().iter().map(|extra| {
+                let val: syn::Expr = syn::parse_str(extra.val).unwrap();
+                quote! { #val }
+            }));
+
+            // Matrices without a transpose argument are column-major
+            let is_matrix = matches!(ty, ItemOrArrayType::Item(GenericType::Matrix(_)) | ItemOrArrayType::Array(GenericType::Matrix(_), _));
+            let transpose = if ty.uniform_method_extra_args().is_empty() {
+                if is_matrix {
+                    call_args.push(quote! { 0 });
+                }
+
+                quote! { false }
+            } else {
+                quote! { transpose }
+            };
 
             call_args.push(ty.uniform_value(&format_ident!("value")));
 
             res.push(quote! {
+                #deprecated
+                #[cfg(not(target_arch = "wasm32"))]
                 pub fn #meth_ident(&self, gl: &::tinygl::Context, program: ::tinygl::gl::ProgramName, #(#meth_args),*) {
                     if let Some(location) = self.#location {
                         unsafe {
@@ -243,6 +361,30 @@ impl<T: AsOutputFormat> WrappedItem for WrappedShader<T> {
                 }
             });
 
+            // WebGL has no program_uniform* functions, so the program has to be bound while setting
+            let web_value = match ty {
+                ItemOrArrayType::Item(GenericType::Atom(_))
+                | ItemOrArrayType::Image { .. }
+                | ItemOrArrayType::Array(_, _) => {
+                    quote! { value }
+                }
+                _ => uniform_array(&format_ident!("value")),
+            };
+
+            if let Some(call) =
+                ty.web_uniform_call(quote! { Some(location) }, web_value, transpose)
+            {
+                res.push(quote! {
+                    #deprecated
+                    #[cfg(target_arch = "wasm32")]
+                    pub fn #meth_ident(&self, gl: &::tinygl::Context, program: ::tinygl::gl::ProgramName, #(#meth_args),*) {
+                        if let Some(location) = &self.#location {
+                            gl.with_program(program, || gl.#call);
+                        }
+                    }
+                });
+            }
+
             res
         }));
 
@@ -1,20 +1,36 @@
 mod codegen_ext;
 pub use codegen_ext::*;
 
-use quote::quote;
+use quote::{format_ident, quote, ToTokens};
 
-use crate::types::{AtomType, GenericType, ItemOrArrayType};
+use crate::types::{AtomType, ConstantValue, GenericType, ItemOrArrayType};
 
 pub trait UniformValueExt {
     fn uniform_value(&self, name: &syn::Ident) -> proc_macro2::TokenStream;
 }
 
+/// Generate an expression borrowing the array value of a vector or matrix setter argument
+pub fn uniform_array(name: &syn::Ident) -> proc_macro2::TokenStream {
+    if cfg!(feature = "uniform-value") {
+        quote! { &::tinygl::wrappers::UniformValue::uniform_value(#name) }
+    } else {
+        quote! { #name.as_ref() }
+    }
+}
+
 impl UniformValueExt for GenericType {
     fn uniform_value(&self, name: &syn::Ident) -> proc_macro2::TokenStream {
         match self {
+            Self::Atom(AtomType::Bool) => quote! { #name as i32 },
             Self::Atom(_) => quote! { #name },
-            Self::Vector(_) => quote! { #name.as_ref().as_ptr() },
-            Self::Matrix(_) => quote! { #name.as_ref().as_ptr() as *const _ },
+            Self::Vector(_) => {
+                let array = uniform_array(name);
+                quote! { (#array).as_ptr() }
+            }
+            Self::Matrix(_) => {
+                let array = uniform_array(name);
+                quote! { (#array).as_ptr() as *const _ }
+            }
         }
     }
 }
@@ -23,8 +39,223 @@ impl UniformValueExt for ItemOrArrayType {
     fn uniform_value(&self, name: &syn::Ident) -> proc_macro2::TokenStream {
         match self {
             Self::Item(inner) => inner.uniform_value(name),
-            Self::Array(_, _) => quote! { #name.as_ref().as_ptr() },
-            Self::Image { .. } => GenericType::Atom(AtomType::UInt).uniform_value(name),
+            // Booleans are uploaded as integers, which are wider
+            Self::Array(GenericType::Atom(AtomType::Bool), _) => {
+                quote! { #name.map(|value| value as i32).as_ptr() }
+            }
+            Self::Array(_, _) => quote! { #name.as_ptr() as *const _ },
+            Self::Image { .. } => quote! { #name as i32 },
+        }
+    }
+}
+
+pub trait ConstantValueExt {
+    /// Generate a Rust expression for a constant of this type
+    ///
+    /// `components` are the scalar components of the constant, in column-major order. Returns
+    /// `None` if they don't match this type.
+    fn constant_value(&self, components: &[ConstantValue]) -> Option<proc_macro2::TokenStream>;
+}
+
+fn constant_component(component: ConstantValue) -> proc_macro2::TokenStream {
+    use proc_macro2::Literal;
+
+    match component {
+        ConstantValue::Int(value) => Literal::i32_suffixed(value).into_token_stream(),
+        ConstantValue::UInt(value) => Literal::u32_suffixed(value).into_token_stream(),
+        ConstantValue::Bool(value) => quote! { #value },
+        ConstantValue::Float(value) if value.is_nan() => quote! { ::std::f32::NAN },
+        ConstantValue::Float(value) if value.is_infinite() && value > 0. => {
+            quote! { ::std::f32::INFINITY }
+        }
+        ConstantValue::Float(value) if value.is_infinite() => quote! { ::std::f32::NEG_INFINITY },
+        ConstantValue::Float(value) => Literal::f32_suffixed(value).into_token_stream(),
+        ConstantValue::Double(value) if value.is_nan() => quote! { ::std::f64::NAN },
+        ConstantValue::Double(value) if value.is_infinite() && value > 0. => {
+            quote! { ::std::f64::INFINITY }
+        }
+        ConstantValue::Double(value) if value.is_infinite() => {
+            quote! { ::std::f64::NEG_INFINITY }
+        }
+        ConstantValue::Double(value) => Literal::f64_suffixed(value).into_token_stream(),
+    }
+}
+
+/// Generate a `&[::tinygl::wrappers::UniformComponent]` expression for the components of a
+/// constant
+pub fn uniform_components(components: &[ConstantValue]) -> proc_macro2::TokenStream {
+    let components = components.iter().map(|component| {
+        let variant = format_ident!(
+            "{}",
+            match component {
+                ConstantValue::Int(_) => "Int",
+                ConstantValue::Float(_) => "Float",
+                ConstantValue::Double(_) => "Double",
+                ConstantValue::UInt(_) => "UInt",
+                ConstantValue::Bool(_) => "Bool",
+            }
+        );
+        let value = constant_component(*component);
+
+        quote! { ::tinygl::wrappers::UniformComponent::#variant(#value) }
+    });
+
+    quote! { &[#(#components),*] }
+}
+
+impl ConstantValueExt for GenericType {
+    fn constant_value(&self, components: &[ConstantValue]) -> Option<proc_macro2::TokenStream> {
+        if components.len() != self.component_count() as usize {
+            return None;
+        }
+
+        let values: Vec<_> = components.iter().copied().map(constant_component).collect();
+
+        Some(match self {
+            Self::Atom(_) => values[0].clone(),
+            Self::Vector(_) => quote! { [#(#values),*] },
+            Self::Matrix(matrix) => {
+                let columns = values.chunks(matrix.n as usize).map(|column| {
+                    quote! { [#(#column),*] }
+                });
+
+                quote! { [#(#columns),*] }
+            }
+        })
+    }
+}
+
+impl ConstantValueExt for ItemOrArrayType {
+    fn constant_value(&self, components: &[ConstantValue]) -> Option<proc_macro2::TokenStream> {
+        match self {
+            Self::Item(inner) => inner.constant_value(components),
+            // Initializers are only reflected for plain values
+            Self::Array(_, _) | Self::Image { .. } => None,
+        }
+    }
+}
+
+pub trait WebUniformExt {
+    /// Generate a WebGL call setting a uniform value
+    ///
+    /// `value` is the value for scalar uniforms, and a reference to an array for vectors and
+    /// matrices. Returns `None` if the type is not supported by WebGL.
+    fn web_uniform_call(
+        &self,
+        location: proc_macro2::TokenStream,
+        value: proc_macro2::TokenStream,
+        transpose: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream>;
+
+    /// Return the attribute restricting methods for this type to the targets which support it
+    fn target_cfg(&self) -> proc_macro2::TokenStream {
+        if self
+            .web_uniform_call(quote! {}, quote! {}, quote! {})
+            .is_some()
+        {
+            quote! {}
+        } else {
+            quote! { #[cfg(not(target_arch = "wasm32"))] }
+        }
+    }
+}
+
+fn web_suffix(atom: AtomType) -> Option<(&'static str, &'static str)> {
+    match atom {
+        AtomType::Float => Some(("f", "f32")),
+        AtomType::Int => Some(("i", "i32")),
+        AtomType::UInt => Some(("ui", "u32")),
+        // No doubles in GLSL ES, and boolean vectors can't be passed as arrays
+        AtomType::Double | AtomType::Bool => None,
+    }
+}
+
+impl WebUniformExt for GenericType {
+    fn web_uniform_call(
+        &self,
+        location: proc_macro2::TokenStream,
+        value: proc_macro2::TokenStream,
+        transpose: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        match self {
+            Self::Atom(AtomType::Bool) => Some(quote! { uniform1i(#location, (#value) as i32) }),
+            Self::Atom(atom) => {
+                let method = format_ident!("uniform1{}", web_suffix(*atom)?.0);
+                Some(quote! { #method(#location, #value) })
+            }
+            Self::Vector(vector) => {
+                let (suffix, ty) = web_suffix(vector.base_type)?;
+                let method =
+                    format_ident!("uniform{}{}v_with_{}_array", vector.components, suffix, ty);
+                Some(quote! { #method(#location, #value) })
+            }
+            Self::Matrix(matrix) => {
+                if matrix.base_type != AtomType::Float {
+                    return None;
+                }
+
+                let method = format_ident!("uniform_matrix{}fv_with_f32_array", matrix.n);
+                let len = (matrix.n * matrix.n) as usize;
+                Some(quote! {
+                    #method(#location, #transpose, unsafe {
+                        ::std::slice::from_raw_parts((#value).as_ptr() as *const f32, #len)
+                    })
+                })
+            }
+        }
+    }
+}
+
+impl WebUniformExt for ItemOrArrayType {
+    fn web_uniform_call(
+        &self,
+        location: proc_macro2::TokenStream,
+        value: proc_macro2::TokenStream,
+        transpose: proc_macro2::TokenStream,
+    ) -> Option<proc_macro2::TokenStream> {
+        match self {
+            Self::Item(inner) => inner.web_uniform_call(location, value, transpose),
+            Self::Array(item, size) => {
+                // Arrays are passed as flat slices of their scalar type
+                let (method, ty, len, transpose) = match item {
+                    GenericType::Atom(atom) => {
+                        let (suffix, ty) = web_suffix(*atom)?;
+                        (
+                            format_ident!("uniform1{}v_with_{}_array", suffix, ty),
+                            ty,
+                            1,
+                            None,
+                        )
+                    }
+                    GenericType::Vector(vector) => {
+                        let (suffix, ty) = web_suffix(vector.base_type)?;
+                        let method = format_ident!(
+                            "uniform{}{}v_with_{}_array",
+                            vector.components,
+                            suffix,
+                            ty
+                        );
+                        (method, ty, vector.components, None)
+                    }
+                    GenericType::Matrix(matrix) if matrix.base_type == AtomType::Float => {
+                        let method = format_ident!("uniform_matrix{}fv_with_f32_array", matrix.n);
+                        (method, "f32", matrix.n * matrix.n, Some(transpose))
+                    }
+                    GenericType::Matrix(_) => return None,
+                };
+
+                let ty = format_ident!("{}", ty);
+                let len = (len * size) as usize;
+                let transpose = transpose.map(|transpose| quote! { #transpose, });
+
+                Some(quote! {
+                    #method(#location, #transpose unsafe {
+                        ::std::slice::from_raw_parts((#value).as_ptr() as *const #ty, #len)
+                    })
+                })
+            }
+            // Samplers are set using integers in WebGL
+            Self::Image { .. } => Some(quote! { uniform1i(#location, (#value) as i32) }),
         }
     }
 }
@@ -6,6 +6,15 @@ use crate::types::{AtomType, GenericType, ItemOrArrayType, MatrixType, VectorTyp
 // TODO: bvec mapping is broken
 // TODO: dvec mapping is broken
 
+/// Trait accepted by the setters of vector and matrix uniforms
+fn value_trait() -> &'static str {
+    if cfg!(feature = "uniform-value") {
+        "::tinygl::wrappers::UniformValue"
+    } else {
+        "::std::convert::AsRef"
+    }
+}
+
 pub struct ExtraArg {
     pub name: &'static str,
     pub ty: &'static str,
@@ -15,8 +24,8 @@ pub struct ExtraArg {
 pub trait CodegenExt {
     fn glsl_base_type(&self) -> &'static str;
     fn glsl_vec_name(&self) -> String;
-    fn glsl_mat_name(&self) -> String;
     fn rust_value_type(&self) -> String;
+    fn rust_storage_type(&self) -> String;
     fn rust_primitive_type(&self) -> &'static str;
     fn uniform_method_name(&self) -> String;
     fn uniform_method_extra_args(&self) -> &[ExtraArg];
@@ -63,11 +72,11 @@ impl CodegenExt for AtomType {
         AtomType::glsl_vec_name(self)
     }
 
-    fn glsl_mat_name(&self) -> String {
-        AtomType::glsl_mat_name(self)
+    fn rust_value_type(&self) -> String {
+        self.rust_primitive_type().into()
     }
 
-    fn rust_value_type(&self) -> String {
+    fn rust_storage_type(&self) -> String {
         self.rust_primitive_type().into()
     }
 
@@ -113,18 +122,23 @@ impl CodegenExt for VectorType {
         VectorType::glsl_vec_name(self)
     }
 
-    fn glsl_mat_name(&self) -> String {
-        panic!("cannot format a vector as a matrix")
-    }
-
     fn rust_value_type(&self) -> String {
         format!(
-            "impl ::std::convert::AsRef<[{}; {}]>",
+            "impl {}<[{}; {}]>",
+            value_trait(),
             self.base_type.rust_value_type(),
             self.components,
         )
     }
 
+    fn rust_storage_type(&self) -> String {
+        format!(
+            "[{}; {}]",
+            self.base_type.rust_storage_type(),
+            self.components
+        )
+    }
+
     fn rust_primitive_type(&self) -> &'static str {
         self.base_type.rust_primitive_type()
     }
@@ -162,18 +176,23 @@ impl CodegenExt for MatrixType {
         panic!("cannot format a matrix as a vector")
     }
 
-    fn glsl_mat_name(&self) -> String {
-        MatrixType::glsl_mat_name(self)
-    }
-
     fn rust_value_type(&self) -> String {
         format!(
-            "impl ::std::convert::AsRef<[[{ty}; {n}]; {n}]>",
+            "impl {trait_name}<[[{ty}; {n}]; {n}]>",
+            trait_name = value_trait(),
             ty = self.base_type.rust_value_type(),
             n = self.n,
         )
     }
 
+    fn rust_storage_type(&self) -> String {
+        format!(
+            "[[{ty}; {n}]; {n}]",
+            ty = self.base_type.rust_storage_type(),
+            n = self.n,
+        )
+    }
+
     fn rust_primitive_type(&self) -> &'static str {
         self.base_type.rust_primitive_type()
     }
@@ -192,6 +211,11 @@ impl CodegenExt for MatrixType {
     }
 
     fn uniform_method_extra_args(&self) -> &[ExtraArg] {
+        // UniformValue matrices are column-major, so they are never transposed
+        if cfg!(feature = "uniform-value") {
+            return &[];
+        }
+
         &[ExtraArg {
             name: "transpose",
             ty: "bool",
@@ -221,14 +245,6 @@ impl CodegenExt for GenericType {
         }
     }
 
-    fn glsl_mat_name(&self) -> String {
-        match self {
-            Self::Atom(atom) => atom.glsl_mat_name(),
-            Self::Vector(vector) => vector.glsl_mat_name(),
-            Self::Matrix(matrix) => matrix.glsl_mat_name(),
-        }
-    }
-
     fn rust_value_type(&self) -> String {
         match self {
             Self::Atom(atom) => atom.rust_value_type(),
@@ -237,6 +253,14 @@ impl CodegenExt for GenericType {
         }
     }
 
+    fn rust_storage_type(&self) -> String {
+        match self {
+            Self::Atom(atom) => atom.rust_storage_type(),
+            Self::Vector(vector) => vector.rust_storage_type(),
+            Self::Matrix(matrix) => matrix.rust_storage_type(),
+        }
+    }
+
     fn rust_primitive_type(&self) -> &'static str {
         match self {
             Self::Atom(atom) => atom.rust_primitive_type(),
@@ -287,19 +311,19 @@ impl CodegenExt for ItemOrArrayType {
         }
     }
 
-    fn glsl_mat_name(&self) -> String {
+    fn rust_value_type(&self) -> String {
         match self {
-            Self::Item(item) => item.glsl_mat_name(),
-            Self::Array(item, _size) => item.glsl_mat_name(),
-            Self::Image { .. } => "image".to_owned(),
+            Self::Item(item) => item.rust_value_type(),
+            Self::Array(item, size) => format!("&[{}; {}]", item.rust_storage_type(), size),
+            Self::Image { .. } => GenericType::Atom(AtomType::UInt).rust_value_type(),
         }
     }
 
-    fn rust_value_type(&self) -> String {
+    fn rust_storage_type(&self) -> String {
         match self {
-            Self::Item(item) => item.rust_value_type(),
-            Self::Array(item, size) => format!("&[{}; {}]", item.glsl_mat_name(), size),
-            Self::Image { .. } => GenericType::Atom(AtomType::UInt).rust_value_type(),
+            Self::Item(item) => item.rust_storage_type(),
+            Self::Array(item, size) => format!("[{}; {}]", item.rust_storage_type(), size),
+            Self::Image { .. } => GenericType::Atom(AtomType::UInt).rust_storage_type(),
         }
     }
 
@@ -314,8 +338,13 @@ impl CodegenExt for ItemOrArrayType {
     fn uniform_method_name(&self) -> String {
         match self {
             Self::Item(item) => item.uniform_method_name(),
+            // Scalar arrays use the vector variant of the method
+            Self::Array(GenericType::Atom(atom), _size) => {
+                format!("{}v", atom.uniform_method_name())
+            }
             Self::Array(item, _size) => item.uniform_method_name(),
-            Self::Image { .. } => GenericType::Atom(AtomType::UInt).uniform_method_name(),
+            // Samplers and images can only be set using signed integers
+            Self::Image { .. } => GenericType::Atom(AtomType::Int).uniform_method_name(),
         }
     }
 
@@ -1,10 +1,17 @@
-use crate::{model::ShaderObject, reflect::ReflectedObject, Error, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::{
+    model::{AsOutputFormat, GlslModule, Lint, LintWarning, ShaderObject},
+    reflect::ReflectedObject,
+    Error, Result, ShaderKind,
+};
 
 #[cfg(feature = "shaderc")]
 mod shaderc_compiler;
 
 mod target_type;
-pub use target_type::{GlslVersion, TargetType};
+pub use target_type::{GlslProfile, GlslVersion, HlslShaderModel, MslVersion, TargetType};
 
 mod uniform_set;
 pub use uniform_set::*;
@@ -15,13 +22,60 @@ pub use wrapped_shader::*;
 mod wrapped_program;
 pub use wrapped_program::*;
 
-pub type IncludeCallback = Box<dyn FnMut(&std::path::Path) -> ()>;
+/// Action taken when a wrapped shader's source is not compatible with the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IncompatibleSourceMode {
+    /// Fail with `Error::IncompatibleSource`
+    Error,
+    /// Transpile the SPIR-V representation of the shader to the target version, if available
+    Transpile,
+    /// Embed the source as-is
+    Ignore,
+}
+
+/// Action taken when a lint finds a portability problem in a shader source
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LintLevel {
+    /// Ignore the problem
+    Allow,
+    /// Report the problem as a cargo warning
+    Warn,
+    /// Fail with `Error::LintFailed`
+    Deny,
+}
+
+/// Treatment of uniforms which are not statically used by their shader
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnusedUniformMode {
+    /// Generate setters as usual
+    Keep,
+    /// Generate setters marked as `#[deprecated]`
+    Deprecate,
+    /// Don't generate setters
+    Omit,
+}
+
+pub type IncludeCallback = Box<dyn FnMut(&std::path::Path) + Send>;
 
 #[cfg_attr(not(feature = "backend-shaderc"), allow(dead_code))]
 pub struct Compiler {
     pub(crate) skip_cargo: bool,
     output_type: TargetType,
-    include_callback: Option<std::rc::Rc<std::cell::RefCell<IncludeCallback>>>,
+    include_callback: Option<IncludeCallback>,
+    hot_reload: bool,
+    strip_debug_info: bool,
+    shader_root: Option<PathBuf>,
+    glsl_profile: GlslProfile,
+    incompatible_source_mode: IncompatibleSourceMode,
+    unused_uniform_mode: UnusedUniformMode,
+    parallel_jobs: Option<usize>,
+    spirv_fallback: Option<GlslVersion>,
+    #[cfg(feature = "transpile")]
+    transpile_options: Option<crate::model::TranspileOptions>,
+    minify_source: bool,
+    lint_target: Option<GlslVersion>,
+    lint_levels: HashMap<Lint, LintLevel>,
+    strict_annotations: bool,
 }
 
 impl Compiler {
@@ -70,19 +124,40 @@ impl Compiler {
                         TargetType::Glsl(version)
                     }
                 }
+                // Other APIs only make sense when exporting shaders from a native build
+                TargetType::Hlsl(_) | TargetType::Msl(_) => {
+                    if is_wasm {
+                        return Err(Error::InvalidTargetType(specific));
+                    } else {
+                        specific
+                    }
+                }
             },
         };
 
         Ok(Self {
             skip_cargo,
             output_type,
-            include_callback: include_callback
-                .map(|cb| std::rc::Rc::new(std::cell::RefCell::new(cb))),
+            include_callback,
+            hot_reload: false,
+            strip_debug_info: false,
+            shader_root: None,
+            glsl_profile: GlslProfile::Core,
+            incompatible_source_mode: IncompatibleSourceMode::Error,
+            unused_uniform_mode: UnusedUniformMode::Keep,
+            parallel_jobs: None,
+            spirv_fallback: None,
+            #[cfg(feature = "transpile")]
+            transpile_options: None,
+            minify_source: false,
+            lint_target: None,
+            lint_levels: HashMap::new(),
+            strict_annotations: false,
         })
     }
 
     #[cfg(feature = "shaderc")]
-    pub fn with_shaderc(self) -> shaderc_compiler::CompilerWithShaderc {
+    pub fn with_shaderc(self) -> Result<shaderc_compiler::CompilerWithShaderc> {
         shaderc_compiler::CompilerWithShaderc::new(self)
     }
 
@@ -90,12 +165,574 @@ impl Compiler {
         self.output_type
     }
 
-    pub fn wrap_shader<'s, T>(
+    /// Return `true` if wrapped shaders record their sources for reloading at runtime
+    pub fn hot_reload(&self) -> bool {
+        self.hot_reload
+    }
+
+    /// Enable or disable hot reloading support in generated code
+    ///
+    /// When enabled, shaders loaded from files record their source path and included files, and
+    /// debug builds of the generated code get `reload` methods which recompile them at runtime.
+    ///
+    /// # Parameters
+    ///
+    /// * `hot_reload`: `true` to record sources for wrapped shaders
+    pub fn set_hot_reload(&mut self, hot_reload: bool) {
+        self.hot_reload = hot_reload;
+    }
+
+    /// Return `true` if wrapped shaders embed SPIR-V binaries without debug info
+    pub fn strip_debug_info(&self) -> bool {
+        self.strip_debug_info
+    }
+
+    /// Enable or disable stripping SPIR-V debug info from wrapped shaders
+    ///
+    /// When enabled, OpName, OpSource, OpLine, OpString and related instructions are removed
+    /// from the binaries embedded in the generated code, after reflection has run. Shaders which
+    /// embed their source instead of their binary are left alone.
+    ///
+    /// # Parameters
+    ///
+    /// * `strip_debug_info`: `true` to strip debug info from wrapped shaders
+    pub fn set_strip_debug_info(&mut self, strip_debug_info: bool) {
+        self.strip_debug_info = strip_debug_info;
+    }
+
+    /// Return the root directory used for organizing generated code into modules
+    pub fn shader_root(&self) -> Option<&Path> {
+        self.shader_root.as_deref()
+    }
+
+    /// Set the root directory of the shader sources
+    ///
+    /// When set, the generated code of each shader is placed in its own module, in parent modules
+    /// which mirror the directory layout relative to this directory, so shaders with the same file
+    /// name in different directories don't collide. Shaders outside of this directory get their
+    /// module at the root. When not set, all shaders are generated at the root.
+    ///
+    /// # Parameters
+    ///
+    /// * `shader_root`: path to the shader sources directory
+    pub fn set_shader_root(&mut self, shader_root: impl AsRef<Path>) -> Result<()> {
+        // Shader objects loaded from files have canonical paths
+        self.shader_root = Some(std::fs::canonicalize(shader_root.as_ref())?);
+        Ok(())
+    }
+
+    /// Return the profile of the OpenGL contexts targeted by desktop GLSL sources
+    pub fn glsl_profile(&self) -> GlslProfile {
+        self.glsl_profile
+    }
+
+    /// Set the profile of the OpenGL contexts targeted by desktop GLSL sources
+    ///
+    /// Sources declaring the compatibility profile are not compatible with core targets.
+    ///
+    /// # Parameters
+    ///
+    /// * `glsl_profile`: profile of the target contexts
+    pub fn set_glsl_profile(&mut self, glsl_profile: GlslProfile) {
+        self.glsl_profile = glsl_profile;
+    }
+
+    /// Return the action taken for sources which are not compatible with the target
+    pub fn incompatible_source_mode(&self) -> IncompatibleSourceMode {
+        self.incompatible_source_mode
+    }
+
+    /// Set the action taken for sources which are not compatible with the target
+    ///
+    /// When wrapping a shader which embeds its GLSL source, the version, profile and extensions
+    /// declared by the source are checked against the GLSL target of this compiler.
+    ///
+    /// # Parameters
+    ///
+    /// * `incompatible_source_mode`: action for incompatible sources
+    pub fn set_incompatible_source_mode(
         &mut self,
-        shader: ReflectedObject<ShaderObject<T>>,
+        incompatible_source_mode: IncompatibleSourceMode,
+    ) {
+        self.incompatible_source_mode = incompatible_source_mode;
+    }
+
+    /// Return the treatment of uniforms which are not used by their shader
+    pub fn unused_uniform_mode(&self) -> UnusedUniformMode {
+        self.unused_uniform_mode
+    }
+
+    /// Set the treatment of uniforms which are not used by their shader
+    ///
+    /// Usage is found by analyzing the SPIR-V representation of shaders, so it is unknown for
+    /// GLSL-only shaders. Unused uniforms are reported as build warnings in any case.
+    ///
+    /// # Parameters
+    ///
+    /// * `unused_uniform_mode`: treatment of unused uniforms in generated code
+    pub fn set_unused_uniform_mode(&mut self, unused_uniform_mode: UnusedUniformMode) {
+        self.unused_uniform_mode = unused_uniform_mode;
+    }
+
+    /// Return the number of threads used to compile batches of shaders
+    ///
+    /// Defaults to the number of jobs of the cargo build, or the available parallelism.
+    pub fn parallel_jobs(&self) -> usize {
+        self.parallel_jobs.unwrap_or_else(|| {
+            std::env::var("NUM_JOBS")
+                .ok()
+                .and_then(|jobs| jobs.parse().ok())
+                .or_else(|| std::thread::available_parallelism().ok().map(Into::into))
+                .unwrap_or(1)
+        })
+    }
+
+    /// Set the number of threads used to compile batches of shaders
+    ///
+    /// # Parameters
+    ///
+    /// * `parallel_jobs`: number of threads, or `None` for the default
+    pub fn set_parallel_jobs(&mut self, parallel_jobs: Option<usize>) {
+        self.parallel_jobs = parallel_jobs;
+    }
+
+    /// Return the GLSL version of the sources embedded next to SPIR-V binaries, if any
+    pub fn spirv_fallback(&self) -> Option<GlslVersion> {
+        self.spirv_fallback
+    }
+
+    /// Set the GLSL version of the sources embedded next to SPIR-V binaries
+    ///
+    /// When set, shaders wrapped with their SPIR-V representation also embed a GLSL source
+    /// transpiled from it. At runtime, the SPIR-V binary is loaded if the context supports it,
+    /// and the source is compiled otherwise. The version has to support explicit uniform
+    /// locations, i.e. 4.30 or later, so both representations use the same locations.
+    ///
+    /// # Parameters
+    ///
+    /// * `spirv_fallback`: version of the fallback sources, or `None` to only embed SPIR-V
+    pub fn set_spirv_fallback(&mut self, spirv_fallback: Option<GlslVersion>) {
+        self.spirv_fallback = spirv_fallback;
+    }
+
+    /// Return the options used when transpiling SPIR-V to GLSL, if set
+    #[cfg(feature = "transpile")]
+    pub fn transpile_options(&self) -> Option<&crate::model::TranspileOptions> {
+        self.transpile_options.as_ref()
+    }
+
+    /// Set the options used when transpiling SPIR-V to GLSL
+    ///
+    /// The options apply to sources transpiled in `IncompatibleSourceMode::Transpile` mode and to
+    /// SPIR-V fallbacks, with their `version` replaced by the version targeted by each. Fallbacks
+    /// never flatten uniform blocks, since they have to declare the same uniforms as the binary.
+    ///
+    /// # Parameters
+    ///
+    /// * `transpile_options`: transpile options, or `None` for the defaults of each version
+    #[cfg(feature = "transpile")]
+    pub fn set_transpile_options(
+        &mut self,
+        transpile_options: Option<crate::model::TranspileOptions>,
+    ) {
+        self.transpile_options = transpile_options;
+    }
+
+    /// Return the transpile options for a given target version
+    #[cfg(feature = "transpile")]
+    fn transpile_options_for(&self, version: GlslVersion) -> crate::model::TranspileOptions {
+        match &self.transpile_options {
+            Some(options) => crate::model::TranspileOptions {
+                version,
+                ..options.clone()
+            },
+            None => crate::model::TranspileOptions::new(version),
+        }
+    }
+
+    /// Return `true` if embedded GLSL sources are minified
+    pub fn minify_source(&self) -> bool {
+        self.minify_source
+    }
+
+    /// Set whether embedded GLSL sources should be minified
+    ///
+    /// This reduces the size of shaders embedded as source, e.g. for WebGL. Comments, `#line`
+    /// directives and whitespace are removed, and locals get short names. Globals, including
+    /// uniforms and interface variables, keep their names. Sources which can't be minified are
+    /// embedded as-is.
+    ///
+    /// # Parameters
+    ///
+    /// * `minify_source`: `true` to minify embedded GLSL sources
+    pub fn set_minify_source(&mut self, minify_source: bool) {
+        self.minify_source = minify_source;
+    }
+
+    /// Return the GLSL version shader sources are linted against, if set explicitly
+    pub fn lint_target(&self) -> Option<GlslVersion> {
+        self.lint_target
+    }
+
+    /// Set the GLSL version shader sources are linted against
+    ///
+    /// By default, sources are linted against the target of this compiler when it is a GLSL ES
+    /// version. Setting a target allows checking that shaders compiled to SPIR-V would also work
+    /// with WebGL. Lints only report problems for GLSL ES targets.
+    ///
+    /// # Parameters
+    ///
+    /// * `lint_target`: GLSL version to lint against, or `None` for the target of this compiler
+    pub fn set_lint_target(&mut self, lint_target: Option<GlslVersion>) {
+        self.lint_target = lint_target;
+    }
+
+    /// Return the level of a lint, `LintLevel::Warn` by default
+    pub fn lint_level(&self, lint: Lint) -> LintLevel {
+        self.lint_levels
+            .get(&lint)
+            .copied()
+            .unwrap_or(LintLevel::Warn)
+    }
+
+    /// Set the level of a lint
+    ///
+    /// # Parameters
+    ///
+    /// * `lint`: lint to configure
+    /// * `level`: action taken when the lint finds a problem
+    pub fn set_lint_level(&mut self, lint: Lint, level: LintLevel) {
+        self.lint_levels.insert(lint, level);
+    }
+
+    /// Return `true` if malformed uniform annotations fail the build
+    pub fn strict_annotations(&self) -> bool {
+        self.strict_annotations
+    }
+
+    /// Set whether malformed uniform annotations fail the build
+    ///
+    /// By default, malformed annotations are skipped and reported as build warnings, since they
+    /// are parsed from comments which may not be meant as annotations.
+    ///
+    /// # Parameters
+    ///
+    /// * `strict_annotations`: `true` to fail with `Error::InvalidAnnotation` instead
+    pub fn set_strict_annotations(&mut self, strict_annotations: bool) {
+        self.strict_annotations = strict_annotations;
+    }
+
+    /// Lint a shader source against the lint target of this compiler
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: source to lint
+    /// * `kind`: stage of the shader
+    ///
+    /// # Returns
+    ///
+    /// The problems found by lints which are not allowed, or an error if the source could not
+    /// be parsed.
+    pub fn lint(&self, source: &GlslModule<'_>, kind: ShaderKind) -> Result<Vec<LintWarning>> {
+        let target = match (self.lint_target, self.output_type) {
+            (Some(target), _) | (None, TargetType::Glsl(target)) => target,
+            _ => return Ok(Vec::new()),
+        };
+
+        let mut warnings = source.lint(kind, target)?;
+        warnings.retain(|warning| self.lint_level(warning.lint) != LintLevel::Allow);
+        Ok(warnings)
+    }
+
+    /// Check the source of a shader against the target of this compiler
+    ///
+    /// # Parameters
+    ///
+    /// * `shader`: shader to check
+    /// * `prefer_spirv`: `true` if the SPIR-V representation of the shader will be embedded
+    ///
+    /// # Returns
+    ///
+    /// The reasons why the embedded source is not compatible, if any.
+    pub fn check_compatibility<T: AsOutputFormat>(
+        &self,
+        shader: &ShaderObject<T>,
+        prefer_spirv: bool,
+    ) -> Vec<crate::model::Incompatibility> {
+        // SPIR-V binaries are not checked
+        #[cfg(feature = "spirv")]
+        if prefer_spirv && shader.as_spirv().is_some() {
+            return Vec::new();
+        }
+        #[cfg(not(feature = "spirv"))]
+        let _ = prefer_spirv;
+
+        match (self.output_type, shader.as_source()) {
+            (TargetType::Glsl(version), Some(source)) => {
+                source.header().check(version, self.glsl_profile)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn wrap_shader<'s, T: AsOutputFormat>(
+        &mut self,
+        mut shader: ReflectedObject<ShaderObject<T>>,
         prefer_spirv: bool,
     ) -> Result<WrappedShader<T>> {
-        Ok(WrappedShader::new(shader, prefer_spirv))
+        // Shaders for other APIs can't be used with the tinygl runtime
+        if !self.output_type.is_opengl() {
+            return Err(Error::InvalidTargetType(self.output_type));
+        }
+
+        let annotation_errors = shader.take_annotation_errors();
+        if self.strict_annotations {
+            if let Some(error) = annotation_errors.into_iter().next() {
+                return Err(error);
+            }
+        } else if !self.skip_cargo {
+            for error in annotation_errors {
+                println!("cargo:warning={}, it will be ignored", error);
+            }
+        }
+
+        let incompatibilities = self.check_compatibility(&shader, prefer_spirv);
+
+        let mut wrapped = WrappedShader::new(
+            shader,
+            prefer_spirv,
+            self.hot_reload,
+            self.shader_root.as_deref(),
+        );
+
+        if !incompatibilities.is_empty() {
+            let source_path = wrapped.result().info().source_path.to_string();
+
+            match self.incompatible_source_mode {
+                IncompatibleSourceMode::Error => {
+                    return Err(Error::IncompatibleSource(source_path, incompatibilities));
+                }
+                IncompatibleSourceMode::Transpile => {
+                    self.transpile_wrapped(&mut wrapped, source_path, incompatibilities)?;
+                }
+                IncompatibleSourceMode::Ignore => {}
+            }
+        }
+
+        if !self.skip_cargo {
+            for uniform in wrapped
+                .result()
+                .uniforms()
+                .iter()
+                .filter(|u| u.ty.is_none())
+            {
+                println!(
+                    "cargo:warning={}: uniform {} has an unsupported type, it will not be wrapped",
+                    wrapped.result().info().source_path,
+                    uniform.name
+                );
+            }
+
+            // Setters for unused uniforms silently do nothing on drivers which strip them
+            for uniform in wrapped.uniforms().iter().filter(|u| u.is_unused()) {
+                println!(
+                    "cargo:warning={}: uniform {} is never used",
+                    wrapped.result().info().source_path,
+                    uniform.name
+                );
+            }
+        }
+
+        self.lint_wrapped(&wrapped)?;
+
+        if let Some(version) = self.spirv_fallback {
+            self.add_spirv_fallback(&mut wrapped, version)?;
+        }
+
+        if self.minify_source {
+            self.minify_wrapped(&mut wrapped);
+        }
+
+        wrapped.set_unused_uniform_mode(self.unused_uniform_mode);
+
+        // Only embedded binaries need to be stripped
+        #[cfg(feature = "spirv")]
+        if self.strip_debug_info && wrapped.prefer_spirv() {
+            if let Some(report) = wrapped.strip_debug_info() {
+                if !self.skip_cargo {
+                    println!(
+                        "cargo:warning={}: stripped SPIR-V debug info, {}",
+                        wrapped.result().info().source_path,
+                        report
+                    );
+                }
+            }
+        }
+
+        Ok(wrapped)
+    }
+
+    #[cfg(feature = "transpile")]
+    fn transpile_wrapped<T: AsOutputFormat>(
+        &self,
+        wrapped: &mut WrappedShader<T>,
+        source_path: String,
+        incompatibilities: Vec<crate::model::Incompatibility>,
+    ) -> Result<()> {
+        let version = match self.output_type {
+            TargetType::Glsl(version) => version,
+            _ => return Ok(()),
+        };
+
+        let source = match wrapped.result().as_spirv() {
+            Some(spirv) => spirv.transpile(self.transpile_options_for(version))?,
+            None => return Err(Error::IncompatibleSource(source_path, incompatibilities)),
+        };
+
+        if !self.skip_cargo {
+            println!(
+                "cargo:warning={}: transpiled to GLSL {} for compatibility",
+                source_path, version
+            );
+        }
+
+        wrapped.set_transpiled_source(source);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "transpile"))]
+    fn transpile_wrapped<T: AsOutputFormat>(
+        &self,
+        _wrapped: &mut WrappedShader<T>,
+        source_path: String,
+        incompatibilities: Vec<crate::model::Incompatibility>,
+    ) -> Result<()> {
+        Err(Error::IncompatibleSource(source_path, incompatibilities))
+    }
+
+    #[cfg(feature = "transpile")]
+    fn add_spirv_fallback<T: AsOutputFormat>(
+        &self,
+        wrapped: &mut WrappedShader<T>,
+        version: GlslVersion,
+    ) -> Result<()> {
+        // Uniform locations of the source have to match the ones of the binary
+        if version.is_es() || version.number() < 430 {
+            return Err(Error::InvalidFallbackVersion(version));
+        }
+
+        // Only shaders which embed their SPIR-V binary need a fallback
+        if !wrapped.prefer_spirv() {
+            return Ok(());
+        }
+
+        if let Some(spirv) = wrapped.result().as_spirv() {
+            let source = spirv.transpile(crate::model::TranspileOptions {
+                flatten_uniform_buffers: false,
+                ..self.transpile_options_for(version)
+            })?;
+            wrapped.set_fallback_source(source);
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "transpile"))]
+    fn add_spirv_fallback<T: AsOutputFormat>(
+        &self,
+        _wrapped: &mut WrappedShader<T>,
+        version: GlslVersion,
+    ) -> Result<()> {
+        Err(Error::TranspilingNotSupported(version))
+    }
+
+    fn lint_wrapped<T: AsOutputFormat>(&self, wrapped: &WrappedShader<T>) -> Result<()> {
+        // Transpiled sources are generated for the target, only the original one is linted
+        let source = match wrapped.result().as_source() {
+            Some(source) => source,
+            None => return Ok(()),
+        };
+
+        let source_path = wrapped.result().info().source_path.to_string();
+        let warnings = match self.lint(source, wrapped.result().info().kind) {
+            Ok(warnings) => warnings,
+            // The GLSL parser doesn't support every source
+            Err(error) => {
+                if !self.skip_cargo {
+                    println!(
+                        "cargo:warning={}: could not lint source, {}",
+                        source_path, error
+                    );
+                }
+
+                return Ok(());
+            }
+        };
+
+        let (denied, warned): (Vec<_>, Vec<_>) = warnings
+            .into_iter()
+            .partition(|warning| self.lint_level(warning.lint) == LintLevel::Deny);
+
+        if !self.skip_cargo {
+            for warning in warned {
+                println!("cargo:warning={}: {}", source_path, warning);
+            }
+        }
+
+        if !denied.is_empty() {
+            return Err(Error::LintFailed(source_path, denied));
+        }
+
+        Ok(())
+    }
+
+    fn minify_wrapped<T: AsOutputFormat>(&self, wrapped: &mut WrappedShader<T>) {
+        // SPIR-V binaries don't embed any source
+        #[cfg(feature = "spirv")]
+        if wrapped.prefer_spirv() && wrapped.result().as_spirv().is_some() {
+            return;
+        }
+
+        let minified = match wrapped.source() {
+            Some(source) => source.minify(),
+            None => return,
+        };
+
+        match minified {
+            Ok(source) => wrapped.set_minified_source(source),
+            Err(error) => {
+                if !self.skip_cargo {
+                    println!(
+                        "cargo:warning={}: could not minify source, {}",
+                        wrapped.result().info().source_path,
+                        error
+                    );
+                }
+            }
+        }
+    }
+
+    /// Wrap the stages of a multi-stage shader
+    ///
+    /// # Parameters
+    ///
+    /// * `stages`: reflected stages, as loaded by `GlslObject::stages_from_path`
+    /// * `prefer_spirv`: `true` to embed the SPIR-V representation of the stages
+    ///
+    /// # Returns
+    ///
+    /// The wrapped stages, which can be given to `wrap_program` using `WrappedStages::shaders`.
+    pub fn wrap_stages<T: AsOutputFormat>(
+        &mut self,
+        stages: impl IntoIterator<Item = ReflectedObject<ShaderObject<T>>>,
+        prefer_spirv: bool,
+    ) -> Result<WrappedStages<T>> {
+        Ok(WrappedStages::new(
+            stages
+                .into_iter()
+                .map(|stage| self.wrap_shader(stage, prefer_spirv))
+                .collect::<Result<_>>()?,
+        ))
     }
 
     pub fn wrap_program<'s>(
@@ -1,143 +1,344 @@
-use std::path::Path;
+use std::cell::RefCell;
+use std::path::{Path, PathBuf};
 
-use crate::model::{GlslCompiler, GlslModule, GlslPreprocessor, SpirVModule};
+use crate::model::{
+    GlslCompiler, GlslModule, GlslObject, GlslPreprocessor, GlslWithSpirVModule, ShaderObject,
+    SpirVModule,
+};
+use crate::reflect::{ReflectedObject, SpirVReflectBackend};
 use crate::{Compiler, Error, ShaderKind};
 
+thread_local! {
+    /// shaderc compilers can't be shared between threads, so each thread gets its own
+    static SHADERC: RefCell<Option<shaderc::Compiler>> = const { RefCell::new(None) };
+}
+
+/// Run a function with the shaderc compiler of the current thread
+fn with_shaderc<R>(
+    f: impl FnOnce(&mut shaderc::Compiler) -> Result<R, crate::Error>,
+) -> Result<R, crate::Error> {
+    SHADERC.with(|cell| {
+        let mut shaderc = match cell.borrow_mut().take() {
+            Some(shaderc) => shaderc,
+            None => shaderc::Compiler::new().ok_or(Error::ShadercInitFailed)?,
+        };
+
+        let result = f(&mut shaderc);
+        *cell.borrow_mut() = Some(shaderc);
+        result
+    })
+}
+
 pub struct CompilerWithShaderc {
     compiler: Compiler,
-    shaderc: shaderc::Compiler,
+    includes: Vec<PathBuf>,
 }
 
 impl CompilerWithShaderc {
-    pub fn new(compiler: Compiler) -> Self {
-        Self {
+    pub fn new(compiler: Compiler) -> Result<Self, crate::Error> {
+        // Check that shaderc is available before compiling anything
+        with_shaderc(|_| Ok(()))?;
+
+        Ok(Self {
             compiler,
-            shaderc: shaderc::Compiler::new().unwrap(),
-        }
+            includes: Vec::new(),
+        })
+    }
+
+    /// Compile GLSL objects to SPIR-V in parallel
+    ///
+    /// Objects are compiled on up to `Compiler::parallel_jobs` threads. Build warnings, cargo
+    /// directives and include callbacks are emitted in the order of `objects` once they are all
+    /// compiled, so the build output doesn't depend on scheduling.
+    ///
+    /// The includes of each object are recorded in its info. Afterwards, `take_includes` returns
+    /// the files included by any of the objects, without duplicates.
+    ///
+    /// # Parameters
+    ///
+    /// * `objects`: GLSL objects to compile
+    ///
+    /// # Returns
+    ///
+    /// The compiled objects in the same order, or the error of the first object which failed to
+    /// compile.
+    pub fn compile_all<'s>(
+        &mut self,
+        objects: Vec<GlslObject<'s>>,
+    ) -> Result<Vec<ShaderObject<GlslWithSpirVModule<'s, 'static>>>, crate::Error> {
+        self.run_parallel(objects, |object, job| object.compile(job))
+    }
+
+    /// Compile GLSL objects to SPIR-V and reflect their uniforms in parallel
+    ///
+    /// See `compile_all` for the ordering of the build output.
+    ///
+    /// # Parameters
+    ///
+    /// * `objects`: GLSL objects to compile
+    /// * `reflector`: backend used to reflect the SPIR-V representation of the objects
+    ///
+    /// # Returns
+    ///
+    /// The reflected objects in the same order, or the error of the first object which failed to
+    /// compile or reflect.
+    pub fn compile_and_reflect_all<'s>(
+        &mut self,
+        objects: Vec<GlslObject<'s>>,
+        reflector: &dyn SpirVReflectBackend,
+    ) -> Result<Vec<ReflectedObject<ShaderObject<GlslWithSpirVModule<'s, 'static>>>>, crate::Error>
+    {
+        self.run_parallel(objects, |object, job| {
+            object.compile(job)?.reflect_spirv(reflector)
+        })
     }
 
-    fn get_options(&self) -> shaderc::CompileOptions<'static> {
-        let skip_cargo = self.compiler.skip_cargo;
-        let cb = self.compiler.include_callback.as_ref().map(|cb| cb.clone());
-
-        // Set callback
-        let mut options = shaderc::CompileOptions::new().unwrap();
-
-        // Add definitions
-        // TODO: Let use configure options?
-        options.add_macro_definition("TINYGL", Some(env!("CARGO_PKG_VERSION_MAJOR")));
-
-        // Default to OpenGL targets
-        options.set_target_env(shaderc::TargetEnv::OpenGL, 0);
-
-        // Set include callback
-        options.set_include_callback(move |name, _include_type, source, _depth| {
-            // TODO: Circular includes?
-            // TODO: Better include resolver?
-            match std::fs::canonicalize(Path::new(&source).parent().unwrap().join(name)) {
-                Ok(full_path) => {
-                    if !skip_cargo {
-                        // Notify cargo to rerun if included file changed
-                        println!("cargo:rerun-if-changed={}", full_path.display());
-                    }
-
-                    if let Some(cb) = &cb {
-                        cb.borrow_mut()(&full_path);
-                    }
-
-                    match std::fs::read_to_string(&full_path) {
-                        Ok(content) => Ok(shaderc::ResolvedInclude {
-                            resolved_name: full_path.to_string_lossy().to_string(),
-                            content,
-                        }),
-                        Err(error) => Err(error.to_string()),
-                    }
+    fn run_parallel<I: Send, O: Send>(
+        &mut self,
+        items: Vec<I>,
+        f: impl Fn(I, &mut ShadercJob) -> Result<O, crate::Error> + Sync,
+    ) -> Result<Vec<O>, crate::Error> {
+        let jobs = self.compiler.parallel_jobs().clamp(1, items.len().max(1));
+
+        // Distribute items round-robin, which is good enough for similar shaders
+        let mut buckets: Vec<Vec<_>> = (0..jobs).map(|_| Vec::new()).collect();
+        for (index, item) in items.into_iter().enumerate() {
+            buckets[index % jobs].push((index, item));
+        }
+
+        let f = &f;
+        let mut results: Vec<_> = std::thread::scope(|scope| {
+            let workers: Vec<_> = buckets
+                .into_iter()
+                .map(|bucket| {
+                    scope.spawn(move || {
+                        bucket
+                            .into_iter()
+                            .map(|(index, item)| {
+                                let mut job = ShadercJob::default();
+                                let result = f(item, &mut job);
+                                (index, result, job)
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+
+            workers
+                .into_iter()
+                .flat_map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+                .collect()
+        });
+
+        results.sort_by_key(|(index, _, _)| *index);
+
+        // Includes are recorded for the whole batch
+        self.includes.clear();
+
+        let mut output = Vec::with_capacity(results.len());
+        for (_, result, job) in results {
+            self.report(&job);
+
+            for path in job.includes {
+                if !self.includes.contains(&path) {
+                    self.includes.push(path);
                 }
-                Err(error) => Err(error.to_string()),
             }
-        });
 
-        options
+            output.push(result?);
+        }
+
+        Ok(output)
+    }
+
+    /// Emit the build output of a job
+    fn report(&mut self, job: &ShadercJob) {
+        for path in &job.includes {
+            if !self.compiler.skip_cargo {
+                // Notify cargo to rerun if included file changed
+                println!("cargo:rerun-if-changed={}", path.display());
+            }
+
+            if let Some(cb) = &mut self.compiler.include_callback {
+                cb(path);
+            }
+        }
+
+        if !self.compiler.skip_cargo {
+            // TODO: Store warnings in result?
+            for l in job.warnings.lines() {
+                println!("cargo:warning={}", l);
+            }
+
+            if let Some(errors) = &job.errors {
+                eprintln!("{}", errors);
+            }
+        }
     }
 }
 
-impl GlslCompiler for CompilerWithShaderc {
-    fn compile_module(
+/// Compilation of a single module, which records its build output instead of printing it
+#[derive(Default)]
+struct ShadercJob {
+    includes: Vec<PathBuf>,
+    warnings: String,
+    errors: Option<String>,
+}
+
+impl ShadercJob {
+    fn run<T>(
         &mut self,
-        source: &str,
-        kind: ShaderKind,
         source_path: &str,
-    ) -> Result<SpirVModule<'static>, crate::Error> {
-        let options = self.get_options();
+        f: impl FnOnce(
+            &mut shaderc::Compiler,
+            &shaderc::CompileOptions,
+        ) -> Result<shaderc::CompilationArtifact, shaderc::Error>,
+        output: impl FnOnce(&shaderc::CompilationArtifact) -> Result<T, crate::Error>,
+    ) -> Result<T, crate::Error> {
+        let includes = RefCell::new(Vec::new());
+        let result = with_shaderc(|shaderc| Ok(f(shaderc, &get_options(&includes)?)));
+        self.includes = includes.into_inner();
 
-        // Compile the requested targets
-        match self.shaderc.compile_into_spirv(
-            source,
-            kind.into(),
-            source_path,
-            "main",
-            Some(&options),
-        ) {
-            Ok(result) => {
-                if !self.compiler.skip_cargo {
-                    // Print warnings
-                    // TODO: Store warnings in result?
-                    if result.get_num_warnings() > 0 {
-                        for l in result.get_warning_messages().lines() {
-                            println!("cargo:warning={}", l);
-                        }
-                    }
+        match result? {
+            Ok(artifact) => {
+                if artifact.get_num_warnings() > 0 {
+                    self.warnings = artifact.get_warning_messages();
                 }
 
-                Ok(SpirVModule::from_words(result.as_binary().to_vec())?)
+                output(&artifact)
             }
             Err(shaderc::Error::CompilationError(num_errors, errors)) => {
-                if !self.compiler.skip_cargo {
-                    eprintln!("{}", errors);
+                self.errors = Some(errors.clone());
+                Err(Error::CompilationError(num_errors as usize, errors))
+            }
+            Err(error) => Err(Error::ShadercError(
+                source_path.to_owned(),
+                error.to_string(),
+            )),
+        }
+    }
+}
+
+fn get_options(
+    includes: &RefCell<Vec<PathBuf>>,
+) -> Result<shaderc::CompileOptions<'_>, crate::Error> {
+    let mut options = shaderc::CompileOptions::new().ok_or(Error::ShadercInitFailed)?;
+
+    // Add definitions
+    // TODO: Let use configure options?
+    options.add_macro_definition("TINYGL", Some(env!("CARGO_PKG_VERSION_MAJOR")));
+
+    // Default to OpenGL targets
+    options.set_target_env(shaderc::TargetEnv::OpenGL, 0);
+
+    // Set include callback
+    options.set_include_callback(move |name, _include_type, source, _depth| {
+        // TODO: Circular includes?
+        // TODO: Better include resolver?
+        let parent = Path::new(&source)
+            .parent()
+            .ok_or_else(|| format!("cannot resolve {} relative to {}", name, source))?;
+
+        match std::fs::canonicalize(parent.join(name)) {
+            Ok(full_path) => {
+                // Record the include for this module
+                if !includes.borrow().contains(&full_path) {
+                    includes.borrow_mut().push(full_path.clone());
                 }
 
-                return Err(Error::CompilationError(num_errors as usize, errors.clone()));
+                match std::fs::read_to_string(&full_path) {
+                    Ok(content) => Ok(shaderc::ResolvedInclude {
+                        resolved_name: full_path.to_string_lossy().to_string(),
+                        content,
+                    }),
+                    Err(error) => Err(error.to_string()),
+                }
             }
-            Err(error) => panic!(error.to_string()),
+            Err(error) => Err(error.to_string()),
         }
+    });
+
+    Ok(options)
+}
+
+impl GlslCompiler for ShadercJob {
+    fn compile_module(
+        &mut self,
+        source: &str,
+        kind: ShaderKind,
+        source_path: &str,
+    ) -> Result<SpirVModule<'static>, crate::Error> {
+        self.run(
+            source_path,
+            |shaderc, options| {
+                shaderc.compile_into_spirv(source, kind.into(), source_path, "main", Some(options))
+            },
+            |result| SpirVModule::from_words(result.as_binary().to_vec()),
+        )
+    }
+
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        // Includes are kept for reporting
+        self.includes.clone()
     }
 }
 
-impl GlslPreprocessor for CompilerWithShaderc {
+impl GlslPreprocessor for ShadercJob {
     fn preprocess_module(
         &mut self,
         source: &str,
         source_path: &str,
     ) -> Result<GlslModule<'static>, crate::Error> {
-        let options = self.get_options();
-
-        // Compile the requested targets
-        match self
-            .shaderc
-            .preprocess(source, source_path, "main", Some(&options))
-        {
-            Ok(result) => {
-                if !self.compiler.skip_cargo {
-                    // Print warnings
-                    // TODO: Store warnings in result?
-                    if result.get_num_warnings() > 0 {
-                        for l in result.get_warning_messages().lines() {
-                            println!("cargo:warning={}", l);
-                        }
-                    }
-                }
+        self.run(
+            source_path,
+            |shaderc, options| shaderc.preprocess(source, source_path, "main", Some(options)),
+            |result| GlslModule::from_string(result.as_text()),
+        )
+    }
 
-                Ok(GlslModule::from_string(result.as_text())?)
-            }
-            Err(shaderc::Error::CompilationError(num_errors, errors)) => {
-                if !self.compiler.skip_cargo {
-                    eprintln!("{}", errors);
-                }
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        self.includes.clone()
+    }
+}
 
-                return Err(Error::CompilationError(num_errors as usize, errors.clone()));
-            }
-            Err(error) => panic!(error.to_string()),
-        }
+impl GlslCompiler for CompilerWithShaderc {
+    fn compile_module(
+        &mut self,
+        source: &str,
+        kind: ShaderKind,
+        source_path: &str,
+    ) -> Result<SpirVModule<'static>, crate::Error> {
+        let mut job = ShadercJob::default();
+        let result = job.compile_module(source, kind, source_path);
+        self.report(&job);
+        self.includes = job.includes;
+        result
+    }
+
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.includes)
+    }
+}
+
+impl GlslPreprocessor for CompilerWithShaderc {
+    fn preprocess_module(
+        &mut self,
+        source: &str,
+        source_path: &str,
+    ) -> Result<GlslModule<'static>, crate::Error> {
+        let mut job = ShadercJob::default();
+        let result = job.preprocess_module(source, source_path);
+        self.report(&job);
+        self.includes = job.includes;
+        result
+    }
+
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.includes)
     }
 }
 
@@ -18,6 +18,8 @@ pub enum GlslVersion {
     V4_60,
     V1_00Es,
     V3_00Es,
+    V3_10Es,
+    V3_20Es,
 }
 
 impl fmt::Display for GlslVersion {
@@ -38,14 +40,123 @@ impl fmt::Display for GlslVersion {
             Self::V4_60 => write!(f, "4.60"),
             Self::V1_00Es => write!(f, "1.00 es"),
             Self::V3_00Es => write!(f, "3.00 es"),
+            Self::V3_10Es => write!(f, "3.10 es"),
+            Self::V3_20Es => write!(f, "3.20 es"),
+        }
+    }
+}
+
+impl GlslVersion {
+    /// Find the version declared by a `#version` directive
+    ///
+    /// # Parameters
+    ///
+    /// * `number`: version number
+    /// * `es`: `true` if the `es` profile was specified
+    pub fn from_number(number: u32, es: bool) -> Option<Self> {
+        // Version 100 is always GLSL ES
+        if es || number == 100 {
+            match number {
+                100 => Some(Self::V1_00Es),
+                300 => Some(Self::V3_00Es),
+                310 => Some(Self::V3_10Es),
+                320 => Some(Self::V3_20Es),
+                _ => None,
+            }
+        } else {
+            match number {
+                110 => Some(Self::V1_10),
+                120 => Some(Self::V1_20),
+                130 => Some(Self::V1_30),
+                140 => Some(Self::V1_40),
+                150 => Some(Self::V1_50),
+                330 => Some(Self::V3_30),
+                400 => Some(Self::V4_00),
+                410 => Some(Self::V4_10),
+                420 => Some(Self::V4_20),
+                430 => Some(Self::V4_30),
+                440 => Some(Self::V4_40),
+                450 => Some(Self::V4_50),
+                460 => Some(Self::V4_60),
+                _ => None,
+            }
+        }
+    }
+
+    /// Return the version number, as used in `#version` directives
+    pub fn number(self) -> u32 {
+        match self {
+            Self::V1_10 => 110,
+            Self::V1_20 => 120,
+            Self::V1_30 => 130,
+            Self::V1_40 => 140,
+            Self::V1_50 => 150,
+            Self::V3_30 => 330,
+            Self::V4_00 => 400,
+            Self::V4_10 => 410,
+            Self::V4_20 => 420,
+            Self::V4_30 => 430,
+            Self::V4_40 => 440,
+            Self::V4_50 => 450,
+            Self::V4_60 => 460,
+            Self::V1_00Es => 100,
+            Self::V3_00Es => 300,
+            Self::V3_10Es => 310,
+            Self::V3_20Es => 320,
+        }
+    }
+
+    /// Return `true` if this is a GLSL ES version
+    pub fn is_es(self) -> bool {
+        matches!(
+            self,
+            Self::V1_00Es | Self::V3_00Es | Self::V3_10Es | Self::V3_20Es
+        )
+    }
+
+    /// Return `true` if shaders declaring the `source` version can be compiled by an OpenGL
+    /// implementation which supports this version
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: version declared by the shader
+    pub fn supports(self, source: GlslVersion) -> bool {
+        match (self.is_es(), source.is_es()) {
+            (true, true) | (false, false) => source.number() <= self.number(),
+            // Desktop OpenGL can compile ES shaders through the ARB_ESx_compatibility extensions
+            (false, true) => match source {
+                Self::V1_00Es => self.number() >= 410,
+                Self::V3_00Es => self.number() >= 430,
+                Self::V3_10Es => self.number() >= 450,
+                _ => false,
+            },
+            (true, false) => false,
+        }
+    }
+}
+
+/// Desktop GLSL profile
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum GlslProfile {
+    Core,
+    Compatibility,
+}
+
+impl fmt::Display for GlslProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Core => write!(f, "core"),
+            Self::Compatibility => write!(f, "compatibility"),
         }
     }
 }
 
 #[cfg(feature = "spirv_cross")]
-impl From<spirv_cross::glsl::Version> for GlslVersion {
-    fn from(version: spirv_cross::glsl::Version) -> Self {
-        match version {
+impl std::convert::TryFrom<spirv_cross::glsl::Version> for GlslVersion {
+    type Error = crate::Error;
+
+    fn try_from(version: spirv_cross::glsl::Version) -> crate::Result<Self> {
+        Ok(match version {
             spirv_cross::glsl::Version::V1_10 => Self::V1_10,
             spirv_cross::glsl::Version::V1_20 => Self::V1_20,
             spirv_cross::glsl::Version::V1_30 => Self::V1_30,
@@ -61,7 +172,10 @@ impl From<spirv_cross::glsl::Version> for GlslVersion {
             spirv_cross::glsl::Version::V4_60 => Self::V4_60,
             spirv_cross::glsl::Version::V1_00Es => Self::V1_00Es,
             spirv_cross::glsl::Version::V3_00Es => Self::V3_00Es,
-        }
+            spirv_cross::glsl::Version::V3_10Es => Self::V3_10Es,
+            spirv_cross::glsl::Version::V3_20Es => Self::V3_20Es,
+            other => return Err(crate::Error::UnsupportedSpirVCrossVersion(other)),
+        })
     }
 }
 
@@ -84,6 +198,89 @@ impl Into<spirv_cross::glsl::Version> for GlslVersion {
             Self::V4_60 => spirv_cross::glsl::Version::V4_60,
             Self::V1_00Es => spirv_cross::glsl::Version::V1_00Es,
             Self::V3_00Es => spirv_cross::glsl::Version::V3_00Es,
+            Self::V3_10Es => spirv_cross::glsl::Version::V3_10Es,
+            Self::V3_20Es => spirv_cross::glsl::Version::V3_20Es,
+        }
+    }
+}
+
+/// HLSL shader model
+#[allow(non_snake_case, non_camel_case_types)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum HlslShaderModel {
+    V3_0,
+    V4_0,
+    V4_1,
+    V5_0,
+    V5_1,
+    V6_0,
+}
+
+impl fmt::Display for HlslShaderModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V3_0 => write!(f, "SM 3.0"),
+            Self::V4_0 => write!(f, "SM 4.0"),
+            Self::V4_1 => write!(f, "SM 4.1"),
+            Self::V5_0 => write!(f, "SM 5.0"),
+            Self::V5_1 => write!(f, "SM 5.1"),
+            Self::V6_0 => write!(f, "SM 6.0"),
+        }
+    }
+}
+
+#[cfg(feature = "spirv_cross")]
+impl From<HlslShaderModel> for spirv_cross::hlsl::ShaderModel {
+    fn from(shader_model: HlslShaderModel) -> Self {
+        match shader_model {
+            HlslShaderModel::V3_0 => spirv_cross::hlsl::ShaderModel::V3_0,
+            HlslShaderModel::V4_0 => spirv_cross::hlsl::ShaderModel::V4_0,
+            HlslShaderModel::V4_1 => spirv_cross::hlsl::ShaderModel::V4_1,
+            HlslShaderModel::V5_0 => spirv_cross::hlsl::ShaderModel::V5_0,
+            HlslShaderModel::V5_1 => spirv_cross::hlsl::ShaderModel::V5_1,
+            HlslShaderModel::V6_0 => spirv_cross::hlsl::ShaderModel::V6_0,
+        }
+    }
+}
+
+/// Metal Shading Language version
+#[allow(non_snake_case, non_camel_case_types)]
+#[derive(Copy, Clone, Debug, Hash, Eq, PartialEq)]
+pub enum MslVersion {
+    V1_0,
+    V1_1,
+    V1_2,
+    V2_0,
+    V2_1,
+    V2_2,
+    V2_3,
+}
+
+impl fmt::Display for MslVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::V1_0 => write!(f, "MSL 1.0"),
+            Self::V1_1 => write!(f, "MSL 1.1"),
+            Self::V1_2 => write!(f, "MSL 1.2"),
+            Self::V2_0 => write!(f, "MSL 2.0"),
+            Self::V2_1 => write!(f, "MSL 2.1"),
+            Self::V2_2 => write!(f, "MSL 2.2"),
+            Self::V2_3 => write!(f, "MSL 2.3"),
+        }
+    }
+}
+
+#[cfg(feature = "spirv_cross")]
+impl From<MslVersion> for spirv_cross::msl::Version {
+    fn from(version: MslVersion) -> Self {
+        match version {
+            MslVersion::V1_0 => spirv_cross::msl::Version::V1_0,
+            MslVersion::V1_1 => spirv_cross::msl::Version::V1_1,
+            MslVersion::V1_2 => spirv_cross::msl::Version::V1_2,
+            MslVersion::V2_0 => spirv_cross::msl::Version::V2_0,
+            MslVersion::V2_1 => spirv_cross::msl::Version::V2_1,
+            MslVersion::V2_2 => spirv_cross::msl::Version::V2_2,
+            MslVersion::V2_3 => spirv_cross::msl::Version::V2_3,
         }
     }
 }
@@ -92,13 +289,35 @@ impl Into<spirv_cross::glsl::Version> for GlslVersion {
 pub enum TargetType {
     SpirV,
     Glsl(GlslVersion),
+    /// HLSL source, for Direct3D. No tinygl wrappers are generated for this target.
+    Hlsl(HlslShaderModel),
+    /// MSL source, for Metal. No tinygl wrappers are generated for this target.
+    Msl(MslVersion),
 }
 
 impl TargetType {
     pub fn is_source(self) -> bool {
         match self {
-            TargetType::Glsl(_) => true,
+            TargetType::Glsl(_) | TargetType::Hlsl(_) | TargetType::Msl(_) => true,
             TargetType::SpirV => false,
         }
     }
+
+    /// Return `true` if this target can be used with OpenGL, and the tinygl runtime
+    pub fn is_opengl(self) -> bool {
+        match self {
+            TargetType::Glsl(_) | TargetType::SpirV => true,
+            TargetType::Hlsl(_) | TargetType::Msl(_) => false,
+        }
+    }
+
+    /// File extension for shaders of this target type
+    pub fn extension(self) -> &'static str {
+        match self {
+            TargetType::SpirV => "spv",
+            TargetType::Glsl(_) => "glsl",
+            TargetType::Hlsl(_) => "hlsl",
+            TargetType::Msl(_) => "metal",
+        }
+    }
 }
@@ -1,6 +1,7 @@
 use heck::CamelCase;
 
 use super::wrapped_shader::*;
+use crate::reflect::FoundUniform;
 
 pub struct WrappedProgram<'s> {
     struct_name: String,
@@ -30,4 +31,27 @@ impl<'s> WrappedProgram<'s> {
             .iter()
             .filter(|s| !s.uniforms().is_empty())
     }
+
+    /// Return the uniforms of this program, along with the shader they are taken from
+    ///
+    /// Uniforms shared by multiple shaders are only returned once, preferably from a shader
+    /// which uses them.
+    pub fn uniforms(&self) -> Vec<(&'s dyn WrappedShaderDetails, &'s FoundUniform)> {
+        let mut result: Vec<(&'s dyn WrappedShaderDetails, &'s FoundUniform)> = Vec::new();
+
+        for shader in self.attached_shaders.iter().copied() {
+            for uniform in shader.uniforms() {
+                match result.iter_mut().find(|(_, u)| u.name == uniform.name) {
+                    Some(entry) => {
+                        if entry.1.is_unused() && !uniform.is_unused() {
+                            *entry = (shader, uniform);
+                        }
+                    }
+                    None => result.push((shader, uniform)),
+                }
+            }
+        }
+
+        result
+    }
 }
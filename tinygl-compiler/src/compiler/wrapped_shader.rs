@@ -1,12 +1,24 @@
+use std::path::{Path, PathBuf};
+
 use heck::{CamelCase, SnakeCase};
 
-use crate::model::ShaderObject;
-use crate::reflect::ReflectedObject;
+use crate::model::{AsOutputFormat, GlslModule, RemovedBinding, ShaderObject, SourcePath};
+use crate::reflect::{FoundUniform, ReflectedObject};
+use crate::UnusedUniformMode;
+
+/// Source information recorded for reloading a shader at runtime
+#[derive(Debug, Clone)]
+pub struct ReloadInfo {
+    /// Path to the GLSL source of the shader
+    pub source_path: PathBuf,
+    /// Files included by the shader source
+    pub includes: Vec<PathBuf>,
+}
 
 #[derive(Debug)]
 pub struct WrappedShader<T> {
     shader: String,
-    rs_file_name: String,
+    module_path: Vec<String>,
 
     shader_struct_name: String,
     shader_variable_name: String,
@@ -14,36 +26,257 @@ pub struct WrappedShader<T> {
     uniform_locations_name: String,
 
     result: ReflectedObject<ShaderObject<T>>,
+    transpiled_source: Option<GlslModule<'static>>,
+    fallback_source: Option<GlslModule<'static>>,
+    minified_source: Option<GlslModule<'static>>,
+    uniforms: Vec<FoundUniform>,
     prefer_spirv: bool,
+    reload_info: Option<ReloadInfo>,
+    removed_bindings: Vec<RemovedBinding>,
+    unused_uniform_mode: UnusedUniformMode,
+
+    #[cfg(feature = "spirv")]
+    stripped_spirv: Option<Vec<u32>>,
+    #[cfg(feature = "spirv")]
+    strip_report: Option<crate::model::StripReport>,
 }
 
-impl<T> WrappedShader<T> {
-    pub fn new(result: ReflectedObject<ShaderObject<T>>, prefer_spirv: bool) -> Self {
-        let shader: String = result
-            .info()
-            .source_path
-            .to_path()
-            .file_name()
-            .unwrap()
-            .to_string_lossy()
-            .into();
+impl<T: AsOutputFormat> WrappedShader<T> {
+    pub fn new(
+        result: ReflectedObject<ShaderObject<T>>,
+        prefer_spirv: bool,
+        hot_reload: bool,
+        shader_root: Option<&Path>,
+    ) -> Self {
+        let source_path = result.info().source_path.to_path();
+
+        // Stages of multi-stage sources are named after the file and their stage
+        let shader: String = if result.info().multi_stage {
+            format!(
+                "{}.{}",
+                source_path
+                    .file_stem()
+                    .unwrap_or_default()
+                    .to_string_lossy(),
+                result.info().kind.extension()
+            )
+        } else {
+            source_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into()
+        };
 
         let base_name = shader.replace(".", "_");
+        // With a shader root, shaders get their own module mirroring the directory layout.
+        // Otherwise they are generated at the root, like before module trees were supported.
+        let module_path: Vec<String> = match shader_root {
+            Some(root) => {
+                let mut module_path: Vec<String> = match &result.info().source_path {
+                    SourcePath::File(path) => path
+                        .parent()
+                        .and_then(|parent| parent.strip_prefix(root).ok())
+                        .map(|relative| {
+                            relative
+                                .components()
+                                .map(|c| module_name(&c.as_os_str().to_string_lossy()))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    _ => Vec::new(),
+                };
+                module_path.push(module_name(&base_name));
+                module_path
+            }
+            None => Vec::new(),
+        };
+
+        // Variable names have to be unique across modules, since they are used in programs
+        let unique_name = if module_path.is_empty() {
+            base_name.clone()
+        } else {
+            module_path.join("_")
+        };
+
         let shader_struct_name = (base_name.to_owned() + "_shader").to_camel_case();
-        let shader_variable_name = shader_struct_name.to_snake_case();
+        let shader_variable_name = (unique_name.to_owned() + "_shader").to_snake_case();
+
+        // Only shaders loaded from single-stage GLSL files can be reloaded
+        let reload_info = match &result.info().source_path {
+            SourcePath::File(path)
+                if hot_reload && result.as_source().is_some() && !result.info().multi_stage =>
+            {
+                Some(ReloadInfo {
+                    source_path: path.clone(),
+                    includes: result.info().includes.clone(),
+                })
+            }
+            _ => None,
+        };
 
-        Self {
+        let uniforms = typed_uniforms(result.uniforms());
+
+        let mut this = Self {
             shader,
-            rs_file_name: base_name.to_owned() + ".rs",
+            module_path,
             shader_struct_name,
             shader_variable_name,
-            uniform_struct_name: (base_name.to_owned() + "_uniforms").to_camel_case(),
-            uniform_locations_name: (base_name + "_locations").to_snake_case(),
+            uniform_struct_name: (base_name + "_uniforms").to_camel_case(),
+            uniform_locations_name: (unique_name + "_locations").to_snake_case(),
             result,
+            transpiled_source: None,
+            fallback_source: None,
+            minified_source: None,
+            uniforms,
             prefer_spirv,
+            reload_info,
+            removed_bindings: Vec::new(),
+            unused_uniform_mode: UnusedUniformMode::Keep,
+            #[cfg(feature = "spirv")]
+            stripped_spirv: None,
+            #[cfg(feature = "spirv")]
+            strip_report: None,
+        };
+
+        if let Some(source) = this.result.as_source() {
+            let removed_bindings = source.removed_bindings().to_vec();
+            let flattened_blocks = source.flattened_blocks().to_vec();
+            this.add_source_info(removed_bindings, &flattened_blocks);
         }
+
+        this
+    }
+
+    /// Return the GLSL source embedded in the generated code
+    pub fn source(&self) -> Option<&GlslModule<'_>> {
+        self.minified_source
+            .as_ref()
+            .or(self.transpiled_source.as_ref())
+            .or_else(|| self.result.as_source())
+    }
+
+    /// Replace the GLSL source of this shader with a transpiled one
+    ///
+    /// The source can't be reloaded from its original file anymore.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: transpiled source
+    #[cfg(feature = "transpile")]
+    pub(crate) fn set_transpiled_source(&mut self, source: GlslModule<'static>) {
+        self.uniforms = typed_uniforms(self.result.uniforms());
+        self.reload_info = None;
+        self.add_source_info(
+            source.removed_bindings().to_vec(),
+            source.flattened_blocks(),
+        );
+        self.transpiled_source = Some(source);
+    }
+
+    /// Replace the embedded GLSL source of this shader with its minified version
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: minified source
+    pub(crate) fn set_minified_source(&mut self, source: GlslModule<'static>) {
+        self.minified_source = Some(source);
     }
 
+    /// Return the GLSL source embedded next to the SPIR-V binary, if any
+    pub fn fallback_source(&self) -> Option<&GlslModule<'_>> {
+        self.fallback_source.as_ref()
+    }
+
+    /// Set the GLSL source used when the SPIR-V binary can't be loaded
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: source transpiled from the SPIR-V binary
+    #[cfg(feature = "transpile")]
+    pub(crate) fn set_fallback_source(&mut self, source: GlslModule<'static>) {
+        self.fallback_source = Some(source);
+    }
+
+    /// Set the treatment of uniforms which are not used by this shader
+    ///
+    /// # Parameters
+    ///
+    /// * `mode`: treatment of unused uniforms in generated code
+    pub(crate) fn set_unused_uniform_mode(&mut self, mode: UnusedUniformMode) {
+        if mode == UnusedUniformMode::Omit {
+            self.uniforms.retain(|uniform| !uniform.is_unused());
+        }
+
+        self.unused_uniform_mode = mode;
+    }
+
+    fn add_source_info(
+        &mut self,
+        removed_bindings: Vec<RemovedBinding>,
+        flattened_blocks: &[crate::model::FlattenedBlock],
+    ) {
+        // Bindings lost when transpiling have to be restored by the program
+        self.removed_bindings = removed_bindings;
+
+        // Flattened uniform blocks are wrapped as uniform arrays
+        for block in flattened_blocks {
+            if self.uniforms.iter().all(|u| u.name != block.name) {
+                self.uniforms.push(FoundUniform {
+                    name: block.name.clone(),
+                    ty: Some(block.ty),
+                    location_name: (block.name.clone() + "_location").to_snake_case(),
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    /// Strip debug instructions from the SPIR-V binary embedded in the generated code
+    ///
+    /// The reflected uniforms are not affected, since reflection has already run on the
+    /// original module.
+    ///
+    /// # Returns
+    ///
+    /// A size report, or `None` if this shader has no SPIR-V representation.
+    #[cfg(feature = "spirv")]
+    pub fn strip_debug_info(&mut self) -> Option<crate::model::StripReport> {
+        let spirv = self.result.as_spirv()?;
+        let stripped = spirv.strip_debug_info();
+
+        let report = crate::model::StripReport {
+            original_size: spirv.as_bytes_u8().len(),
+            stripped_size: stripped.as_bytes_u8().len(),
+        };
+
+        self.stripped_spirv = Some(stripped.as_bytes().to_vec());
+        self.strip_report = Some(report);
+        Some(report)
+    }
+}
+
+/// Turn a file or directory name into a valid Rust module name
+fn module_name(name: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ];
+
+    let name = name.to_snake_case();
+
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", name)
+    } else if KEYWORDS.contains(&name.as_str()) {
+        name + "_"
+    } else {
+        name
+    }
+}
+
+impl<T> WrappedShader<T> {
     pub fn prefer_spirv(&self) -> bool {
         self.prefer_spirv
     }
@@ -51,6 +284,50 @@ impl<T> WrappedShader<T> {
     pub fn result(&self) -> &ReflectedObject<ShaderObject<T>> {
         &self.result
     }
+
+    /// SPIR-V binary stripped of its debug info, if `strip_debug_info` was called
+    #[cfg(feature = "spirv")]
+    pub fn stripped_spirv(&self) -> Option<&[u32]> {
+        self.stripped_spirv.as_deref()
+    }
+
+    /// Size report from stripping the SPIR-V debug info, if `strip_debug_info` was called
+    #[cfg(feature = "spirv")]
+    pub fn strip_report(&self) -> Option<crate::model::StripReport> {
+        self.strip_report
+    }
+}
+
+/// The wrapped stages of a multi-stage shader
+#[derive(Debug)]
+pub struct WrappedStages<T> {
+    stages: Vec<WrappedShader<T>>,
+}
+
+impl<T> WrappedStages<T> {
+    pub fn new(stages: Vec<WrappedShader<T>>) -> Self {
+        Self { stages }
+    }
+
+    /// Return the stages, for attaching them to a program
+    pub fn shaders(&self) -> Vec<&dyn WrappedShaderDetails> {
+        self.stages
+            .iter()
+            .map(|stage| stage as &dyn WrappedShaderDetails)
+            .collect()
+    }
+
+    pub fn into_inner(self) -> Vec<WrappedShader<T>> {
+        self.stages
+    }
+}
+
+impl<T> std::ops::Deref for WrappedStages<T> {
+    type Target = [WrappedShader<T>];
+
+    fn deref(&self) -> &Self::Target {
+        &self.stages
+    }
 }
 
 pub trait WrappedShaderDetails {
@@ -59,11 +336,19 @@ pub trait WrappedShaderDetails {
     fn shader_struct_name(&self) -> &str;
     fn uniform_struct_name(&self) -> &str;
     fn uniform_locations_name(&self) -> &str;
+    fn reload_info(&self) -> Option<&ReloadInfo>;
+    /// Path of the module containing the generated code for this shader, relative to the root
+    /// of the generated code
+    fn module_path(&self) -> &[String];
+    /// Bindings which have to be restored after linking the program
+    fn removed_bindings(&self) -> &[RemovedBinding];
+    /// Treatment of the uniforms which are not used by this shader
+    fn unused_uniform_mode(&self) -> UnusedUniformMode;
 }
 
 impl<T> WrappedShaderDetails for WrappedShader<T> {
     fn uniforms(&self) -> &[crate::reflect::FoundUniform] {
-        self.result().uniforms()
+        &self.uniforms
     }
 
     fn shader_variable_name(&self) -> &str {
@@ -81,4 +366,29 @@ impl<T> WrappedShaderDetails for WrappedShader<T> {
     fn uniform_locations_name(&self) -> &str {
         &self.uniform_locations_name
     }
+
+    fn reload_info(&self) -> Option<&ReloadInfo> {
+        self.reload_info.as_ref()
+    }
+
+    fn module_path(&self) -> &[String] {
+        &self.module_path
+    }
+
+    fn removed_bindings(&self) -> &[RemovedBinding] {
+        &self.removed_bindings
+    }
+
+    fn unused_uniform_mode(&self) -> UnusedUniformMode {
+        self.unused_uniform_mode
+    }
+}
+
+/// Return the uniforms which have a supported type, the others can't be wrapped
+fn typed_uniforms(uniforms: &[FoundUniform]) -> Vec<FoundUniform> {
+    uniforms
+        .iter()
+        .filter(|uniform| uniform.ty.is_some())
+        .cloned()
+        .collect()
 }
@@ -20,18 +20,57 @@ pub enum Error {
     #[cfg(feature = "spirv_cross")]
     #[error("spirv_cross error: {0:?}")]
     SpirVCrossError(spirv_cross::ErrorCode),
+    #[cfg(feature = "spirv_cross")]
+    #[error("unsupported spirv_cross GLSL version: {0:?}")]
+    UnsupportedSpirVCrossVersion(spirv_cross::glsl::Version),
+    #[cfg(feature = "spirv_cross")]
+    #[error(
+        "uniform block {0} cannot be flattened, all its members must have the same scalar type"
+    )]
+    UnsupportedFlattenedBlock(String),
     #[error(
         "shader {0} was not wrapped before building the program, call Compiler::wrap_shader first"
     )]
     UnwrappedShader(String),
     #[error("program {0} was not wrapped before building the uniform set, call Compiler::wrap_program first")]
     UnwrappedProgram(String),
+    #[error("more than one shader would be generated in module {0}, use Compiler::set_shader_root to organize shaders by directory")]
+    DuplicateModule(String),
+    #[error("{0} is not compatible with the target: {}", .1.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    IncompatibleSource(String, Vec<crate::model::Incompatibility>),
+    #[error("{0} is not portable to the lint target: {}", .1.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    LintFailed(String, Vec<crate::model::LintWarning>),
     #[error("transpiling to {0} is not supported, please enable the transpile feature")]
     TranspilingNotSupported(GlslVersion),
+    #[error(
+        "GLSL {0} can't be used as a SPIR-V fallback, explicit uniform locations are required"
+    )]
+    InvalidFallbackVersion(GlslVersion),
     #[error("error parsing GLSL code: {0}")]
     GlslParseError(#[from] glsl::parser::ParseError),
-    #[error("could not determine type of shader object based on extension")]
-    CouldNotDetermineType,
+    #[error("could not minify GLSL code: {0}")]
+    MinifyFailed(String),
+    #[error("{0} does not declare any shader stage")]
+    NoShaderStages(String),
+    #[error("{0}: unknown shader stage {1}")]
+    UnknownShaderStage(String, String),
+    #[error("{0}: invalid annotation: {1}")]
+    InvalidAnnotation(String, String),
+    #[error("could not determine the type of shader object {0} based on its extension")]
+    CouldNotDetermineType(String),
+    #[error("unsupported type combination: {0}")]
+    UnsupportedTypeCombination(String),
+    #[cfg(feature = "rspirv")]
+    #[error("{0}: invalid SPIR-V: {1}")]
+    InvalidSpirV(String, String),
+    #[error("{0}: {1}")]
+    ReflectionFailed(String, Box<Error>),
+    #[cfg(feature = "shaderc")]
+    #[error("{0}: shaderc error: {1}")]
+    ShadercError(String, String),
+    #[cfg(feature = "shaderc")]
+    #[error("failed to initialize shaderc")]
+    ShadercInitFailed,
     #[error("a SPIR-V object is required for this operation")]
     SpirVObjectRequired,
     #[error("a source object is required for this operation")]
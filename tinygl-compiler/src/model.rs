@@ -1,7 +1,7 @@
 use std::path::{Path, PathBuf};
 
 use crate::{
-    reflect::{GlslReflectBackend, ReflectedObject},
+    reflect::{GlslReflectBackend, ReflectedObject, UniformAnnotations},
     Result, ShaderKind,
 };
 
@@ -36,6 +36,17 @@ pub struct ObjectInfo {
     pub source_path: SourcePath,
     /// Shader stage
     pub kind: ShaderKind,
+    /// Files included while preprocessing or compiling this shader
+    pub includes: Vec<PathBuf>,
+    /// `true` if this shader is one of the stages of a multi-stage source
+    pub multi_stage: bool,
+}
+
+impl ObjectInfo {
+    /// Attach the source path of this object to an error raised while reflecting it
+    pub(crate) fn reflection_failed(&self, error: crate::Error) -> crate::Error {
+        crate::Error::ReflectionFailed(self.source_path.to_string(), Box::new(error))
+    }
 }
 
 /// A shader object manipulated by tinygl
@@ -78,11 +89,21 @@ pub trait GlslCompiler {
         kind: ShaderKind,
         source_path: &str,
     ) -> Result<SpirVModule<'static>>;
+
+    /// Return the list of files included by the last compiled module
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
 
 pub trait GlslPreprocessor {
     fn preprocess_module(&mut self, source: &str, source_path: &str)
         -> Result<GlslModule<'static>>;
+
+    /// Return the list of files included by the last preprocessed module
+    fn take_includes(&mut self) -> Vec<PathBuf> {
+        Vec::new()
+    }
 }
 
 fn bytes_to_id(src: &[u8], kind: ShaderKind) -> ObjectInfo {
@@ -95,6 +116,8 @@ fn bytes_to_id(src: &[u8], kind: ShaderKind) -> ObjectInfo {
     ObjectInfo {
         source_path: SourcePath::Generated(source_path.into()),
         kind: kind.into(),
+        includes: Vec::new(),
+        multi_stage: false,
     }
 }
 
@@ -123,12 +146,29 @@ pub trait AsOutputFormat {
 }
 
 mod glsl;
-pub use self::glsl::GlslModule;
+pub use self::glsl::{FlattenedBlock, GlslModule, Lint, LintWarning, RemovedBinding};
+
+mod header;
+pub use header::{ExtensionBehavior, GlslExtension, GlslHeader, Incompatibility};
+
+mod stages;
 pub type GlslObject<'s> = ShaderObject<GlslModule<'s>>;
 
 #[cfg(feature = "spirv")]
 mod spirv;
 #[cfg(feature = "spirv")]
-pub use spirv::{GlslWithSpirVModule, SpirVModule};
+pub use spirv::{GlslWithSpirVModule, SpirVModule, StripReport};
 #[cfg(feature = "spirv")]
 pub type SpirVObject<'s> = ShaderObject<SpirVModule<'s>>;
+
+#[cfg(feature = "transpile")]
+mod transpile;
+#[cfg(feature = "transpile")]
+pub use transpile::{Precision, TranspileOptions};
+
+#[cfg(feature = "transpile")]
+mod foreign;
+#[cfg(feature = "transpile")]
+pub use foreign::ForeignModule;
+#[cfg(feature = "transpile")]
+pub type ForeignObject = ShaderObject<ForeignModule>;
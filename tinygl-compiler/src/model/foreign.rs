@@ -0,0 +1,133 @@
+use super::*;
+
+use crate::{HlslShaderModel, MslVersion, TargetType};
+
+/// Shader source for another graphics API, transpiled from SPIR-V
+///
+/// No tinygl wrappers can be generated for these modules, but they can be written to files.
+pub struct ForeignModule {
+    /// Transpiled source
+    source: String,
+    /// Target of the source
+    target: TargetType,
+}
+
+impl ForeignModule {
+    /// Return the source of this module as a string
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Return the target type of this module
+    pub fn target(&self) -> TargetType {
+        self.target
+    }
+}
+
+impl<'s> SpirVModule<'s> {
+    /// Transpile this SPIR-V module to HLSL using spirv_cross
+    ///
+    /// # Parameters
+    ///
+    /// * `shader_model`: HLSL shader model to target
+    pub fn transpile_hlsl(&self, shader_model: HlslShaderModel) -> Result<ForeignModule> {
+        let module = spirv_cross::spirv::Module::from_words(self.as_bytes());
+        let mut ast = spirv_cross::spirv::Ast::<spirv_cross::hlsl::Target>::parse(&module)?;
+
+        let mut options = spirv_cross::hlsl::CompilerOptions::default();
+        options.shader_model = shader_model.into();
+        ast.set_compiler_options(&options)?;
+
+        Ok(ForeignModule {
+            source: ast.compile()?,
+            target: TargetType::Hlsl(shader_model),
+        })
+    }
+
+    /// Transpile this SPIR-V module to MSL using spirv_cross
+    ///
+    /// # Parameters
+    ///
+    /// * `version`: MSL version to target
+    pub fn transpile_msl(&self, version: MslVersion) -> Result<ForeignModule> {
+        let module = spirv_cross::spirv::Module::from_words(self.as_bytes());
+        let mut ast = spirv_cross::spirv::Ast::<spirv_cross::msl::Target>::parse(&module)?;
+
+        let mut options = spirv_cross::msl::CompilerOptions::default();
+        options.version = version.into();
+        ast.set_compiler_options(&options)?;
+
+        Ok(ForeignModule {
+            source: ast.compile()?,
+            target: TargetType::Msl(version),
+        })
+    }
+}
+
+impl<'s> ShaderObject<SpirVModule<'s>> {
+    /// Transpile this SPIR-V object to HLSL
+    ///
+    /// # Parameters
+    ///
+    /// * `shader_model`: HLSL shader model to target
+    pub fn transpile_hlsl(&self, shader_model: HlslShaderModel) -> Result<ForeignObject> {
+        Ok(ShaderObject {
+            module: self.module.transpile_hlsl(shader_model)?,
+            info: self.info.clone(),
+        })
+    }
+
+    /// Transpile this SPIR-V object to MSL
+    ///
+    /// # Parameters
+    ///
+    /// * `version`: MSL version to target
+    pub fn transpile_msl(&self, version: MslVersion) -> Result<ForeignObject> {
+        Ok(ShaderObject {
+            module: self.module.transpile_msl(version)?,
+            info: self.info.clone(),
+        })
+    }
+}
+
+impl ShaderObject<ForeignModule> {
+    /// Return a file name for this object, based on its source path and target
+    ///
+    /// For example, `quad.vert` transpiled to HLSL is named `quad.vert.hlsl`. Stages of
+    /// multi-stage sources are named after the file and their stage, e.g. `effect.vert.hlsl`.
+    pub fn file_name(&self) -> String {
+        let name = match &self.info.source_path {
+            SourcePath::File(path) if self.info.multi_stage => format!(
+                "{}.{}",
+                path.file_stem().unwrap_or_default().to_string_lossy(),
+                self.info.kind.extension()
+            ),
+            SourcePath::File(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            // Generated names are base64 strings, which may contain slashes
+            SourcePath::Generated(name) => name.replace('/', "_"),
+        };
+
+        format!("{}.{}", name, self.module.target.extension())
+    }
+
+    /// Write the source of this object to a file
+    ///
+    /// # Parameters
+    ///
+    /// * `dest_dir`: directory to write the file to, using the name returned by `file_name`
+    ///
+    /// # Returns
+    ///
+    /// The path to the written file.
+    pub fn write(&self, dest_dir: impl AsRef<Path>) -> Result<PathBuf> {
+        std::fs::create_dir_all(dest_dir.as_ref())?;
+
+        let path = dest_dir.as_ref().join(self.file_name());
+        std::fs::write(&path, self.module.as_str())?;
+
+        Ok(path)
+    }
+}
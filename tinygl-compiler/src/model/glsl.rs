@@ -9,7 +9,34 @@ use ::glsl::syntax::{
 
 use crate::GlslVersion;
 
+mod lint;
+pub use lint::{Lint, LintWarning};
+
+mod minify;
+
+/// A binding which could not be expressed in the source of a shader
+///
+/// GLSL ES 3.00 doesn't support `layout(binding = N)`, so these bindings have to be restored
+/// after the program is linked.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RemovedBinding {
+    /// Sampler uniform, which should be set to the given texture unit
+    Sampler { name: String, binding: u32 },
+    /// Uniform block, which should be bound to the given binding point
+    UniformBlock { name: String, binding: u32 },
+}
+
+/// A uniform block which was turned into a plain uniform array
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FlattenedBlock {
+    /// Name of the block, which is also the name of the array
+    pub name: String,
+    /// Type of the array
+    pub ty: crate::types::ItemOrArrayType,
+}
+
 /// A GLSL shader
+#[derive(Debug)]
 pub struct GlslModule<'s> {
     /// Raw GLSL source
     source: Cow<'s, str>,
@@ -17,6 +44,10 @@ pub struct GlslModule<'s> {
     ast: Option<TranslationUnit>,
     /// Parsed (or assumed) version
     version: Option<GlslVersion>,
+    /// Bindings removed from the source
+    removed_bindings: Vec<RemovedBinding>,
+    /// Uniform blocks flattened into arrays
+    flattened_blocks: Vec<FlattenedBlock>,
 }
 
 impl<'s> GlslModule<'s> {
@@ -47,6 +78,8 @@ impl<'s> GlslModule<'s> {
             source: Cow::Owned(source),
             ast: None,
             version: None,
+            removed_bindings: Vec::new(),
+            flattened_blocks: Vec::new(),
         })
     }
 
@@ -60,6 +93,8 @@ impl<'s> GlslModule<'s> {
             source: Cow::Borrowed(source),
             ast: None,
             version: None,
+            removed_bindings: Vec::new(),
+            flattened_blocks: Vec::new(),
         })
     }
 
@@ -72,29 +107,10 @@ impl<'s> GlslModule<'s> {
 
             for decl in &ast.0 {
                 if let ExternalDeclaration::Preprocessor(Preprocessor::Version(version)) = decl {
-                    self.version = match version.profile {
-                        Some(PreprocessorVersionProfile::ES) => match version.version {
-                            100 => Some(GlslVersion::V1_00Es),
-                            300 => Some(GlslVersion::V3_00Es),
-                            _ => None,
-                        },
-                        _ => match version.version {
-                            110 => Some(GlslVersion::V1_10),
-                            120 => Some(GlslVersion::V1_20),
-                            130 => Some(GlslVersion::V1_30),
-                            140 => Some(GlslVersion::V1_40),
-                            150 => Some(GlslVersion::V1_50),
-                            330 => Some(GlslVersion::V3_30),
-                            400 => Some(GlslVersion::V4_00),
-                            410 => Some(GlslVersion::V4_10),
-                            420 => Some(GlslVersion::V4_20),
-                            430 => Some(GlslVersion::V4_30),
-                            440 => Some(GlslVersion::V4_40),
-                            450 => Some(GlslVersion::V4_50),
-                            460 => Some(GlslVersion::V4_60),
-                            _ => None,
-                        },
-                    };
+                    self.version = GlslVersion::from_number(
+                        version.version as u32,
+                        version.profile == Some(PreprocessorVersionProfile::ES),
+                    );
                 }
             }
 
@@ -112,6 +128,73 @@ impl<'s> GlslModule<'s> {
     pub fn version(&self) -> Option<GlslVersion> {
         self.version
     }
+
+    /// Read the version, profile and extensions declared by this source
+    ///
+    /// Unlike `parse`, this only looks at preprocessor directives.
+    pub fn header(&self) -> super::GlslHeader {
+        super::GlslHeader::scan(self.as_str())
+    }
+
+    /// Get the bindings which were removed from this source when it was generated
+    ///
+    /// These bindings have to be restored at link time for the shader to behave as the
+    /// original.
+    pub fn removed_bindings(&self) -> &[RemovedBinding] {
+        &self.removed_bindings
+    }
+
+    /// Get the uniform blocks which were flattened into uniform arrays when this source was
+    /// generated
+    pub fn flattened_blocks(&self) -> &[FlattenedBlock] {
+        &self.flattened_blocks
+    }
+
+    /// Record bindings and uniform blocks which were altered when generating this source
+    ///
+    /// # Parameters
+    ///
+    /// * `removed_bindings`: bindings removed from the source
+    /// * `flattened_blocks`: uniform blocks turned into arrays
+    #[cfg(feature = "transpile")]
+    pub(crate) fn with_transpile_info(
+        mut self,
+        removed_bindings: Vec<RemovedBinding>,
+        flattened_blocks: Vec<FlattenedBlock>,
+    ) -> Self {
+        self.removed_bindings = removed_bindings;
+        self.flattened_blocks = flattened_blocks;
+        self
+    }
+}
+
+/// Type names misspelled by the transpiler, with their correct spelling
+const TYPE_TYPOS: &[(&str, &str)] = &[
+    ("isampler2MS", "isampler2DMS"),
+    ("usamplerDMSArray", "usampler2DMSArray"),
+];
+
+/// Parse preprocessed GLSL code into an AST
+///
+/// Line directives from shaderc use file names, which the parser doesn't support, so they are
+/// removed first.
+///
+/// # Parameters
+///
+/// * `source`: preprocessed GLSL code
+fn parse_preprocessed(source: &str) -> Result<TranslationUnit> {
+    let source: String = source
+        .lines()
+        .filter(|l| {
+            let l = l.trim_start();
+            !l.starts_with("#line")
+                && !l.starts_with("#extension GL_GOOGLE_include_directive")
+                && !l.starts_with("#extension GL_GOOGLE_cpp_style_line_directive")
+        })
+        .flat_map(|l| [l, "\n"])
+        .collect();
+
+    Ok(TranslationUnit::parse(&source)?)
 }
 
 #[cfg(feature = "spirv")]
@@ -129,7 +212,7 @@ impl<'s> ShaderObject<GlslModule<'s>> {
 
         let kind = match kind {
             Some(kind) => kind.into(),
-            None => ShaderKind::from_path(&p).ok_or(crate::Error::CouldNotDetermineType)?,
+            None => ShaderKind::from_path(&p)?,
         };
 
         Ok(Self {
@@ -137,6 +220,8 @@ impl<'s> ShaderObject<GlslModule<'s>> {
             info: ObjectInfo {
                 kind,
                 source_path: SourcePath::File(p),
+                includes: Vec::new(),
+                multi_stage: false,
             },
         })
     }
@@ -183,9 +268,12 @@ impl<'s> ShaderObject<GlslModule<'s>> {
             self.info.source_path.to_string().as_str(),
         )?;
 
+        let mut info = self.info;
+        info.includes.extend(preprocessor.take_includes());
+
         Ok(ShaderObject {
             module: source_module,
-            info: self.info,
+            info,
         })
     }
 
@@ -200,15 +288,26 @@ impl<'s> ShaderObject<GlslModule<'s>> {
             self.info.source_path.to_string().as_str(),
         )?;
 
+        let mut info = self.info;
+        info.includes.extend(compiler.take_includes());
+
         Ok(ShaderObject {
             module: GlslWithSpirVModule::new(self.module, binary_module),
-            info: self.info,
+            info,
         })
     }
 
     pub fn reflect(self, reflector: &dyn GlslReflectBackend) -> Result<ReflectedObject<Self>> {
-        let uniforms = reflector.reflect(self.module.as_str())?;
-        Ok(ReflectedObject::new(self, uniforms))
+        let mut uniforms = reflector
+            .reflect(self.module.as_str())
+            .map_err(|error| self.info.reflection_failed(error))?;
+        let annotation_errors = UniformAnnotations::apply(
+            &mut uniforms,
+            self.module.as_str(),
+            &self.info.source_path.to_string(),
+        );
+
+        Ok(ReflectedObject::new(self, uniforms).with_annotation_errors(annotation_errors))
     }
 }
 
@@ -0,0 +1,1227 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+
+use ::glsl::syntax::*;
+use ::glsl::transpiler::glsl as transpiler;
+use ::glsl::visitor::{Host, Visit, Visitor};
+
+use super::{GlslModule, TYPE_TYPOS};
+use crate::{GlslVersion, ShaderKind};
+
+/// Portability problem checked by [`GlslModule::lint`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lint {
+    /// Implicit conversions between scalar types, such as `float x = 1;`, which GLSL ES doesn't
+    /// perform
+    ImplicitConversion,
+    /// Floats in fragment shaders and samplers without a precision qualifier nor a default
+    /// precision
+    MissingPrecision,
+    /// `for` loops which don't follow the restrictions of GLSL ES 1.00: the index has to be
+    /// initialized, compared and stepped with constant expressions, and not modified in the body
+    NonConstantLoopIndex,
+    /// Built-in functions and variables which are not available in the target version
+    UnavailableBuiltin,
+}
+
+impl Lint {
+    /// Every lint, e.g. for setting all their levels at once
+    pub const ALL: &'static [Lint] = &[
+        Self::ImplicitConversion,
+        Self::MissingPrecision,
+        Self::NonConstantLoopIndex,
+        Self::UnavailableBuiltin,
+    ];
+
+    /// Return the name of this lint, as shown in warnings
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::ImplicitConversion => "implicit_conversion",
+            Self::MissingPrecision => "missing_precision",
+            Self::NonConstantLoopIndex => "non_constant_loop_index",
+            Self::UnavailableBuiltin => "unavailable_builtin",
+        }
+    }
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Portability problem found in a GLSL source
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LintWarning {
+    /// Lint which found the problem
+    pub lint: Lint,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} [{}]", self.message, self.lint)
+    }
+}
+
+impl GlslModule<'_> {
+    /// Check this source for problems which prevent it from compiling on a GLSL ES target
+    ///
+    /// Desktop GLSL accepts everything the lints look for, so nothing is reported for desktop
+    /// targets.
+    ///
+    /// # Parameters
+    ///
+    /// * `kind`: stage of this shader, since default precisions depend on it
+    /// * `target`: GLSL version this source should be portable to
+    ///
+    /// # Returns
+    ///
+    /// The problems found, or an error if the source could not be parsed.
+    pub fn lint(&self, kind: ShaderKind, target: GlslVersion) -> crate::Result<Vec<LintWarning>> {
+        if !target.is_es() {
+            return Ok(Vec::new());
+        }
+
+        let ast = match &self.ast {
+            Some(ast) => Cow::Borrowed(ast),
+            None => Cow::Owned(super::parse_preprocessed(self.as_str())?),
+        };
+
+        let mut linter = Linter {
+            kind,
+            target,
+            warnings: Vec::new(),
+            scopes: vec![HashMap::new()],
+            functions: HashMap::new(),
+            function: None,
+            subject: None,
+            default_precisions: Vec::new(),
+            unqualified_float: None,
+            unqualified_opaque: Vec::new(),
+        };
+
+        linter.translation_unit(&ast);
+        Ok(linter.warnings)
+    }
+}
+
+/// Scalar type of a value, which implicit conversions apply to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    Bool,
+    Int,
+    UInt,
+    Float,
+    Double,
+}
+
+impl Scalar {
+    fn of(ty: &TypeSpecifierNonArray) -> Option<Self> {
+        use TypeSpecifierNonArray as T;
+
+        match ty {
+            T::Bool | T::BVec2 | T::BVec3 | T::BVec4 => Some(Self::Bool),
+            T::Int | T::IVec2 | T::IVec3 | T::IVec4 => Some(Self::Int),
+            T::UInt | T::UVec2 | T::UVec3 | T::UVec4 => Some(Self::UInt),
+            T::Float
+            | T::Vec2
+            | T::Vec3
+            | T::Vec4
+            | T::Mat2
+            | T::Mat3
+            | T::Mat4
+            | T::Mat23
+            | T::Mat24
+            | T::Mat32
+            | T::Mat34
+            | T::Mat42
+            | T::Mat43 => Some(Self::Float),
+            T::Double
+            | T::DVec2
+            | T::DVec3
+            | T::DVec4
+            | T::DMat2
+            | T::DMat3
+            | T::DMat4
+            | T::DMat23
+            | T::DMat24
+            | T::DMat32
+            | T::DMat34
+            | T::DMat42
+            | T::DMat43 => Some(Self::Double),
+            _ => None,
+        }
+    }
+
+    /// Scalar type of the constructor of a built-in type
+    fn of_constructor(name: &str) -> Option<Self> {
+        let float_shape = |name: &str| {
+            matches!(
+                name,
+                "vec2"
+                    | "vec3"
+                    | "vec4"
+                    | "mat2"
+                    | "mat3"
+                    | "mat4"
+                    | "mat2x2"
+                    | "mat2x3"
+                    | "mat2x4"
+                    | "mat3x2"
+                    | "mat3x3"
+                    | "mat3x4"
+                    | "mat4x2"
+                    | "mat4x3"
+                    | "mat4x4"
+            )
+        };
+        let vector_shape = |name: &str| name.starts_with("vec") && float_shape(name);
+
+        match name {
+            "bool" => Some(Self::Bool),
+            "int" => Some(Self::Int),
+            "uint" => Some(Self::UInt),
+            "float" => Some(Self::Float),
+            "double" => Some(Self::Double),
+            _ if float_shape(name) => Some(Self::Float),
+            _ => match (name.get(..1), name.get(1..)) {
+                (Some("b"), Some(rest)) if vector_shape(rest) => Some(Self::Bool),
+                (Some("i"), Some(rest)) if vector_shape(rest) => Some(Self::Int),
+                (Some("u"), Some(rest)) if vector_shape(rest) => Some(Self::UInt),
+                (Some("d"), Some(rest)) if float_shape(rest) => Some(Self::Double),
+                _ => None,
+            },
+        }
+    }
+
+    /// Return `true` if desktop GLSL implicitly converts values of this type to `target`
+    fn converts_to(self, target: Self) -> bool {
+        matches!(
+            (self, target),
+            (Self::Int, Self::UInt)
+                | (Self::Int | Self::UInt, Self::Float)
+                | (Self::Int | Self::UInt | Self::Float, Self::Double)
+        )
+    }
+}
+
+impl fmt::Display for Scalar {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool => write!(f, "bool"),
+            Self::Int => write!(f, "int"),
+            Self::UInt => write!(f, "uint"),
+            Self::Float => write!(f, "float"),
+            Self::Double => write!(f, "double"),
+        }
+    }
+}
+
+/// Built-in functions whose arguments and result share the same scalar type
+const GENERIC_FUNCTIONS: &[&str] = &[
+    "abs",
+    "sign",
+    "floor",
+    "ceil",
+    "fract",
+    "mod",
+    "min",
+    "max",
+    "clamp",
+    "mix",
+    "step",
+    "smoothstep",
+    "sqrt",
+    "inversesqrt",
+    "pow",
+    "exp",
+    "log",
+    "exp2",
+    "log2",
+    "sin",
+    "cos",
+    "tan",
+    "asin",
+    "acos",
+    "atan",
+    "radians",
+    "degrees",
+    "round",
+    "trunc",
+    "roundEven",
+    "length",
+    "distance",
+    "dot",
+    "cross",
+    "normalize",
+    "faceforward",
+    "reflect",
+    "refract",
+    "dFdx",
+    "dFdy",
+    "fwidth",
+    "matrixCompMult",
+    "outerProduct",
+    "transpose",
+    "determinant",
+    "inverse",
+];
+
+/// Scalar types of built-in variables
+const BUILTIN_VARIABLES: &[(&str, Scalar)] = &[
+    ("gl_Position", Scalar::Float),
+    ("gl_PointSize", Scalar::Float),
+    ("gl_FragCoord", Scalar::Float),
+    ("gl_FragDepth", Scalar::Float),
+    ("gl_PointCoord", Scalar::Float),
+    ("gl_FragColor", Scalar::Float),
+    ("gl_FrontFacing", Scalar::Bool),
+    ("gl_VertexID", Scalar::Int),
+    ("gl_InstanceID", Scalar::Int),
+];
+
+const ES_100: Option<(u32, u32)> = Some((100, 100));
+const SINCE_ES_300: Option<(u32, u32)> = Some((300, 320));
+const SINCE_ES_310: Option<(u32, u32)> = Some((310, 320));
+const SINCE_ES_320: Option<(u32, u32)> = Some((320, 320));
+const DESKTOP: Option<(u32, u32)> = None;
+
+/// Built-ins which are missing from some GLSL ES versions, with the first and last ES versions
+/// which provide them
+const BUILTINS: &[(&str, Option<(u32, u32)>)] = &[
+    // Replaced by `texture` and fragment outputs in GLSL ES 3.00
+    ("texture2D", ES_100),
+    ("texture2DProj", ES_100),
+    ("texture2DLod", ES_100),
+    ("texture2DProjLod", ES_100),
+    ("textureCube", ES_100),
+    ("textureCubeLod", ES_100),
+    ("gl_FragColor", ES_100),
+    ("gl_FragData", ES_100),
+    ("texture", SINCE_ES_300),
+    ("textureProj", SINCE_ES_300),
+    ("textureLod", SINCE_ES_300),
+    ("textureOffset", SINCE_ES_300),
+    ("textureProjOffset", SINCE_ES_300),
+    ("textureLodOffset", SINCE_ES_300),
+    ("textureProjLod", SINCE_ES_300),
+    ("textureGrad", SINCE_ES_300),
+    ("textureGradOffset", SINCE_ES_300),
+    ("textureProjGrad", SINCE_ES_300),
+    ("textureSize", SINCE_ES_300),
+    ("texelFetch", SINCE_ES_300),
+    ("texelFetchOffset", SINCE_ES_300),
+    ("round", SINCE_ES_300),
+    ("roundEven", SINCE_ES_300),
+    ("trunc", SINCE_ES_300),
+    ("modf", SINCE_ES_300),
+    ("sinh", SINCE_ES_300),
+    ("cosh", SINCE_ES_300),
+    ("tanh", SINCE_ES_300),
+    ("asinh", SINCE_ES_300),
+    ("acosh", SINCE_ES_300),
+    ("atanh", SINCE_ES_300),
+    ("isnan", SINCE_ES_300),
+    ("isinf", SINCE_ES_300),
+    ("outerProduct", SINCE_ES_300),
+    ("transpose", SINCE_ES_300),
+    ("determinant", SINCE_ES_300),
+    ("inverse", SINCE_ES_300),
+    ("floatBitsToInt", SINCE_ES_300),
+    ("floatBitsToUint", SINCE_ES_300),
+    ("intBitsToFloat", SINCE_ES_300),
+    ("uintBitsToFloat", SINCE_ES_300),
+    ("packSnorm2x16", SINCE_ES_300),
+    ("unpackSnorm2x16", SINCE_ES_300),
+    ("packUnorm2x16", SINCE_ES_300),
+    ("unpackUnorm2x16", SINCE_ES_300),
+    ("packHalf2x16", SINCE_ES_300),
+    ("unpackHalf2x16", SINCE_ES_300),
+    ("gl_VertexID", SINCE_ES_300),
+    ("gl_InstanceID", SINCE_ES_300),
+    ("gl_FragDepth", SINCE_ES_300),
+    ("textureGather", SINCE_ES_310),
+    ("textureGatherOffset", SINCE_ES_310),
+    ("imageLoad", SINCE_ES_310),
+    ("imageStore", SINCE_ES_310),
+    ("imageSize", SINCE_ES_310),
+    ("bitfieldExtract", SINCE_ES_310),
+    ("bitfieldInsert", SINCE_ES_310),
+    ("bitfieldReverse", SINCE_ES_310),
+    ("bitCount", SINCE_ES_310),
+    ("findLSB", SINCE_ES_310),
+    ("findMSB", SINCE_ES_310),
+    ("uaddCarry", SINCE_ES_310),
+    ("usubBorrow", SINCE_ES_310),
+    ("umulExtended", SINCE_ES_310),
+    ("imulExtended", SINCE_ES_310),
+    ("frexp", SINCE_ES_310),
+    ("ldexp", SINCE_ES_310),
+    ("packUnorm4x8", SINCE_ES_310),
+    ("unpackUnorm4x8", SINCE_ES_310),
+    ("packSnorm4x8", SINCE_ES_310),
+    ("unpackSnorm4x8", SINCE_ES_310),
+    ("barrier", SINCE_ES_310),
+    ("memoryBarrier", SINCE_ES_310),
+    ("gl_NumWorkGroups", SINCE_ES_310),
+    ("gl_WorkGroupID", SINCE_ES_310),
+    ("gl_LocalInvocationID", SINCE_ES_310),
+    ("gl_GlobalInvocationID", SINCE_ES_310),
+    ("gl_LocalInvocationIndex", SINCE_ES_310),
+    ("gl_HelperInvocation", SINCE_ES_310),
+    ("fma", SINCE_ES_320),
+    ("interpolateAtCentroid", SINCE_ES_320),
+    ("interpolateAtSample", SINCE_ES_320),
+    ("interpolateAtOffset", SINCE_ES_320),
+    ("gl_SampleID", SINCE_ES_320),
+    ("gl_SamplePosition", SINCE_ES_320),
+    ("gl_SampleMaskIn", SINCE_ES_320),
+    ("gl_SampleMask", SINCE_ES_320),
+    ("gl_PrimitiveID", SINCE_ES_320),
+    ("gl_Layer", SINCE_ES_320),
+    ("texture1D", DESKTOP),
+    ("texture1DProj", DESKTOP),
+    ("texture1DLod", DESKTOP),
+    ("texture3D", DESKTOP),
+    ("texture3DLod", DESKTOP),
+    ("shadow1D", DESKTOP),
+    ("shadow2D", DESKTOP),
+    ("shadow2DProj", DESKTOP),
+    ("textureQueryLod", DESKTOP),
+    ("textureQueryLevels", DESKTOP),
+    ("textureSamples", DESKTOP),
+    ("dFdxFine", DESKTOP),
+    ("dFdyFine", DESKTOP),
+    ("dFdxCoarse", DESKTOP),
+    ("dFdyCoarse", DESKTOP),
+    ("fwidthFine", DESKTOP),
+    ("fwidthCoarse", DESKTOP),
+    ("packDouble2x32", DESKTOP),
+    ("unpackDouble2x32", DESKTOP),
+    ("ftransform", DESKTOP),
+    ("gl_ClipDistance", DESKTOP),
+    ("gl_CullDistance", DESKTOP),
+    ("gl_ViewportIndex", DESKTOP),
+    ("gl_BaseVertex", DESKTOP),
+    ("gl_BaseInstance", DESKTOP),
+    ("gl_DrawID", DESKTOP),
+    ("gl_Vertex", DESKTOP),
+    ("gl_Normal", DESKTOP),
+    ("gl_Color", DESKTOP),
+    ("gl_MultiTexCoord0", DESKTOP),
+    ("gl_TexCoord", DESKTOP),
+    ("gl_ModelViewMatrix", DESKTOP),
+    ("gl_ProjectionMatrix", DESKTOP),
+    ("gl_ModelViewProjectionMatrix", DESKTOP),
+    ("gl_NormalMatrix", DESKTOP),
+];
+
+/// Variable declared by the shader
+struct Variable {
+    scalar: Option<Scalar>,
+    constant: bool,
+}
+
+/// Walks a translation unit, keeping track of the scalar types of expressions
+struct Linter {
+    kind: ShaderKind,
+    target: GlslVersion,
+    warnings: Vec<LintWarning>,
+    /// Declared variables, the first scope holds globals
+    scopes: Vec<HashMap<String, Variable>>,
+    /// Return types of user functions, `None` for overloads with different return types
+    functions: HashMap<String, Option<Scalar>>,
+    /// Name and return type of the function being checked
+    function: Option<(String, Option<Scalar>)>,
+    /// Description of what the expression being checked is assigned to
+    subject: Option<String>,
+    /// Types given a default precision by `precision` statements
+    default_precisions: Vec<TypeSpecifierNonArray>,
+    /// First float declaration without a precision qualifier
+    unqualified_float: Option<String>,
+    /// Opaque types declared without a precision qualifier, with their first declaration
+    unqualified_opaque: Vec<(TypeSpecifierNonArray, String)>,
+}
+
+impl Linter {
+    fn warn(&mut self, lint: Lint, message: String) {
+        let warning = LintWarning { lint, message };
+        if !self.warnings.contains(&warning) {
+            self.warnings.push(warning);
+        }
+    }
+
+    /// Describe the function being checked, for warnings
+    fn location(&self) -> String {
+        match &self.function {
+            Some((name, _)) => format!("in `{}`", name),
+            None => "in a global declaration".to_owned(),
+        }
+    }
+
+    /// Describe where the current expression is, for warnings
+    fn context(&self) -> String {
+        match &self.subject {
+            Some(subject) => format!("for {} {}", subject, self.location()),
+            None => self.location(),
+        }
+    }
+
+    /// Check what is assigned to `subject`, so warnings can name it
+    fn with_subject<R>(&mut self, subject: String, f: impl FnOnce(&mut Self) -> R) -> R {
+        let previous = self.subject.replace(subject);
+        let result = f(self);
+        self.subject = previous;
+        result
+    }
+
+    fn translation_unit(&mut self, ast: &TranslationUnit) {
+        // Functions may be called before their definition through prototypes
+        for declaration in (ast.0).0.iter() {
+            let prototype = match declaration {
+                ExternalDeclaration::FunctionDefinition(function) => &function.prototype,
+                ExternalDeclaration::Declaration(Declaration::FunctionPrototype(prototype)) => {
+                    prototype
+                }
+                _ => continue,
+            };
+
+            let scalar = Scalar::of(&prototype.ty.ty.ty);
+            self.functions
+                .entry(prototype.name.0.clone())
+                .and_modify(|existing| {
+                    if *existing != scalar {
+                        *existing = None;
+                    }
+                })
+                .or_insert(scalar);
+        }
+
+        for declaration in (ast.0).0.iter() {
+            match declaration {
+                ExternalDeclaration::FunctionDefinition(function) => self.function(function),
+                ExternalDeclaration::Declaration(declaration) => self.declaration(declaration),
+                ExternalDeclaration::Preprocessor(_) => {}
+            }
+        }
+
+        // Fragment shaders have no default float precision
+        if let Some(declaration) = self.unqualified_float.take() {
+            if self.kind == ShaderKind::Fragment
+                && !self
+                    .default_precisions
+                    .contains(&TypeSpecifierNonArray::Float)
+            {
+                self.warn(
+                    Lint::MissingPrecision,
+                    format!(
+                        "float has no default precision in fragment shaders, {} needs a precision qualifier",
+                        declaration
+                    ),
+                );
+            }
+        }
+
+        for (ty, declaration) in std::mem::take(&mut self.unqualified_opaque) {
+            if !self.default_precisions.contains(&ty) {
+                self.warn(
+                    Lint::MissingPrecision,
+                    format!(
+                        "{} has no default precision, {} needs a precision qualifier",
+                        type_name(&ty),
+                        declaration
+                    ),
+                );
+            }
+        }
+    }
+
+    fn declare(&mut self, name: &Identifier, scalar: Option<Scalar>, constant: bool) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.0.clone(), Variable { scalar, constant });
+        }
+    }
+
+    fn function(&mut self, function: &FunctionDefinition) {
+        let prototype = &function.prototype;
+        self.check_precision(
+            prototype.ty.qualifier.as_ref(),
+            &prototype.ty.ty.ty,
+            Some(&prototype.name),
+        );
+        self.function = Some((prototype.name.0.clone(), Scalar::of(&prototype.ty.ty.ty)));
+
+        // The body shares the scope of the parameters
+        self.scopes.push(HashMap::new());
+
+        for parameter in &prototype.parameters {
+            match parameter {
+                FunctionParameterDeclaration::Named(qualifier, declarator) => {
+                    self.check_precision(
+                        qualifier.as_ref(),
+                        &declarator.ty.ty,
+                        Some(&declarator.ident.ident),
+                    );
+
+                    let scalar = if declarator.ty.array_specifier.is_none()
+                        && declarator.ident.array_spec.is_none()
+                    {
+                        Scalar::of(&declarator.ty.ty)
+                    } else {
+                        None
+                    };
+
+                    self.declare(
+                        &declarator.ident.ident,
+                        scalar,
+                        is_const(qualifier.as_ref()),
+                    );
+                }
+                FunctionParameterDeclaration::Unnamed(qualifier, ty) => {
+                    self.check_precision(qualifier.as_ref(), &ty.ty, None);
+                }
+            }
+        }
+
+        for statement in &function.statement.statement_list {
+            self.statement(statement);
+        }
+
+        self.scopes.pop();
+        self.function = None;
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            Declaration::FunctionPrototype(prototype) => {
+                self.check_precision(
+                    prototype.ty.qualifier.as_ref(),
+                    &prototype.ty.ty.ty,
+                    Some(&prototype.name),
+                );
+            }
+            Declaration::InitDeclaratorList(list) => {
+                let head = &list.head;
+                let qualifier = head.ty.qualifier.as_ref();
+                self.check_precision(qualifier, &head.ty.ty.ty, head.name.as_ref());
+
+                // Arrays are not checked
+                let scalar = if head.ty.ty.array_specifier.is_none() {
+                    Scalar::of(&head.ty.ty.ty)
+                } else {
+                    None
+                };
+                let constant = is_const(qualifier);
+
+                if let Some(name) = &head.name {
+                    let scalar = scalar.filter(|_| head.array_specifier.is_none());
+                    self.initializer(head.initializer.as_ref(), scalar, name);
+                    self.declare(name, scalar, constant);
+                }
+
+                for item in &list.tail {
+                    let scalar = scalar.filter(|_| item.ident.array_spec.is_none());
+                    self.initializer(item.initializer.as_ref(), scalar, &item.ident.ident);
+                    self.declare(&item.ident.ident, scalar, constant);
+                }
+            }
+            Declaration::Precision(_, ty) => {
+                self.default_precisions.push(ty.ty.clone());
+            }
+            Declaration::Block(block) => {
+                for field in &block.fields {
+                    self.check_precision(
+                        field.qualifier.as_ref(),
+                        &field.ty.ty,
+                        Some(&field.identifiers.0[0].ident),
+                    );
+                }
+
+                match &block.identifier {
+                    Some(identifier) => self.declare(&identifier.ident, None, false),
+                    // Members of unnamed blocks are globals
+                    None => {
+                        for field in &block.fields {
+                            let scalar = Scalar::of(&field.ty.ty)
+                                .filter(|_| field.ty.array_specifier.is_none());
+
+                            for identifier in &field.identifiers.0 {
+                                let scalar = scalar.filter(|_| identifier.array_spec.is_none());
+                                self.declare(&identifier.ident, scalar, false);
+                            }
+                        }
+                    }
+                }
+            }
+            Declaration::Global(_, _) => {}
+        }
+    }
+
+    /// Record declarations without a precision qualifier, which need a default precision
+    ///
+    /// # Parameters
+    ///
+    /// * `qualifier`: qualifier of the declaration
+    /// * `ty`: declared type
+    /// * `name`: declared identifier, for warnings
+    fn check_precision(
+        &mut self,
+        qualifier: Option<&TypeQualifier>,
+        ty: &TypeSpecifierNonArray,
+        name: Option<&Identifier>,
+    ) {
+        if let TypeSpecifierNonArray::Struct(specifier) = ty {
+            for field in &specifier.fields.0 {
+                self.check_precision(
+                    field.qualifier.as_ref(),
+                    &field.ty.ty,
+                    Some(&field.identifiers.0[0].ident),
+                );
+            }
+
+            return;
+        }
+
+        let qualified = qualifier.is_some_and(|qualifier| {
+            qualifier
+                .qualifiers
+                .0
+                .iter()
+                .any(|spec| matches!(spec, TypeQualifierSpec::Precision(_)))
+        });
+
+        if qualified {
+            return;
+        }
+
+        let declaration = || match name {
+            Some(name) => format!("`{}` {}", name.0, self.location()),
+            None => format!("a parameter {}", self.location()),
+        };
+
+        if Scalar::of(ty) == Some(Scalar::Float) {
+            if self.unqualified_float.is_none() {
+                self.unqualified_float = Some(declaration());
+            }
+        } else if needs_precision(ty) && !self.unqualified_opaque.iter().any(|(t, _)| t == ty) {
+            let declaration = declaration();
+            self.unqualified_opaque.push((ty.clone(), declaration));
+        }
+    }
+
+    fn initializer(
+        &mut self,
+        initializer: Option<&Initializer>,
+        expected: Option<Scalar>,
+        name: &Identifier,
+    ) {
+        match initializer {
+            Some(Initializer::Simple(expr)) => self.with_subject(format!("`{}`", name.0), |this| {
+                let found = this.expr(expr);
+                this.convert(found, expected);
+            }),
+            // Initializer lists are for arrays and structs, which are not checked
+            Some(Initializer::List(items)) => {
+                for item in &items.0 {
+                    self.initializer(Some(item), None, name);
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Compound(compound) => {
+                self.scopes.push(HashMap::new());
+
+                for statement in &compound.statement_list {
+                    self.statement(statement);
+                }
+
+                self.scopes.pop();
+            }
+            Statement::Simple(simple) => self.simple_statement(simple),
+        }
+    }
+
+    fn simple_statement(&mut self, statement: &SimpleStatement) {
+        match statement {
+            SimpleStatement::Declaration(declaration) => self.declaration(declaration),
+            SimpleStatement::Expression(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            SimpleStatement::Selection(selection) => {
+                self.expr(&selection.cond);
+
+                match &selection.rest {
+                    SelectionRestStatement::Statement(statement) => self.statement(statement),
+                    SelectionRestStatement::Else(if_statement, else_statement) => {
+                        self.statement(if_statement);
+                        self.statement(else_statement);
+                    }
+                }
+            }
+            SimpleStatement::Switch(switch) => {
+                self.expr(&switch.head);
+                self.scopes.push(HashMap::new());
+
+                for statement in &switch.body {
+                    self.statement(statement);
+                }
+
+                self.scopes.pop();
+            }
+            SimpleStatement::CaseLabel(CaseLabel::Case(expr)) => {
+                self.expr(expr);
+            }
+            SimpleStatement::CaseLabel(CaseLabel::Def) => {}
+            SimpleStatement::Iteration(iteration) => {
+                self.scopes.push(HashMap::new());
+                self.iteration(iteration);
+                self.scopes.pop();
+            }
+            SimpleStatement::Jump(JumpStatement::Return(Some(expr))) => {
+                let expected = self.function.as_ref().and_then(|(_, scalar)| *scalar);
+                self.with_subject("the return value".to_owned(), |this| {
+                    let found = this.expr(expr);
+                    this.convert(found, expected);
+                });
+            }
+            SimpleStatement::Jump(_) => {}
+        }
+    }
+
+    fn iteration(&mut self, iteration: &IterationStatement) {
+        match iteration {
+            IterationStatement::While(condition, body) => {
+                self.condition(condition);
+                self.statement(body);
+            }
+            IterationStatement::DoWhile(body, condition) => {
+                self.statement(body);
+                self.expr(condition);
+            }
+            IterationStatement::For(init, rest, body) => {
+                if self.target == GlslVersion::V1_00Es {
+                    if let Err(problem) = self.check_loop(init, rest, body) {
+                        let index = match init {
+                            ForInitStatement::Declaration(declaration) => {
+                                match declaration.as_ref() {
+                                    Declaration::InitDeclaratorList(list) => {
+                                        list.head.name.as_ref()
+                                    }
+                                    _ => None,
+                                }
+                            }
+                            ForInitStatement::Expression(_) => None,
+                        };
+                        let subject = match index {
+                            Some(index) => format!("for loop over `{}`", index.0),
+                            None => "for loop".to_owned(),
+                        };
+                        let message = format!(
+                            "{} {} is not supported by GLSL {}, {}",
+                            subject,
+                            self.location(),
+                            self.target,
+                            problem
+                        );
+                        self.warn(Lint::NonConstantLoopIndex, message);
+                    }
+                }
+
+                match init {
+                    ForInitStatement::Expression(expr) => {
+                        if let Some(expr) = expr {
+                            self.expr(expr);
+                        }
+                    }
+                    ForInitStatement::Declaration(declaration) => self.declaration(declaration),
+                }
+
+                if let Some(condition) = &rest.condition {
+                    self.condition(condition);
+                }
+
+                if let Some(expr) = &rest.post_expr {
+                    self.expr(expr);
+                }
+
+                self.statement(body);
+            }
+        }
+    }
+
+    fn condition(&mut self, condition: &Condition) {
+        match condition {
+            Condition::Expr(expr) => {
+                self.expr(expr);
+            }
+            Condition::Assignment(ty, name, initializer) => {
+                let scalar = Scalar::of(&ty.ty.ty);
+                self.initializer(Some(initializer), scalar, name);
+                self.declare(name, scalar, false);
+            }
+        }
+    }
+
+    /// Check a `for` loop against the restrictions of GLSL ES 1.00
+    ///
+    /// # Returns
+    ///
+    /// The reason why the loop is not supported, if any.
+    fn check_loop(
+        &self,
+        init: &ForInitStatement,
+        rest: &ForRestStatement,
+        body: &Statement,
+    ) -> Result<(), &'static str> {
+        let index = match init {
+            ForInitStatement::Declaration(declaration) => match declaration.as_ref() {
+                Declaration::InitDeclaratorList(InitDeclaratorList { head, tail })
+                    if tail.is_empty()
+                        && matches!(
+                            head.ty.ty.ty,
+                            TypeSpecifierNonArray::Int | TypeSpecifierNonArray::Float
+                        )
+                        && matches!(
+                            &head.initializer,
+                            Some(Initializer::Simple(expr)) if self.is_constant(expr)
+                        ) =>
+                {
+                    head.name.as_ref()
+                }
+                _ => None,
+            },
+            ForInitStatement::Expression(_) => None,
+        }
+        .ok_or("the index must be declared with a constant initializer")?;
+
+        let is_index = |expr: &Expr| matches!(expr, Expr::Variable(name) if name == index);
+
+        match &rest.condition {
+            Some(Condition::Expr(expr)) => match expr.as_ref() {
+                Expr::Binary(
+                    BinaryOp::LT
+                    | BinaryOp::GT
+                    | BinaryOp::LTE
+                    | BinaryOp::GTE
+                    | BinaryOp::Equal
+                    | BinaryOp::NonEqual,
+                    left,
+                    right,
+                ) if is_index(left) && self.is_constant(right) => {}
+                _ => return Err("the index must be compared to a constant expression"),
+            },
+            _ => return Err("the index must be compared to a constant expression"),
+        }
+
+        match rest.post_expr.as_deref() {
+            Some(Expr::PostInc(expr) | Expr::PostDec(expr))
+            | Some(Expr::Unary(UnaryOp::Inc | UnaryOp::Dec, expr))
+                if is_index(expr) => {}
+            Some(Expr::Assignment(left, AssignmentOp::Add | AssignmentOp::Sub, right))
+                if is_index(left) && self.is_constant(right) => {}
+            _ => return Err("the index must be stepped by a constant expression"),
+        }
+
+        let mut modified = IndexModified {
+            index,
+            modified: false,
+        };
+        body.visit(&mut modified);
+        if modified.modified {
+            return Err("the index must not be modified in the loop body");
+        }
+
+        Ok(())
+    }
+
+    /// Return `true` if an expression is built from literals and constants only
+    fn is_constant(&self, expr: &Expr) -> bool {
+        match expr {
+            Expr::IntConst(_)
+            | Expr::UIntConst(_)
+            | Expr::BoolConst(_)
+            | Expr::FloatConst(_)
+            | Expr::DoubleConst(_) => true,
+            Expr::Variable(name) => self
+                .lookup(&name.0)
+                .is_some_and(|variable| variable.constant),
+            Expr::Unary(_, expr) => self.is_constant(expr),
+            Expr::Binary(_, left, right) => self.is_constant(left) && self.is_constant(right),
+            Expr::FunCall(FunIdentifier::Identifier(name), args) => {
+                Scalar::of_constructor(&name.0).is_some()
+                    && args.iter().all(|arg| self.is_constant(arg))
+            }
+            _ => false,
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Variable> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Report implicit conversions from `found` to `expected`
+    fn convert(&mut self, found: Option<Scalar>, expected: Option<Scalar>) {
+        if let (Some(found), Some(expected)) = (found, expected) {
+            if found != expected && found.converts_to(expected) {
+                let message = format!(
+                    "implicit conversion from {} to {} {}",
+                    found,
+                    expected,
+                    self.context()
+                );
+                self.warn(Lint::ImplicitConversion, message);
+            }
+        }
+    }
+
+    /// Return the scalar type two operands are converted to, reporting the conversion
+    fn common(&mut self, left: Option<Scalar>, right: Option<Scalar>) -> Option<Scalar> {
+        match (left, right) {
+            (Some(left), Some(right)) if left == right => Some(left),
+            (Some(left), Some(right)) if left.converts_to(right) => {
+                self.convert(Some(left), Some(right));
+                Some(right)
+            }
+            (Some(left), Some(right)) if right.converts_to(left) => {
+                self.convert(Some(right), Some(left));
+                Some(left)
+            }
+            (Some(_), Some(_)) => None,
+            (left, right) => left.or(right),
+        }
+    }
+
+    /// Check an expression
+    ///
+    /// # Returns
+    ///
+    /// The scalar type of the expression, if it could be found.
+    fn expr(&mut self, expr: &Expr) -> Option<Scalar> {
+        match expr {
+            Expr::Variable(name) => self.variable(&name.0),
+            Expr::IntConst(_) => Some(Scalar::Int),
+            Expr::UIntConst(_) => Some(Scalar::UInt),
+            Expr::BoolConst(_) => Some(Scalar::Bool),
+            Expr::FloatConst(_) => Some(Scalar::Float),
+            Expr::DoubleConst(_) => Some(Scalar::Double),
+            Expr::Unary(op, expr) => {
+                let scalar = self.expr(expr);
+                match op {
+                    UnaryOp::Not => Some(Scalar::Bool),
+                    _ => scalar,
+                }
+            }
+            Expr::Binary(op, left, right) => {
+                let left = self.expr(left);
+                let right = self.expr(right);
+
+                match op {
+                    BinaryOp::Or | BinaryOp::Xor | BinaryOp::And => Some(Scalar::Bool),
+                    BinaryOp::Equal
+                    | BinaryOp::NonEqual
+                    | BinaryOp::LT
+                    | BinaryOp::GT
+                    | BinaryOp::LTE
+                    | BinaryOp::GTE => {
+                        self.common(left, right);
+                        Some(Scalar::Bool)
+                    }
+                    // Shifts don't need both operands to have the same signedness
+                    BinaryOp::LShift | BinaryOp::RShift => left,
+                    _ => self.common(left, right),
+                }
+            }
+            Expr::Ternary(condition, if_true, if_false) => {
+                self.expr(condition);
+                let if_true = self.expr(if_true);
+                let if_false = self.expr(if_false);
+                self.common(if_true, if_false)
+            }
+            Expr::Assignment(left, op, right) => {
+                let subject = match assigned_variable(left) {
+                    Some(name) => format!("`{}`", name.0),
+                    None => "an assignment".to_owned(),
+                };
+                let left = self.expr(left);
+
+                self.with_subject(subject, |this| {
+                    let right = this.expr(right);
+
+                    if !matches!(op, AssignmentOp::LShift | AssignmentOp::RShift) {
+                        this.convert(right, left);
+                    }
+                });
+
+                left
+            }
+            Expr::Bracket(expr, specifier) => {
+                for dimension in &specifier.dimensions.0 {
+                    if let ArraySpecifierDimension::ExplicitlySized(index) = dimension {
+                        self.expr(index);
+                    }
+                }
+
+                self.expr(expr)
+            }
+            Expr::FunCall(identifier, args) => self.call(identifier, args),
+            // Swizzles keep the scalar type, struct fields are unknown
+            Expr::Dot(expr, _) => self.expr(expr),
+            Expr::PostInc(expr) | Expr::PostDec(expr) => self.expr(expr),
+            Expr::Comma(left, right) => {
+                self.expr(left);
+                self.expr(right)
+            }
+        }
+    }
+
+    fn variable(&mut self, name: &str) -> Option<Scalar> {
+        if let Some(variable) = self.lookup(name) {
+            return variable.scalar;
+        }
+
+        self.check_builtin(name);
+
+        BUILTIN_VARIABLES
+            .iter()
+            .find(|(builtin, _)| *builtin == name)
+            .map(|(_, scalar)| *scalar)
+    }
+
+    fn call(&mut self, identifier: &FunIdentifier, args: &[Expr]) -> Option<Scalar> {
+        let scalars: Vec<_> = args.iter().map(|arg| self.expr(arg)).collect();
+
+        let name = match identifier {
+            FunIdentifier::Identifier(name) => &name.0,
+            FunIdentifier::Expr(expr) => {
+                self.expr(expr);
+                return None;
+            }
+        };
+
+        if let Some(scalar) = self.functions.get(name) {
+            return *scalar;
+        }
+
+        self.check_builtin(name);
+
+        // Constructors convert their arguments explicitly
+        if let Some(scalar) = Scalar::of_constructor(name) {
+            return Some(scalar);
+        }
+
+        if !GENERIC_FUNCTIONS.contains(&name.as_str()) {
+            return None;
+        }
+
+        // Desktop GLSL converts the arguments to the widest of their types
+        let mut widest = None;
+        for scalar in scalars.iter().flatten().copied() {
+            widest = match widest {
+                None => Some(scalar),
+                Some(current) if scalar == current || scalar.converts_to(current) => widest,
+                Some(current) if current.converts_to(scalar) => Some(scalar),
+                // Mixing with a boolean selector is allowed
+                Some(_) => return scalars.first().copied().flatten(),
+            };
+        }
+
+        self.with_subject(format!("the arguments of `{}`", name), |this| {
+            for scalar in scalars {
+                this.convert(scalar, widest);
+            }
+        });
+
+        widest
+    }
+
+    fn check_builtin(&mut self, name: &str) {
+        let versions = match BUILTINS.iter().find(|(builtin, _)| *builtin == name) {
+            Some((_, versions)) => *versions,
+            None => return,
+        };
+
+        let number = self.target.number();
+        let available =
+            matches!(versions, Some((first, last)) if first <= number && number <= last);
+
+        if !available {
+            let message = format!(
+                "`{}` used {} is not available in GLSL {}",
+                name,
+                self.location(),
+                self.target
+            );
+            self.warn(Lint::UnavailableBuiltin, message);
+        }
+    }
+}
+
+/// Return the variable an assignment writes to, through swizzles, fields and indices
+fn assigned_variable(expr: &Expr) -> Option<&Identifier> {
+    match expr {
+        Expr::Variable(name) => Some(name),
+        Expr::Dot(expr, _) | Expr::Bracket(expr, _) => assigned_variable(expr),
+        _ => None,
+    }
+}
+
+/// Finds assignments to a loop index
+struct IndexModified<'i> {
+    index: &'i Identifier,
+    modified: bool,
+}
+
+impl Visitor for IndexModified<'_> {
+    fn visit_expr(&mut self, expr: &Expr) -> Visit {
+        let target = match expr {
+            Expr::Assignment(target, _, _)
+            | Expr::PostInc(target)
+            | Expr::PostDec(target)
+            | Expr::Unary(UnaryOp::Inc | UnaryOp::Dec, target) => target,
+            _ => return Visit::Children,
+        };
+
+        if matches!(target.as_ref(), Expr::Variable(name) if name == self.index) {
+            self.modified = true;
+        }
+
+        Visit::Children
+    }
+}
+
+fn is_const(qualifier: Option<&TypeQualifier>) -> bool {
+    qualifier.is_some_and(|qualifier| {
+        qualifier
+            .qualifiers
+            .0
+            .iter()
+            .any(|spec| matches!(spec, TypeQualifierSpec::Storage(StorageQualifier::Const)))
+    })
+}
+
+/// Return the GLSL name of a type
+fn type_name(ty: &TypeSpecifierNonArray) -> String {
+    let mut name = String::new();
+    transpiler::show_type_specifier_non_array(&mut name, ty);
+
+    match TYPE_TYPOS.iter().find(|(typo, _)| *typo == name) {
+        Some((_, fixed)) => (*fixed).to_owned(),
+        None => name,
+    }
+}
+
+/// Return `true` if GLSL ES has no default precision for an opaque type
+fn needs_precision(ty: &TypeSpecifierNonArray) -> bool {
+    let name = type_name(ty);
+    (name.contains("sampler") || name.contains("image"))
+        && !matches!(name.as_str(), "sampler2D" | "samplerCube")
+}
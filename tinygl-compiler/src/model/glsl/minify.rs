@@ -0,0 +1,620 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use ::glsl::parser::Parse;
+use ::glsl::syntax::*;
+use ::glsl::transpiler::glsl as transpiler;
+use ::glsl::visitor::{Host, Visit, Visitor};
+
+use super::{GlslModule, TYPE_TYPOS};
+
+/// GLSL keywords and reserved words which are short enough to be generated as local names
+const RESERVED_WORDS: &[&str] = &[
+    "do", "if", "in", "for", "int", "out", "mat2", "mat3", "mat4", "vec2", "vec3", "vec4", "bool",
+    "case", "else", "flat", "goto", "half", "inout", "long", "uint", "void", "asm", "enum", "true",
+];
+
+impl GlslModule<'_> {
+    /// Minify this source for embedding
+    ///
+    /// Comments, `#line` directives and unnecessary whitespace are removed, and the parameters and
+    /// local variables of functions are given short names. Global declarations, including
+    /// uniforms and interface variables, keep their names so reflection and uniform lookups still
+    /// work.
+    ///
+    /// # Returns
+    ///
+    /// The minified source, or an error if the source could not be parsed.
+    pub fn minify(&self) -> crate::Result<GlslModule<'static>> {
+        let mut ast = super::parse_preprocessed(self.as_str())?;
+
+        // Generated names must not clash with any existing name
+        let mut names = NameCollector::default();
+        ast.visit(&mut names);
+
+        let mut renamer = Renamer {
+            reserved: names.names,
+            macro_words: names.macro_words,
+            scopes: Vec::new(),
+            next: 0,
+        };
+
+        for declaration in (ast.0).0.iter_mut() {
+            if let ExternalDeclaration::FunctionDefinition(function) = declaration {
+                renamer.function(function);
+            }
+        }
+
+        // The transpiler writes `lowp` as `low`, which can't be told apart from a name
+        let mut low = LowPrecision::default();
+        ast.visit(&mut low);
+        if low.qualifier && low.name {
+            return Err(crate::Error::MinifyFailed(
+                "low can't be used as a name along with lowp".to_owned(),
+            ));
+        }
+
+        let mut printed = TokenWriter {
+            output: String::new(),
+            fix_low_precision: low.qualifier,
+            // Only fix the spelling if it can't be a user-defined name
+            fix_types: TYPE_TYPOS
+                .iter()
+                .filter(|(typo, _)| !renamer.reserved.contains(*typo))
+                .copied()
+                .collect(),
+        };
+
+        for declaration in (ast.0).0.iter() {
+            match declaration {
+                ExternalDeclaration::FunctionDefinition(function) => printed.function(function),
+                ExternalDeclaration::Declaration(declaration) => printed.declaration(declaration),
+                ExternalDeclaration::Preprocessor(preprocessor) => {
+                    // Directives must start on their own line
+                    printed.output.push('\n');
+                    transpiler::show_preprocessor(&mut printed, preprocessor)
+                }
+            }
+        }
+
+        // Make sure the printed code means the same as the original
+        let minified = compact_whitespace(&printed.output);
+        if TranslationUnit::parse(&minified).ok().as_ref() != Some(&ast) {
+            return Err(crate::Error::MinifyFailed(
+                "the minified code doesn't match the original".to_owned(),
+            ));
+        }
+
+        let mut module = GlslModule::from_string(minified)?;
+        module.removed_bindings = self.removed_bindings().to_vec();
+        module.flattened_blocks = self.flattened_blocks().to_vec();
+        Ok(module)
+    }
+}
+
+/// Collects every name used in a translation unit
+#[derive(Default)]
+struct NameCollector {
+    names: HashSet<String>,
+    macro_words: HashSet<String>,
+}
+
+impl Visitor for NameCollector {
+    fn visit_identifier(&mut self, identifier: &Identifier) -> Visit {
+        self.names.insert(identifier.0.clone());
+        Visit::Children
+    }
+
+    fn visit_type_name(&mut self, type_name: &TypeName) -> Visit {
+        self.names.insert(type_name.0.clone());
+        Visit::Children
+    }
+
+    fn visit_preprocessor_define(&mut self, define: &PreprocessorDefine) -> Visit {
+        let value = match define {
+            PreprocessorDefine::ObjectLike { value, .. } => value,
+            PreprocessorDefine::FunctionLike { value, .. } => value,
+        };
+
+        // Macros may refer to locals by name, so those can't be renamed
+        self.macro_words.extend(
+            value
+                .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .filter(|word| !word.is_empty())
+                .map(str::to_owned),
+        );
+
+        Visit::Children
+    }
+}
+
+/// Finds uses of the `lowp` qualifier and of the `low` name
+#[derive(Default)]
+struct LowPrecision {
+    qualifier: bool,
+    name: bool,
+}
+
+impl Visitor for LowPrecision {
+    fn visit_identifier(&mut self, identifier: &Identifier) -> Visit {
+        self.name |= identifier.0 == "low";
+        Visit::Children
+    }
+
+    fn visit_type_name(&mut self, type_name: &TypeName) -> Visit {
+        self.name |= type_name.0 == "low";
+        Visit::Children
+    }
+
+    fn visit_precision_qualifier(&mut self, qualifier: &PrecisionQualifier) -> Visit {
+        self.qualifier |= *qualifier == PrecisionQualifier::Low;
+        Visit::Children
+    }
+}
+
+/// Renames the parameters and local variables of functions
+struct Renamer {
+    reserved: HashSet<String>,
+    macro_words: HashSet<String>,
+    scopes: Vec<HashMap<String, String>>,
+    next: usize,
+}
+
+impl Renamer {
+    fn function(&mut self, function: &mut FunctionDefinition) {
+        // Names can be reused across functions
+        self.next = 0;
+        self.scopes.push(HashMap::new());
+
+        for parameter in &mut function.prototype.parameters {
+            if let FunctionParameterDeclaration::Named(_, declarator) = parameter {
+                self.type_specifier(&mut declarator.ty);
+                self.array_specifier(&mut declarator.ident.array_spec);
+                self.declare(&mut declarator.ident.ident);
+            }
+        }
+
+        // The body shares the scope of the parameters
+        for statement in &mut function.statement.statement_list {
+            self.statement(statement);
+        }
+
+        self.scopes.pop();
+    }
+
+    /// Give a new name to a local declaration
+    fn declare(&mut self, identifier: &mut Identifier) {
+        if self.macro_words.contains(&identifier.0) {
+            return;
+        }
+
+        let name = loop {
+            let name = short_name(self.next);
+            self.next += 1;
+
+            if !self.reserved.contains(&name) && !RESERVED_WORDS.contains(&name.as_str()) {
+                break name;
+            }
+        };
+
+        let original = std::mem::replace(&mut identifier.0, name.clone());
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(original, name);
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Compound(compound) => self.scoped(|this| {
+                for statement in &mut compound.statement_list {
+                    this.statement(statement);
+                }
+            }),
+            Statement::Simple(simple) => self.simple_statement(simple),
+        }
+    }
+
+    fn simple_statement(&mut self, statement: &mut SimpleStatement) {
+        match statement {
+            SimpleStatement::Declaration(declaration) => self.declaration(declaration),
+            SimpleStatement::Expression(expr) => {
+                if let Some(expr) = expr {
+                    self.expr(expr);
+                }
+            }
+            SimpleStatement::Selection(selection) => {
+                self.expr(&mut selection.cond);
+                match &mut selection.rest {
+                    SelectionRestStatement::Statement(statement) => self.statement(statement),
+                    SelectionRestStatement::Else(if_true, if_false) => {
+                        self.statement(if_true);
+                        self.statement(if_false);
+                    }
+                }
+            }
+            SimpleStatement::Switch(switch) => {
+                self.expr(&mut switch.head);
+                self.scoped(|this| {
+                    for statement in &mut switch.body {
+                        this.statement(statement);
+                    }
+                });
+            }
+            SimpleStatement::CaseLabel(CaseLabel::Case(expr)) => self.expr(expr),
+            SimpleStatement::CaseLabel(CaseLabel::Def) => {}
+            SimpleStatement::Iteration(iteration) => self.scoped(|this| match iteration {
+                IterationStatement::While(condition, body) => {
+                    this.condition(condition);
+                    this.statement(body);
+                }
+                IterationStatement::DoWhile(body, condition) => {
+                    this.statement(body);
+                    this.expr(condition);
+                }
+                IterationStatement::For(init, rest, body) => {
+                    match init {
+                        ForInitStatement::Expression(Some(expr)) => this.expr(expr),
+                        ForInitStatement::Expression(None) => {}
+                        ForInitStatement::Declaration(declaration) => this.declaration(declaration),
+                    }
+
+                    if let Some(condition) = &mut rest.condition {
+                        this.condition(condition);
+                    }
+
+                    if let Some(expr) = &mut rest.post_expr {
+                        this.expr(expr);
+                    }
+
+                    this.statement(body);
+                }
+            }),
+            SimpleStatement::Jump(JumpStatement::Return(Some(expr))) => self.expr(expr),
+            SimpleStatement::Jump(_) => {}
+        }
+    }
+
+    fn condition(&mut self, condition: &mut Condition) {
+        match condition {
+            Condition::Expr(expr) => self.expr(expr),
+            Condition::Assignment(ty, identifier, initializer) => {
+                self.type_specifier(&mut ty.ty);
+                self.initializer(initializer);
+                self.declare(identifier);
+            }
+        }
+    }
+
+    fn declaration(&mut self, declaration: &mut Declaration) {
+        // Local blocks, prototypes and precision statements declare no local variable
+        if let Declaration::InitDeclaratorList(list) = declaration {
+            self.type_specifier(&mut list.head.ty.ty);
+            self.array_specifier(&mut list.head.array_specifier);
+
+            // Variables are in scope after their initializer
+            if let Some(initializer) = &mut list.head.initializer {
+                self.initializer(initializer);
+            }
+            if let Some(name) = &mut list.head.name {
+                self.declare(name);
+            }
+
+            for declaration in &mut list.tail {
+                self.array_specifier(&mut declaration.ident.array_spec);
+                if let Some(initializer) = &mut declaration.initializer {
+                    self.initializer(initializer);
+                }
+                self.declare(&mut declaration.ident.ident);
+            }
+        }
+    }
+
+    fn initializer(&mut self, initializer: &mut Initializer) {
+        match initializer {
+            Initializer::Simple(expr) => self.expr(expr),
+            Initializer::List(initializers) => {
+                for initializer in initializers.0.iter_mut() {
+                    self.initializer(initializer);
+                }
+            }
+        }
+    }
+
+    fn type_specifier(&mut self, ty: &mut TypeSpecifier) {
+        self.array_specifier(&mut ty.array_specifier);
+    }
+
+    fn array_specifier(&mut self, array_specifier: &mut Option<ArraySpecifier>) {
+        if let Some(array_specifier) = array_specifier {
+            self.dimensions(array_specifier);
+        }
+    }
+
+    fn dimensions(&mut self, array_specifier: &mut ArraySpecifier) {
+        for dimension in array_specifier.dimensions.0.iter_mut() {
+            if let ArraySpecifierDimension::ExplicitlySized(expr) = dimension {
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn expr(&mut self, expr: &mut Expr) {
+        match expr {
+            Expr::Variable(identifier) => {
+                let renamed = self
+                    .scopes
+                    .iter()
+                    .rev()
+                    .find_map(|scope| scope.get(&identifier.0));
+
+                if let Some(renamed) = renamed {
+                    identifier.0 = renamed.clone();
+                }
+            }
+            Expr::IntConst(_)
+            | Expr::UIntConst(_)
+            | Expr::BoolConst(_)
+            | Expr::FloatConst(_)
+            | Expr::DoubleConst(_) => {}
+            Expr::Unary(_, expr) | Expr::PostInc(expr) | Expr::PostDec(expr) => self.expr(expr),
+            Expr::Binary(_, lhs, rhs) | Expr::Assignment(lhs, _, rhs) | Expr::Comma(lhs, rhs) => {
+                self.expr(lhs);
+                self.expr(rhs);
+            }
+            Expr::Ternary(condition, if_true, if_false) => {
+                self.expr(condition);
+                self.expr(if_true);
+                self.expr(if_false);
+            }
+            Expr::Bracket(expr, array_specifier) => {
+                self.expr(expr);
+                self.dimensions(array_specifier);
+            }
+            Expr::FunCall(function, arguments) => {
+                // Function and constructor names are global
+                if let FunIdentifier::Expr(expr) = function {
+                    self.expr(expr);
+                }
+
+                for argument in arguments {
+                    self.expr(argument);
+                }
+            }
+            // Field and swizzle names are not variables
+            Expr::Dot(expr, _) => self.expr(expr),
+        }
+    }
+}
+
+/// Output of the minifier
+///
+/// Expressions, types and directives are written by the transpiler of the `glsl` crate.
+/// Statements are written here, since the transpiler drops some of their separators.
+///
+/// The transpiler doesn't separate binary and unary operators either, which turns `a - -b` into
+/// `a--b`. Operators are always written by separate calls, so a space is inserted between them.
+struct TokenWriter {
+    output: String,
+    fix_low_precision: bool,
+    fix_types: Vec<(&'static str, &'static str)>,
+}
+
+impl TokenWriter {
+    fn function(&mut self, function: &FunctionDefinition) {
+        transpiler::show_function_prototype(self, &function.prototype);
+        self.compound_statement(&function.statement);
+    }
+
+    fn declaration(&mut self, declaration: &Declaration) {
+        match declaration {
+            // The transpiler omits the precision keyword
+            Declaration::Precision(qualifier, ty) => {
+                self.push("precision ");
+                transpiler::show_precision_qualifier(self, qualifier);
+                self.push(" ");
+                transpiler::show_type_specifier(self, ty);
+                self.push(";");
+            }
+            declaration => transpiler::show_declaration(self, declaration),
+        }
+    }
+
+    fn compound_statement(&mut self, compound: &CompoundStatement) {
+        self.push("{");
+        for statement in &compound.statement_list {
+            self.statement(statement);
+        }
+        self.push("}");
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Compound(compound) => self.compound_statement(compound),
+            Statement::Simple(simple) => self.simple_statement(simple),
+        }
+    }
+
+    fn simple_statement(&mut self, statement: &SimpleStatement) {
+        match statement {
+            SimpleStatement::Declaration(declaration) => self.declaration(declaration),
+            SimpleStatement::Expression(expr) => {
+                if let Some(expr) = expr {
+                    transpiler::show_expr(self, expr);
+                }
+                self.push(";");
+            }
+            SimpleStatement::Selection(selection) => {
+                self.push("if(");
+                transpiler::show_expr(self, &selection.cond);
+                self.push(")");
+
+                match &selection.rest {
+                    SelectionRestStatement::Statement(statement) => self.statement(statement),
+                    SelectionRestStatement::Else(if_true, if_false) => {
+                        self.statement(if_true);
+                        self.push(" else ");
+                        self.statement(if_false);
+                    }
+                }
+            }
+            SimpleStatement::Switch(switch) => {
+                self.push("switch(");
+                transpiler::show_expr(self, &switch.head);
+                self.push("){");
+                for statement in &switch.body {
+                    self.statement(statement);
+                }
+                self.push("}");
+            }
+            SimpleStatement::CaseLabel(CaseLabel::Case(expr)) => {
+                self.push("case ");
+                transpiler::show_expr(self, expr);
+                self.push(":");
+            }
+            SimpleStatement::CaseLabel(CaseLabel::Def) => self.push("default:"),
+            SimpleStatement::Iteration(IterationStatement::While(condition, body)) => {
+                self.push("while(");
+                self.condition(condition);
+                self.push(")");
+                self.statement(body);
+            }
+            SimpleStatement::Iteration(IterationStatement::DoWhile(body, condition)) => {
+                self.push("do ");
+                self.statement(body);
+                self.push(" while(");
+                transpiler::show_expr(self, condition);
+                self.push(");");
+            }
+            SimpleStatement::Iteration(IterationStatement::For(init, rest, body)) => {
+                self.push("for(");
+                match init {
+                    ForInitStatement::Expression(expr) => {
+                        if let Some(expr) = expr {
+                            transpiler::show_expr(self, expr);
+                        }
+                        self.push(";");
+                    }
+                    ForInitStatement::Declaration(declaration) => self.declaration(declaration),
+                }
+
+                if let Some(condition) = &rest.condition {
+                    self.condition(condition);
+                }
+                self.push(";");
+
+                if let Some(expr) = &rest.post_expr {
+                    transpiler::show_expr(self, expr);
+                }
+                self.push(")");
+                self.statement(body);
+            }
+            SimpleStatement::Jump(jump) => transpiler::show_jump_statement(self, jump),
+        }
+    }
+
+    fn condition(&mut self, condition: &Condition) {
+        transpiler::show_condition(self, condition);
+    }
+
+    fn push(&mut self, s: &str) {
+        self.write_str(s).ok();
+    }
+}
+
+impl Write for TokenWriter {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        let s = if self.fix_low_precision && s == "low" {
+            "lowp"
+        } else if let Some((_, fixed)) = self.fix_types.iter().find(|(typo, _)| *typo == s) {
+            fixed
+        } else {
+            s
+        };
+
+        let is_operator = |c: char| "+-*/%<>=!&|^~".contains(c);
+
+        if let (Some(last), Some(first)) = (self.output.chars().last(), s.chars().next()) {
+            if is_operator(last) && is_operator(first) {
+                self.output.push(' ');
+            }
+        }
+
+        self.output.push_str(s);
+        Ok(())
+    }
+}
+
+/// Return the `index`-th short identifier: `a`..`Z`, then `aa`, `ab`...
+fn short_name(mut index: usize) -> String {
+    const FIRST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+    const REST: &[u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+    let mut name = vec![FIRST[index % FIRST.len()]];
+    index /= FIRST.len();
+
+    while index > 0 {
+        index -= 1;
+        name.push(REST[index % REST.len()]);
+        index /= REST.len();
+    }
+
+    String::from_utf8(name).unwrap()
+}
+
+/// Remove the whitespace which doesn't separate tokens
+///
+/// Preprocessor directives are kept on their own lines.
+fn compact_whitespace(source: &str) -> String {
+    let is_word = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+    let is_operator = |c: char| "+-*/%<>=!&|^".contains(c);
+
+    let mut output = String::with_capacity(source.len());
+    let mut last = None;
+
+    for line in source.lines() {
+        let line = line.trim();
+
+        if line.starts_with('#') {
+            if !output.is_empty() && !output.ends_with('\n') {
+                output.push('\n');
+            }
+
+            output.push_str(line);
+            output.push('\n');
+            last = None;
+            continue;
+        }
+
+        let mut pending_space = true;
+        for c in line.chars() {
+            if c.is_whitespace() {
+                pending_space = true;
+                continue;
+            }
+
+            if let Some(last) = last {
+                let separate =
+                    (is_word(last) && is_word(c)) || (is_operator(last) && is_operator(c));
+                if pending_space && separate {
+                    output.push(' ');
+                }
+            }
+
+            output.push(c);
+            last = Some(c);
+            pending_space = false;
+        }
+    }
+
+    if !output.ends_with('\n') {
+        output.push('\n');
+    }
+
+    output
+}
@@ -0,0 +1,182 @@
+use std::fmt;
+
+use crate::{GlslProfile, GlslVersion};
+
+/// Behavior requested by an `#extension` directive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExtensionBehavior {
+    Require,
+    Enable,
+    Warn,
+    Disable,
+}
+
+/// An `#extension` directive
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlslExtension {
+    /// Name of the extension, or `all`
+    pub name: String,
+    /// Requested behavior
+    pub behavior: ExtensionBehavior,
+}
+
+/// Version, profile and extensions declared by a GLSL source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GlslHeader {
+    /// Declared version, or `None` if it is not supported. Sources without a `#version`
+    /// directive are GLSL 1.10.
+    pub version: Option<GlslVersion>,
+    /// Declared profile, for desktop versions
+    pub profile: Option<GlslProfile>,
+    /// Extension directives
+    pub extensions: Vec<GlslExtension>,
+}
+
+impl GlslHeader {
+    /// Read the header directives of a GLSL source
+    ///
+    /// This only looks at preprocessor lines, so it works on sources the GLSL parser doesn't
+    /// support.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: GLSL source
+    pub fn scan(source: &str) -> Self {
+        let mut header = Self {
+            version: Some(GlslVersion::V1_10),
+            profile: None,
+            extensions: Vec::new(),
+        };
+
+        for line in source.lines() {
+            let line = line.trim_start();
+            let directive = match line.strip_prefix('#') {
+                Some(directive) => directive.replace(':', " "),
+                None => continue,
+            };
+
+            let mut tokens = directive.split_whitespace();
+            match tokens.next() {
+                Some("version") => {
+                    let number = tokens.next().and_then(|n| n.parse().ok());
+                    let profile = tokens.next();
+
+                    header.version = number
+                        .and_then(|number| GlslVersion::from_number(number, profile == Some("es")));
+                    header.profile = match profile {
+                        Some("core") => Some(GlslProfile::Core),
+                        Some("compatibility") => Some(GlslProfile::Compatibility),
+                        _ => None,
+                    };
+                }
+                Some("extension") => {
+                    let name = tokens.next();
+                    let behavior = match tokens.next() {
+                        Some("require") => Some(ExtensionBehavior::Require),
+                        Some("enable") => Some(ExtensionBehavior::Enable),
+                        Some("warn") => Some(ExtensionBehavior::Warn),
+                        Some("disable") => Some(ExtensionBehavior::Disable),
+                        _ => None,
+                    };
+
+                    if let (Some(name), Some(behavior)) = (name, behavior) {
+                        header.extensions.push(GlslExtension {
+                            name: name.to_owned(),
+                            behavior,
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        header
+    }
+
+    /// Return the effective profile of this source
+    ///
+    /// Desktop sources from version 1.50 default to the core profile, and older versions have
+    /// no profiles.
+    pub fn effective_profile(&self) -> Option<GlslProfile> {
+        match self.version {
+            Some(version) if !version.is_es() && version.number() >= 150 => {
+                Some(self.profile.unwrap_or(GlslProfile::Core))
+            }
+            _ => None,
+        }
+    }
+
+    /// Check this header against a GLSL target
+    ///
+    /// Required extensions are checked with a heuristic: desktop vendor extensions can't be used
+    /// with GLSL ES targets, and `GL_OES_*` extensions can't be used with desktop targets.
+    ///
+    /// # Parameters
+    ///
+    /// * `target`: GLSL version of the target
+    /// * `profile`: profile of the target, for desktop versions
+    ///
+    /// # Returns
+    ///
+    /// The reasons why this source is not compatible with the target, if any.
+    pub fn check(&self, target: GlslVersion, profile: GlslProfile) -> Vec<Incompatibility> {
+        let mut result = Vec::new();
+
+        match self.version {
+            Some(version) if !target.supports(version) => {
+                result.push(Incompatibility::Version(version))
+            }
+            Some(_) => {}
+            None => result.push(Incompatibility::UnknownVersion),
+        }
+
+        // Compatibility features are not available in core contexts
+        if !target.is_es()
+            && profile == GlslProfile::Core
+            && self.effective_profile() == Some(GlslProfile::Compatibility)
+        {
+            result.push(Incompatibility::Profile(GlslProfile::Compatibility));
+        }
+
+        for extension in &self.extensions {
+            if extension.behavior != ExtensionBehavior::Require {
+                continue;
+            }
+
+            let desktop_only = ["GL_ARB_", "GL_NV_", "GL_AMD_", "GL_ATI_"]
+                .iter()
+                .any(|prefix| extension.name.starts_with(prefix));
+            let es_only = extension.name.starts_with("GL_OES_");
+
+            if (target.is_es() && desktop_only) || (!target.is_es() && es_only) {
+                result.push(Incompatibility::Extension(extension.name.clone()));
+            }
+        }
+
+        result
+    }
+}
+
+/// Reason why a GLSL source is not compatible with a target
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Incompatibility {
+    /// The `#version` directive could not be understood
+    UnknownVersion,
+    /// The declared version is not supported by the target
+    Version(GlslVersion),
+    /// The declared profile is not supported by the target
+    Profile(GlslProfile),
+    /// A required extension is not available on the target
+    Extension(String),
+}
+
+impl fmt::Display for Incompatibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownVersion => write!(f, "unknown #version directive"),
+            Self::Version(version) => write!(f, "unsupported version {}", version),
+            Self::Profile(profile) => write!(f, "unsupported {} profile", profile),
+            Self::Extension(name) => write!(f, "unavailable extension {}", name),
+        }
+    }
+}
@@ -13,11 +13,50 @@ pub struct SpirVModule<'s> {
 }
 
 fn bytes_to_words(binary: &[u8]) -> Vec<u32> {
-    unsafe {
-        let s = &binary[..];
-        std::slice::from_raw_parts(s.as_ptr() as *mut u32, s.len() / std::mem::size_of::<u32>())
+    // The input may not be aligned for u32, so copy it word by word
+    binary
+        .chunks_exact(std::mem::size_of::<u32>())
+        .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+        .collect()
+}
+
+/// Parse a SPIR-V module using rspirv
+///
+/// rspirv panics on some malformed modules instead of returning an error, these panics are
+/// reported as parse errors.
+fn parse_words(binary: &[u32]) -> Result<rspirv::dr::Module> {
+    check_instruction_bounds(binary)?;
+
+    std::panic::catch_unwind(|| {
+        let mut loader = rspirv::dr::Loader::new();
+        rspirv::binary::parse_words(binary, &mut loader)
+            .map_err(|e| crate::Error::SpirVParseError(format!("{}", e)))?;
+
+        Ok(loader.module())
+    })
+    .unwrap_or_else(|_| Err(crate::Error::SpirVParseError("malformed module".to_owned())))
+}
+
+/// Check that every instruction of a SPIR-V module fits in its binary
+///
+/// rspirv reads operands past the end of the binary when the word count of the last instruction
+/// is too large, so this is rejected before parsing.
+fn check_instruction_bounds(binary: &[u32]) -> Result<()> {
+    // Instructions start after the 5 words of the header
+    let mut offset = 5;
+    while offset < binary.len() {
+        let word_count = (binary[offset] >> 16) as usize;
+        if word_count == 0 || offset + word_count > binary.len() {
+            return Err(crate::Error::SpirVParseError(format!(
+                "invalid word count {} for instruction at word {}",
+                word_count, offset
+            )));
+        }
+
+        offset += word_count;
     }
-    .to_vec()
+
+    Ok(())
 }
 
 impl<'s> SpirVModule<'s> {
@@ -52,13 +91,11 @@ impl<'s> SpirVModule<'s> {
     ///
     /// * `binary`: bytes representing the object
     pub(crate) fn from_words(binary: Vec<u32>) -> Result<SpirVModule<'static>> {
-        let mut loader = rspirv::dr::Loader::new();
-        rspirv::binary::parse_words(&binary, &mut loader)
-            .map_err(|e| crate::Error::SpirVParseError(format!("{}", e)))?;
+        let module = parse_words(&binary)?;
 
         Ok(SpirVModule {
             binary: Cow::Owned(binary),
-            module: loader.module(),
+            module,
         })
     }
 
@@ -69,13 +106,9 @@ impl<'s> SpirVModule<'s> {
     /// * `binary`: bytes representing the object
     #[allow(dead_code)]
     pub(crate) fn from_word_slice(binary: &'s [u32]) -> Result<Self> {
-        let mut loader = rspirv::dr::Loader::new();
-        rspirv::binary::parse_words(binary, &mut loader)
-            .map_err(|e| crate::Error::SpirVParseError(format!("{}", e)))?;
-
         Ok(Self {
             binary: Cow::Borrowed(binary),
-            module: loader.module(),
+            module: parse_words(binary)?,
         })
     }
 
@@ -83,6 +116,61 @@ impl<'s> SpirVModule<'s> {
     pub fn module(&self) -> &rspirv::dr::Module {
         &self.module
     }
+
+    /// Return a copy of this module without debug instructions
+    ///
+    /// This removes OpName, OpMemberName, OpSource, OpString, OpLine and related instructions.
+    /// Uniforms and blocks of the stripped module can't be looked up by name anymore, so this
+    /// should only be done after reflection has run.
+    pub fn strip_debug_info(&self) -> SpirVModule<'static> {
+        use rspirv::binary::Assemble;
+
+        let mut module = self.module.clone();
+
+        module.debug_string_source.clear();
+        module.debug_names.clear();
+        module.debug_module_processed.clear();
+
+        let is_line = |i: &rspirv::dr::Instruction| {
+            matches!(
+                i.class.opcode,
+                rspirv::spirv::Op::Line | rspirv::spirv::Op::NoLine
+            )
+        };
+
+        module.types_global_values.retain(|i| !is_line(i));
+        for function in &mut module.functions {
+            for block in &mut function.blocks {
+                block.instructions.retain(|i| !is_line(i));
+            }
+        }
+
+        SpirVModule {
+            binary: Cow::Owned(module.assemble()),
+            module,
+        }
+    }
+}
+
+/// Size report for a SPIR-V module stripped of its debug info
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StripReport {
+    /// Size of the original module, in bytes
+    pub original_size: usize,
+    /// Size of the stripped module, in bytes
+    pub stripped_size: usize,
+}
+
+impl std::fmt::Display for StripReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} -> {} bytes ({} bytes saved)",
+            self.original_size,
+            self.stripped_size,
+            self.original_size.saturating_sub(self.stripped_size)
+        )
+    }
 }
 
 impl<'s> ShaderObject<SpirVModule<'s>> {
@@ -97,7 +185,7 @@ impl<'s> ShaderObject<SpirVModule<'s>> {
 
         let kind = match kind {
             Some(kind) => kind.into(),
-            None => ShaderKind::from_path(&p).ok_or(crate::Error::CouldNotDetermineType)?,
+            None => ShaderKind::from_path(&p)?,
         };
 
         Ok(Self {
@@ -105,6 +193,8 @@ impl<'s> ShaderObject<SpirVModule<'s>> {
             info: ObjectInfo {
                 kind,
                 source_path: SourcePath::File(p),
+                includes: Vec::new(),
+                multi_stage: false,
             },
         })
     }
@@ -128,30 +218,157 @@ impl<'s> ShaderObject<SpirVModule<'s>> {
     }
 
     pub fn reflect(self, reflector: &dyn SpirVReflectBackend) -> Result<ReflectedObject<Self>> {
-        let uniforms = reflector.reflect(&self.module.module)?;
+        let uniforms = reflector
+            .reflect(&self.module.module)
+            .map_err(|error| self.info.reflection_failed(error))?;
         Ok(ReflectedObject::new(self, uniforms))
     }
 
+    /// Transpile this SPIR-V object to GLSL
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: GLSL version to target, or detailed transpile options
     #[cfg(feature = "transpile")]
     pub fn transpile(
         self,
-        version: crate::GlslVersion,
-    ) -> crate::Result<GlslWithSpirVModule<'static, 's>> {
+        options: impl Into<super::TranspileOptions>,
+    ) -> Result<ShaderObject<GlslWithSpirVModule<'static, 's>>> {
+        Ok(ShaderObject {
+            module: GlslWithSpirVModule {
+                glsl: self.module.transpile(options)?,
+                spirv: self.module,
+            },
+            info: self.info,
+        })
+    }
+}
+
+impl<'s> SpirVModule<'s> {
+    /// Transpile this SPIR-V module to GLSL using spirv_cross
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: GLSL version to target, or detailed transpile options
+    #[cfg(feature = "transpile")]
+    pub fn transpile(
+        &self,
+        options: impl Into<super::TranspileOptions>,
+    ) -> Result<GlslModule<'static>> {
+        use spirv_cross::spirv::Decoration;
+
+        let options = options.into();
+
         // Use spirv_cross to write valid code
-        let module = spirv_cross::spirv::Module::from_words(self.module.as_bytes());
+        let module = spirv_cross::spirv::Module::from_words(self.as_bytes());
         let mut ast = spirv_cross::spirv::Ast::<spirv_cross::glsl::Target>::parse(&module)?;
+        ast.set_compiler_options(&options.compiler_options())?;
 
-        // Target the right GLSL version
-        ast.set_compiler_options(&spirv_cross::glsl::CompilerOptions {
-            version: version.into(),
-            ..Default::default()
-        })
-        .unwrap();
+        let resources = ast.get_shader_resources()?;
+        let mut removed_bindings = Vec::new();
+        let mut flattened_blocks = Vec::new();
 
-        Ok(GlslWithSpirVModule {
-            glsl: glsl::GlslModule::from_string(ast.compile()?)?,
-            spirv: self.module,
-        })
+        // Record the bindings spirv_cross won't be able to write
+        if !options.supports_binding_layout() {
+            for resource in resources.sampled_images.iter() {
+                removed_bindings.push(glsl::RemovedBinding::Sampler {
+                    name: resource.name.clone(),
+                    binding: ast.get_decoration(resource.id, Decoration::Binding)?,
+                });
+            }
+        }
+
+        for resource in resources.uniform_buffers.iter() {
+            if options.flatten_uniform_buffers {
+                // Flattened blocks are arrays of 4-component vectors
+                let size = ast.get_declared_struct_size(resource.base_type_id)?;
+                let base_type =
+                    block_base_type(&self.module, resource.base_type_id).ok_or_else(|| {
+                        crate::Error::UnsupportedFlattenedBlock(resource.name.clone())
+                    })?;
+
+                ast.flatten_buffer_block(resource.id)?;
+                flattened_blocks.push(glsl::FlattenedBlock {
+                    name: resource.name.clone(),
+                    ty: crate::types::ItemOrArrayType::array(
+                        crate::types::ItemOrArrayType::vector(
+                            crate::types::ItemOrArrayType::atom(base_type),
+                            4,
+                        )?,
+                        size.div_ceil(16),
+                    )?,
+                });
+            } else if !options.supports_binding_layout() {
+                removed_bindings.push(glsl::RemovedBinding::UniformBlock {
+                    name: resource.name.clone(),
+                    binding: ast.get_decoration(resource.id, Decoration::Binding)?,
+                });
+            }
+        }
+
+        // Separate images and samplers are combined for GLSL. spirv_cross can't return an
+        // empty list of combined samplers, so only look for them if there are samplers.
+        if options.name_combined_samplers && !resources.separate_samplers.is_empty() {
+            for combined in ast.get_combined_image_samplers()? {
+                let name = format!(
+                    "{}_{}",
+                    ast.get_name(combined.image_id)?,
+                    ast.get_name(combined.sampler_id)?
+                );
+                let binding = ast.get_decoration(combined.image_id, Decoration::Binding)?;
+
+                ast.set_name(combined.combined_id, &name)?;
+
+                if options.supports_binding_layout() {
+                    ast.set_decoration(combined.combined_id, Decoration::Binding, binding)?;
+                } else {
+                    removed_bindings.push(glsl::RemovedBinding::Sampler { name, binding });
+                }
+            }
+        }
+
+        Ok(glsl::GlslModule::from_string(ast.compile()?)?
+            .with_transpile_info(removed_bindings, flattened_blocks))
+    }
+}
+
+/// Find the scalar type of a uniform block, which must be the same for all members in order to
+/// flatten the block
+#[cfg(feature = "transpile")]
+fn block_base_type(module: &rspirv::dr::Module, type_id: u32) -> Option<crate::types::AtomType> {
+    use crate::types::AtomType;
+    use rspirv::dr::Operand;
+    use rspirv::spirv::Op;
+
+    let inst = module
+        .types_global_values
+        .iter()
+        .find(|inst| inst.result_id == Some(type_id))?;
+
+    let id_operand = |index: usize| match inst.operands.get(index) {
+        Some(Operand::IdRef(id)) => Some(*id),
+        _ => None,
+    };
+
+    match inst.class.opcode {
+        Op::TypeFloat => Some(AtomType::Float),
+        Op::TypeInt => match inst.operands.get(1) {
+            Some(Operand::LiteralInt32(0)) => Some(AtomType::UInt),
+            _ => Some(AtomType::Int),
+        },
+        Op::TypeVector | Op::TypeMatrix | Op::TypeArray => block_base_type(module, id_operand(0)?),
+        Op::TypeStruct => {
+            let mut types =
+                (0..inst.operands.len()).map(|i| block_base_type(module, id_operand(i)?));
+            let first = types.next()??;
+
+            if types.all(|ty| ty == Some(first)) {
+                Some(first)
+            } else {
+                None
+            }
+        }
+        _ => None,
     }
 }
 
@@ -166,25 +383,158 @@ pub struct GlslWithSpirVModule<'s, 't> {
     spirv: SpirVModule<'t>,
 }
 
-// TODO: Implement traits to delegate functionality
 impl<'s, 't> GlslWithSpirVModule<'s, 't> {
     pub fn new(glsl: glsl::GlslModule<'s>, spirv: SpirVModule<'t>) -> Self {
         Self { glsl, spirv }
     }
+
+    /// Get the GLSL part of this module
+    pub fn glsl(&self) -> &GlslModule<'s> {
+        &self.glsl
+    }
+
+    /// Get the GLSL part of this module
+    pub fn glsl_mut(&mut self) -> &mut GlslModule<'s> {
+        &mut self.glsl
+    }
+
+    /// Get the SPIR-V part of this module
+    pub fn spirv(&self) -> &SpirVModule<'t> {
+        &self.spirv
+    }
+
+    /// Split this module into its GLSL and SPIR-V parts
+    pub fn into_inner(self) -> (GlslModule<'s>, SpirVModule<'t>) {
+        (self.glsl, self.spirv)
+    }
+
+    /// Return the GLSL source of this module as a string
+    pub fn as_str(&self) -> &str {
+        self.glsl.as_str()
+    }
+
+    /// Parse the GLSL source into an AST
+    ///
+    /// See [`GlslModule::parse`] for details.
+    pub fn parse(&mut self) -> Result<()> {
+        self.glsl.parse()
+    }
+
+    /// Get the version of the GLSL source
+    ///
+    /// See [`GlslModule::version`] for details.
+    pub fn version(&self) -> Option<crate::GlslVersion> {
+        self.glsl.version()
+    }
+
+    /// Get the SPIR-V binary of this module
+    pub fn as_bytes(&self) -> &[u32] {
+        self.spirv.as_bytes()
+    }
+
+    /// Get the SPIR-V binary of this module
+    pub fn as_bytes_u8(&self) -> &[u8] {
+        self.spirv.as_bytes_u8()
+    }
+
+    /// Get the SPIR-V module parsed using rspirv
+    pub fn module(&self) -> &rspirv::dr::Module {
+        self.spirv.module()
+    }
 }
 
 impl<'s, 't> ShaderObject<GlslWithSpirVModule<'s, 't>> {
     pub fn reflect_glsl(self, reflector: &dyn GlslReflectBackend) -> Result<ReflectedObject<Self>> {
-        let uniforms = reflector.reflect(&self.glsl.as_str())?;
-        Ok(ReflectedObject::new(self, uniforms))
+        let mut uniforms = reflector
+            .reflect(&self.glsl.as_str())
+            .map_err(|error| self.info.reflection_failed(error))?;
+        let annotation_errors = UniformAnnotations::apply(
+            &mut uniforms,
+            self.glsl.as_str(),
+            &self.info.source_path.to_string(),
+        );
+
+        Ok(ReflectedObject::new(self, uniforms).with_annotation_errors(annotation_errors))
     }
 
     pub fn reflect_spirv(
         self,
         reflector: &dyn SpirVReflectBackend,
     ) -> Result<ReflectedObject<Self>> {
-        let uniforms = reflector.reflect(&self.spirv.module)?;
-        Ok(ReflectedObject::new(self, uniforms))
+        // Annotations are only found in the GLSL source
+        let mut uniforms = reflector
+            .reflect(&self.spirv.module)
+            .map_err(|error| self.info.reflection_failed(error))?;
+        let annotation_errors = UniformAnnotations::apply(
+            &mut uniforms,
+            self.glsl.as_str(),
+            &self.info.source_path.to_string(),
+        );
+
+        Ok(ReflectedObject::new(self, uniforms).with_annotation_errors(annotation_errors))
+    }
+
+    /// Preprocess the GLSL part of this object again, keeping the SPIR-V part
+    ///
+    /// # Parameters
+    ///
+    /// * `preprocessor`: preprocessor to use
+    pub fn preprocess(
+        self,
+        preprocessor: &mut dyn GlslPreprocessor,
+    ) -> Result<ShaderObject<GlslWithSpirVModule<'static, 't>>> {
+        let (glsl, spirv) = self.split();
+        let glsl = glsl.preprocess(preprocessor)?;
+
+        Ok(ShaderObject {
+            module: GlslWithSpirVModule::new(glsl.module, spirv.module),
+            info: glsl.info,
+        })
+    }
+
+    /// Replace the GLSL part of this object by the result of transpiling its SPIR-V part
+    ///
+    /// # Parameters
+    ///
+    /// * `options`: GLSL version to target, or detailed transpile options
+    #[cfg(feature = "transpile")]
+    pub fn transpile(
+        self,
+        options: impl Into<super::TranspileOptions>,
+    ) -> Result<ShaderObject<GlslWithSpirVModule<'static, 't>>> {
+        Ok(ShaderObject {
+            module: GlslWithSpirVModule {
+                glsl: self.module.spirv.transpile(options)?,
+                spirv: self.module.spirv,
+            },
+            info: self.info,
+        })
+    }
+
+    /// Split this object into its GLSL and SPIR-V parts
+    pub fn split(self) -> (ShaderObject<GlslModule<'s>>, ShaderObject<SpirVModule<'t>>) {
+        let (glsl, spirv) = self.module.into_inner();
+
+        (
+            ShaderObject {
+                module: glsl,
+                info: self.info.clone(),
+            },
+            ShaderObject {
+                module: spirv,
+                info: self.info,
+            },
+        )
+    }
+
+    /// Discard the SPIR-V part of this object
+    pub fn into_glsl(self) -> ShaderObject<GlslModule<'s>> {
+        self.split().0
+    }
+
+    /// Discard the GLSL part of this object
+    pub fn into_spirv(self) -> ShaderObject<SpirVModule<'t>> {
+        self.split().1
     }
 }
 
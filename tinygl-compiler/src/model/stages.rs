@@ -0,0 +1,224 @@
+use super::*;
+
+use crate::ShaderKind;
+
+/// Stages which can be declared in multi-stage sources, in output order
+const STAGES: &[ShaderKind] = &[
+    ShaderKind::Vertex,
+    ShaderKind::Fragment,
+    ShaderKind::Compute,
+];
+
+/// Split the source of a multi-stage GLSL file into per-stage sources
+///
+/// # Parameters
+///
+/// * `source`: GLSL source containing multiple stages
+/// * `source_path`: name of the source, for error messages
+///
+/// # Returns
+///
+/// The source of every stage declared by the file.
+fn split_stages(source: &str, source_path: &str) -> Result<Vec<(ShaderKind, String)>> {
+    let lines: Vec<&str> = source.lines().collect();
+
+    // Find #pragma stage(name) lines
+    let mut sections = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(name) = parse_stage_pragma(line) {
+            let kind = stage_from_name(name).ok_or_else(|| {
+                crate::Error::UnknownShaderStage(source_path.to_owned(), name.to_owned())
+            })?;
+
+            sections.push((index, kind));
+        }
+    }
+
+    let stages = if !sections.is_empty() {
+        split_sections(&lines, &sections)
+    } else {
+        split_defines(&lines)
+    };
+
+    if stages.is_empty() {
+        return Err(crate::Error::NoShaderStages(source_path.to_owned()));
+    }
+
+    Ok(stages)
+}
+
+/// Parse a `#pragma stage(name)` line, returning the stage name
+fn parse_stage_pragma(line: &str) -> Option<&str> {
+    let directive = line.trim_start().strip_prefix('#')?.trim_start();
+    let arguments = directive.strip_prefix("pragma")?.trim_start();
+    let name = arguments
+        .strip_prefix("stage")?
+        .trim_start()
+        .strip_prefix('(')?;
+    let name = name.trim_end().strip_suffix(')')?;
+
+    Some(name.trim())
+}
+
+fn stage_from_name(name: &str) -> Option<ShaderKind> {
+    match name {
+        "vertex" => Some(ShaderKind::Vertex),
+        "fragment" => Some(ShaderKind::Fragment),
+        "compute" => Some(ShaderKind::Compute),
+        _ => None,
+    }
+}
+
+/// Split a source into the sections declared by `#pragma stage` lines
+///
+/// Lines before the first pragma are shared by all stages. Lines which belong to other stages
+/// are blanked out, so line numbers in the stage sources match the original file.
+fn split_sections(lines: &[&str], sections: &[(usize, ShaderKind)]) -> Vec<(ShaderKind, String)> {
+    STAGES
+        .iter()
+        .filter(|stage| sections.iter().any(|(_, kind)| kind == *stage))
+        .map(|stage| {
+            let mut current = None;
+            let mut output = String::new();
+
+            for (index, line) in lines.iter().enumerate() {
+                let pragma = sections.iter().find(|(start, _)| *start == index);
+                if let Some((_, kind)) = pragma {
+                    current = Some(*kind);
+                }
+
+                if pragma.is_none() && current.map(|kind| kind == *stage).unwrap_or(true) {
+                    output.push_str(line);
+                }
+
+                output.push('\n');
+            }
+
+            (*stage, output)
+        })
+        .collect()
+}
+
+/// Split a source using `VERTEX_SHADER`, `FRAGMENT_SHADER` and `COMPUTE_SHADER` macros
+///
+/// Every stage whose macro is referenced by a preprocessor directive gets a copy of the
+/// source, with its macro defined after the `#version` directive. A `#line` directive
+/// restores the line numbers of the original file.
+fn split_defines(lines: &[&str]) -> Vec<(ShaderKind, String)> {
+    let referenced = |stage: &ShaderKind| {
+        lines
+            .iter()
+            .filter(|line| line.trim_start().starts_with('#'))
+            .flat_map(|line| line.split(|c: char| !c.is_ascii_alphanumeric() && c != '_'))
+            .any(|token| token == stage.constant_name())
+    };
+
+    let version_index = lines
+        .iter()
+        .position(|line| line.trim_start().starts_with("#version"));
+    let header = GlslHeader::scan(&lines.join("\n"));
+
+    // The line following `#line N` is line N for GLSL ES and from GLSL 3.30, and line N + 1
+    // for earlier desktop versions
+    let next_line = version_index.map(|index| index + 2).unwrap_or(1);
+    let next_line = match header.version {
+        Some(version) if version.is_es() || version.number() >= 330 => next_line,
+        _ => next_line - 1,
+    };
+
+    STAGES
+        .iter()
+        .filter(|stage| referenced(stage))
+        .map(|stage| {
+            let mut output = String::new();
+
+            if version_index.is_none() {
+                output.push_str(&format!(
+                    "#define {}\n#line {}\n",
+                    stage.constant_name(),
+                    next_line
+                ));
+            }
+
+            for (index, line) in lines.iter().enumerate() {
+                output.push_str(line);
+                output.push('\n');
+
+                if Some(index) == version_index {
+                    output.push_str(&format!(
+                        "#define {}\n#line {}\n",
+                        stage.constant_name(),
+                        next_line
+                    ));
+                }
+            }
+
+            (*stage, output)
+        })
+        .collect()
+}
+
+impl ShaderObject<GlslModule<'static>> {
+    /// Load the stages of a multi-stage GLSL shader from a file
+    ///
+    /// Stages are either declared by `#pragma stage(vertex)`, `#pragma stage(fragment)` and
+    /// `#pragma stage(compute)` lines, in which case the lines before the first pragma are shared
+    /// by all stages, or selected with `#ifdef VERTEX_SHADER` blocks (resp. `FRAGMENT_SHADER` and
+    /// `COMPUTE_SHADER`), in which case the matching macro is defined for each stage.
+    ///
+    /// Line numbers in compiler messages match the original file. Shaders loaded this way can't
+    /// be reloaded at runtime.
+    ///
+    /// # Parameters
+    ///
+    /// * `p`: path to load the shader from
+    ///
+    /// # Returns
+    ///
+    /// One shader object per declared stage.
+    pub fn stages_from_path(p: impl AsRef<Path>) -> Result<Vec<Self>> {
+        let p = std::fs::canonicalize(p.as_ref())?;
+        let source = std::fs::read_to_string(&p)?;
+
+        split_stages(&source, &p.to_string_lossy())?
+            .into_iter()
+            .map(|(kind, source)| {
+                Ok(Self {
+                    module: GlslModule::from_string(source)?,
+                    info: ObjectInfo {
+                        source_path: SourcePath::File(p.clone()),
+                        kind,
+                        includes: Vec::new(),
+                        multi_stage: true,
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Load the stages of a multi-stage GLSL shader from source
+    ///
+    /// See `stages_from_path` for the supported syntaxes.
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: source to load the shader from
+    ///
+    /// # Returns
+    ///
+    /// One shader object per declared stage.
+    pub fn stages_from_str(source: &str) -> Result<Vec<Self>> {
+        split_stages(source, "<string>")?
+            .into_iter()
+            .map(|(kind, source)| {
+                let mut info = bytes_to_id(source.as_bytes(), kind);
+                info.multi_stage = true;
+
+                Ok(Self {
+                    module: GlslModule::from_string(source)?,
+                    info,
+                })
+            })
+            .collect()
+    }
+}
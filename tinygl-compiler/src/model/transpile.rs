@@ -0,0 +1,89 @@
+use crate::GlslVersion;
+
+/// Default precision qualifier for GLSL ES targets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Precision {
+    /// Don't declare a default precision
+    DontCare,
+    Low,
+    Medium,
+    High,
+}
+
+impl From<Precision> for spirv_cross::glsl::Precision {
+    fn from(precision: Precision) -> Self {
+        match precision {
+            Precision::DontCare => Self::DontCare,
+            Precision::Low => Self::Low,
+            Precision::Medium => Self::Medium,
+            Precision::High => Self::High,
+        }
+    }
+}
+
+/// Options for transpiling SPIR-V modules to GLSL
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TranspileOptions {
+    /// GLSL version to target
+    pub version: GlslVersion,
+    /// Default float precision for fragment shaders
+    pub float_precision: Precision,
+    /// Default int precision for fragment shaders
+    pub int_precision: Precision,
+    /// Invert the Y coordinate of vertex positions
+    pub flip_vertex_y: bool,
+    /// Remap the depth of vertex positions from the [0, 1] to the [-1, 1] range
+    pub fixup_clip_space: bool,
+    /// Turn uniform blocks into plain uniform arrays of 4-component vectors
+    ///
+    /// The arrays are named after the block, and get array setters in the generated code.
+    pub flatten_uniform_buffers: bool,
+    /// Name combined image samplers `<image>_<sampler>`, and give them the binding of the image
+    ///
+    /// Otherwise, spirv_cross generates names and the combined samplers have no binding.
+    pub name_combined_samplers: bool,
+}
+
+impl TranspileOptions {
+    /// Create default transpile options for a given version
+    ///
+    /// Uniform blocks are flattened when targeting GLSL ES 1.00, since it doesn't support them.
+    ///
+    /// # Parameters
+    ///
+    /// * `version`: GLSL version to target
+    pub fn new(version: GlslVersion) -> Self {
+        Self {
+            version,
+            float_precision: Precision::Medium,
+            int_precision: Precision::High,
+            flip_vertex_y: false,
+            fixup_clip_space: false,
+            flatten_uniform_buffers: version == GlslVersion::V1_00Es,
+            name_combined_samplers: true,
+        }
+    }
+
+    pub(crate) fn compiler_options(&self) -> spirv_cross::glsl::CompilerOptions {
+        let mut options = spirv_cross::glsl::CompilerOptions::default();
+        options.version = self.version.into();
+        options.fragment.default_float_precision = self.float_precision.into();
+        options.fragment.default_int_precision = self.int_precision.into();
+        options.vertex.invert_y = self.flip_vertex_y;
+        options.vertex.transform_clip_space = self.fixup_clip_space;
+        options
+    }
+
+    /// Return `true` if `layout(binding = N)` can be used with the target version
+    ///
+    /// Desktop versions before 4.20 use the GL_ARB_shading_language_420pack extension.
+    pub(crate) fn supports_binding_layout(&self) -> bool {
+        !matches!(self.version, GlslVersion::V1_00Es | GlslVersion::V3_00Es)
+    }
+}
+
+impl From<GlslVersion> for TranspileOptions {
+    fn from(version: GlslVersion) -> Self {
+        Self::new(version)
+    }
+}
@@ -1,3 +1,6 @@
+mod annotations;
+pub use annotations::*;
+
 mod found_uniform;
 pub use found_uniform::*;
 
@@ -11,12 +14,12 @@ pub use spirv::*;
 
 use crate::model::{ObjectInfo, ShaderObject};
 
-pub trait GlslReflectBackend {
+pub trait GlslReflectBackend: Send + Sync {
     fn reflect<'s>(&self, input: &str) -> crate::Result<Vec<FoundUniform>>;
 }
 
 #[cfg(feature = "spirv")]
-pub trait SpirVReflectBackend {
+pub trait SpirVReflectBackend: Send + Sync {
     fn reflect<'s>(&self, input: &rspirv::dr::Module) -> crate::Result<Vec<FoundUniform>>;
 }
 
@@ -24,11 +27,22 @@ pub trait SpirVReflectBackend {
 pub struct ReflectedObject<T> {
     object: T,
     uniforms: Vec<FoundUniform>,
+    annotation_errors: Vec<crate::Error>,
 }
 
 impl<T> ReflectedObject<T> {
     pub fn new(object: T, uniforms: Vec<FoundUniform>) -> Self {
-        Self { object, uniforms }
+        Self {
+            object,
+            uniforms,
+            annotation_errors: Vec::new(),
+        }
+    }
+
+    /// Record the malformed annotations which were skipped when annotating the uniforms
+    pub(crate) fn with_annotation_errors(mut self, errors: Vec<crate::Error>) -> Self {
+        self.annotation_errors.extend(errors);
+        self
     }
 
     pub fn object(&self) -> &T {
@@ -38,6 +52,32 @@ impl<T> ReflectedObject<T> {
     pub fn uniforms(&self) -> &[FoundUniform] {
         &self.uniforms
     }
+
+    /// Return the malformed annotations which were skipped when annotating the uniforms
+    pub fn annotation_errors(&self) -> &[crate::Error] {
+        &self.annotation_errors
+    }
+
+    /// Take the malformed annotations which were skipped when annotating the uniforms
+    pub(crate) fn take_annotation_errors(&mut self) -> Vec<crate::Error> {
+        std::mem::take(&mut self.annotation_errors)
+    }
+
+    /// Attach the annotations declared in a GLSL source to the uniforms of this object
+    ///
+    /// Objects reflected from GLSL sources are annotated automatically, this is meant for SPIR-V
+    /// binaries whose source is available separately. Malformed annotations are skipped and
+    /// recorded in [`Self::annotation_errors`].
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: GLSL source declaring the uniforms
+    /// * `source_path`: name of the source, for error messages
+    pub fn annotate(mut self, source: &str, source_path: &str) -> crate::Result<Self> {
+        let errors = UniformAnnotations::apply(&mut self.uniforms, source, source_path);
+        self.annotation_errors.extend(errors);
+        Ok(self)
+    }
 }
 
 impl<T> ReflectedObject<ShaderObject<T>> {
@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+
+use super::FoundUniform;
+
+/// Metadata declared by annotations on a uniform
+///
+/// Annotations are written as `@name(arguments)` in `//` comments or `#pragma` lines directly
+/// preceding the uniform declaration, or in a comment on the same line:
+///
+/// ```glsl
+/// // @label("Exposure")
+/// uniform float uExposure; // @range(0, 10)
+/// ```
+///
+/// Unknown annotations are ignored. Malformed annotations are skipped and reported as build
+/// warnings, unless strict annotations are enabled with [`crate::Compiler::set_strict_annotations`].
+///
+/// Only the source of the shader itself is scanned, before includes are expanded: annotations on
+/// uniforms declared in `#include`d files are not seen.
+#[derive(Debug, Default, Clone)]
+pub struct UniformAnnotations {
+    /// Range of values, from `@range(min, max)`
+    pub range: Option<(f64, f64)>,
+    /// Label for user interfaces, from `@label("...")`
+    pub label: Option<String>,
+}
+
+// Ranges are compared by their representation, like constants
+impl PartialEq for UniformAnnotations {
+    fn eq(&self, other: &Self) -> bool {
+        let bits =
+            |range: Option<(f64, f64)>| range.map(|(min, max)| (min.to_bits(), max.to_bits()));
+        bits(self.range) == bits(other.range) && self.label == other.label
+    }
+}
+
+impl Eq for UniformAnnotations {}
+
+impl UniformAnnotations {
+    /// Return `true` if no annotation was declared
+    pub fn is_empty(&self) -> bool {
+        self.range.is_none() && self.label.is_none()
+    }
+
+    /// Parse the annotations declared by uniforms in a GLSL source
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: GLSL source
+    /// * `source_path`: name of the source, for error messages
+    ///
+    /// # Returns
+    ///
+    /// The annotations of every annotated uniform, by name, or the first malformed annotation.
+    pub fn parse(source: &str, source_path: &str) -> crate::Result<HashMap<String, Self>> {
+        let (result, errors) = Self::parse_lenient(source, source_path);
+
+        match errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(result),
+        }
+    }
+
+    /// Parse the annotations declared by uniforms in a GLSL source, skipping malformed ones
+    ///
+    /// # Parameters
+    ///
+    /// * `source`: GLSL source
+    /// * `source_path`: name of the source, for error messages
+    ///
+    /// # Returns
+    ///
+    /// The annotations of every annotated uniform, by name, and an `Error::InvalidAnnotation`
+    /// for every malformed annotation.
+    pub fn parse_lenient(
+        source: &str,
+        source_path: &str,
+    ) -> (HashMap<String, Self>, Vec<crate::Error>) {
+        let mut result = HashMap::new();
+        let mut errors = Vec::new();
+        let mut pending = Self::default();
+
+        for (index, line) in source.lines().enumerate() {
+            let mut error = |message: String| {
+                errors.push(crate::Error::InvalidAnnotation(
+                    format!("{}:{}", source_path, index + 1),
+                    message,
+                ))
+            };
+
+            let (code, comment) = match line.find("//") {
+                Some(start) => (&line[..start], Some(&line[start + 2..])),
+                None => (line, None),
+            };
+            let code = code.trim();
+
+            if let Some(pragma) = code
+                .strip_prefix('#')
+                .and_then(|directive| directive.trim_start().strip_prefix("pragma"))
+            {
+                pending.parse_into(pragma, &mut error);
+                continue;
+            }
+
+            if code.is_empty() {
+                match comment {
+                    Some(comment) => pending.parse_into(comment, &mut error),
+                    // Blank lines detach annotations from the following declaration
+                    None => pending = Self::default(),
+                }
+
+                continue;
+            }
+
+            let mut annotations = std::mem::take(&mut pending);
+            if let Some(comment) = comment {
+                annotations.parse_into(comment, &mut error);
+            }
+
+            if annotations.is_empty() {
+                continue;
+            }
+
+            for name in declared_uniforms(code) {
+                result.insert(name.to_owned(), annotations.clone());
+            }
+        }
+
+        (result, errors)
+    }
+
+    /// Attach the annotations declared in a GLSL source to reflected uniforms
+    ///
+    /// # Parameters
+    ///
+    /// * `uniforms`: uniforms to annotate
+    /// * `source`: GLSL source declaring the uniforms
+    /// * `source_path`: name of the source, for error messages
+    ///
+    /// # Returns
+    ///
+    /// An `Error::InvalidAnnotation` for every malformed annotation, which are skipped.
+    pub fn apply(
+        uniforms: &mut [FoundUniform],
+        source: &str,
+        source_path: &str,
+    ) -> Vec<crate::Error> {
+        let (mut annotations, errors) = Self::parse_lenient(source, source_path);
+
+        for uniform in uniforms {
+            if let Some(annotations) = annotations.remove(&uniform.name) {
+                uniform.annotations = annotations;
+            }
+        }
+
+        errors
+    }
+
+    fn parse_into(&mut self, text: &str, mut error: impl FnMut(String)) {
+        let mut rest = text;
+
+        while let Some(start) = rest.find('@') {
+            // Only consider annotations at the start of a word, e.g. not in e-mail addresses
+            let preceded_by_word = rest[..start]
+                .chars()
+                .last()
+                .map(|c| !c.is_whitespace())
+                .unwrap_or(false);
+
+            rest = &rest[start + 1..];
+            if preceded_by_word {
+                continue;
+            }
+
+            let name_len = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            let name = &rest[..name_len];
+            rest = &rest[name_len..];
+
+            let arguments = match rest.trim_start().strip_prefix('(') {
+                Some(arguments) => match split_arguments(arguments) {
+                    Some((arguments, remaining)) => {
+                        rest = remaining;
+                        Some(arguments)
+                    }
+                    // The rest of the text can't be parsed, only known annotations are reported
+                    None => {
+                        if is_known(name) {
+                            error(format!("unterminated arguments for @{}", name));
+                        }
+
+                        break;
+                    }
+                },
+                None => None,
+            };
+
+            match name {
+                "range" => {
+                    let values = arguments
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|value| value.parse::<f64>().ok().filter(|value| value.is_finite()))
+                        .collect::<Option<Vec<_>>>();
+
+                    match values.as_deref() {
+                        Some(&[min, max]) if min <= max => self.range = Some((min, max)),
+                        _ => error("@range expects two numbers, min and max".to_owned()),
+                    }
+                }
+                "label" => match arguments.as_deref().and_then(|arguments| match arguments {
+                    [label] => parse_string(label),
+                    _ => None,
+                }) {
+                    Some(label) => self.label = Some(label),
+                    None => error("@label expects a string".to_owned()),
+                },
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Return `true` if `name` is an annotation parsed by [`UniformAnnotations`]
+fn is_known(name: &str) -> bool {
+    matches!(name, "range" | "label")
+}
+
+/// Split the arguments of an annotation, following its opening parenthesis
+///
+/// Returns the trimmed arguments and the text after the closing parenthesis.
+fn split_arguments(text: &str) -> Option<(Vec<&str>, &str)> {
+    let mut arguments = Vec::new();
+    let mut start = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+
+            continue;
+        }
+
+        match c {
+            '"' => in_string = true,
+            ',' => {
+                arguments.push(text[start..index].trim());
+                start = index + 1;
+            }
+            ')' => {
+                let last = text[start..index].trim();
+                if !last.is_empty() || !arguments.is_empty() {
+                    arguments.push(last);
+                }
+
+                return Some((arguments, &text[index + 1..]));
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Parse a double-quoted string literal
+fn parse_string(text: &str) -> Option<String> {
+    let text = text.strip_prefix('"')?.strip_suffix('"')?;
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => result.push(chars.next()?),
+            _ => result.push(c),
+        }
+    }
+
+    Some(result)
+}
+
+/// Return the names of the uniforms declared by a line of GLSL code
+fn declared_uniforms(code: &str) -> Vec<&str> {
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    // Find the uniform storage qualifier, ignoring layout qualifiers
+    let mut depth = 0;
+    let mut start = None;
+    for (index, c) in code.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0
+                && code[index..].starts_with("uniform")
+                && !code[..index].ends_with(is_ident)
+                && !code[index + "uniform".len()..].starts_with(is_ident) =>
+            {
+                start = Some(index + "uniform".len());
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let declarators = match start {
+        Some(start) => &code[start..],
+        None => return Vec::new(),
+    };
+
+    // Uniform blocks don't declare plain uniforms
+    if declarators.contains('{') {
+        return Vec::new();
+    }
+
+    // Split declarators at top-level commas, the first one includes the type
+    let mut names = Vec::new();
+    let mut depth = 0;
+    let mut declarator_start = 0;
+    for (index, c) in declarators.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth -= 1,
+            ',' | ';' if depth == 0 => {
+                names.push(&declarators[declarator_start..index]);
+                declarator_start = index + 1;
+
+                if c == ';' {
+                    break;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    names
+        .into_iter()
+        .filter_map(|declarator| {
+            let declarator = declarator
+                .split(['=', '['])
+                .next()
+                .unwrap_or_default()
+                .trim_end();
+
+            let name_start = declarator
+                .rfind(|c: char| !is_ident(c))
+                .map(|index| index + 1)
+                .unwrap_or(0);
+
+            Some(&declarator[name_start..]).filter(|name| !name.is_empty())
+        })
+        .collect()
+}
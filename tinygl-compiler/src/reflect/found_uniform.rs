@@ -1,17 +1,35 @@
-use crate::types::ItemOrArrayType;
+use crate::types::{ConstantValue, ItemOrArrayType};
 
 #[derive(Debug, Default, Eq, Clone)]
 pub struct FoundUniform {
     pub name: String,
     pub location: u32,
+    /// Type of this uniform, `None` if it is not supported and can't be wrapped
     pub ty: Option<ItemOrArrayType>,
 
     pub binding: Option<i32>,
 
     pub location_name: String,
+
+    /// `Some(true)` if an entry point of the shader statically uses this uniform, `None` if
+    /// this is unknown
+    pub used: Option<bool>,
+
+    /// Components of the initializer of this uniform, in column-major order
+    pub default: Option<Vec<ConstantValue>>,
+
+    /// Metadata declared by annotations in the shader source
+    pub annotations: super::UniformAnnotations,
 }
 
 impl FoundUniform {
+    /// Return `true` if this uniform is known to be unused by the shader
+    ///
+    /// Drivers may optimize such uniforms out, in which case setting them has no effect.
+    pub fn is_unused(&self) -> bool {
+        self.used == Some(false)
+    }
+
     pub fn format(&self) -> Option<u32> {
         match self.ty {
             Some(ItemOrArrayType::Image { format }) => format,
@@ -20,16 +20,16 @@ impl SpirVBackend {
         // Enumerate known names from debug info
         for debug in &module.debug_names {
             if let rspirv::spirv::Op::Name = debug.class.opcode {
-                if let rr::Operand::IdRef(id) = debug.operands[0] {
-                    if let rr::Operand::LiteralString(name) = &debug.operands[1] {
-                        names.insert(
-                            id,
-                            FoundUniform {
-                                name: name.to_owned(),
-                                ..Default::default()
-                            },
-                        );
-                    }
+                if let (Some(rr::Operand::IdRef(id)), Some(rr::Operand::LiteralString(name))) =
+                    (debug.operands.first(), debug.operands.get(1))
+                {
+                    names.insert(
+                        *id,
+                        FoundUniform {
+                            name: name.to_owned(),
+                            ..Default::default()
+                        },
+                    );
                 }
             }
         }
@@ -37,6 +37,10 @@ impl SpirVBackend {
         // Find constants
         let mut constants = std::collections::HashMap::new();
 
+        // Values of scalar and composite constants, for initializers
+        let mut values: std::collections::HashMap<rspirv::spirv::Word, Vec<ConstantValue>> =
+            std::collections::HashMap::new();
+
         // Find types
         let mut types: std::collections::HashMap<rspirv::spirv::Word, ItemOrArrayType> =
             std::collections::HashMap::new();
@@ -46,89 +50,141 @@ impl SpirVBackend {
 
             match type_global_value.class.opcode {
                 rspirv::spirv::Op::Constant => {
-                    if let rr::Operand::LiteralInt32(value) = type_global_value.operands[0] {
-                        constants.insert(id, value);
+                    if let Some(rr::Operand::LiteralInt32(value)) =
+                        type_global_value.operands.first()
+                    {
+                        constants.insert(id, *value);
                     }
-                }
-                rspirv::spirv::Op::TypeInt => {
-                    if let rr::Operand::LiteralInt32(32) = type_global_value.operands[0] {
-                        if let rr::Operand::LiteralInt32(0) = type_global_value.operands[1] {
-                            types.insert(id, ItemOrArrayType::atom(AtomType::UInt));
-                        } else {
-                            types.insert(id, ItemOrArrayType::atom(AtomType::Int));
+
+                    let ty = type_global_value.result_type.and_then(|ty| types.get(&ty));
+                    let value = match (ty, type_global_value.operands.first()) {
+                        (
+                            Some(ItemOrArrayType::Item(GenericType::Atom(atom))),
+                            Some(rr::Operand::LiteralInt32(value)),
+                        ) => match atom {
+                            AtomType::Int => Some(ConstantValue::Int(*value as i32)),
+                            AtomType::UInt => Some(ConstantValue::UInt(*value)),
+                            _ => None,
+                        },
+                        (_, Some(rr::Operand::LiteralFloat32(value))) => {
+                            Some(ConstantValue::Float(*value))
                         }
-                    } else {
-                        panic!("unsupported integer width");
+                        (_, Some(rr::Operand::LiteralFloat64(value))) => {
+                            Some(ConstantValue::Double(*value))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(value) = value {
+                        values.insert(id, vec![value]);
+                    }
+                }
+                rspirv::spirv::Op::ConstantTrue => {
+                    values.insert(id, vec![ConstantValue::Bool(true)]);
+                }
+                rspirv::spirv::Op::ConstantFalse => {
+                    values.insert(id, vec![ConstantValue::Bool(false)]);
+                }
+                rspirv::spirv::Op::ConstantComposite => {
+                    // Vectors are made of scalars, and matrices of column vectors
+                    let components: Option<Vec<_>> = type_global_value
+                        .operands
+                        .iter()
+                        .map(|operand| match operand {
+                            rr::Operand::IdRef(id) => values.get(id).cloned(),
+                            _ => None,
+                        })
+                        .collect();
+
+                    if let Some(components) = components {
+                        values.insert(id, components.concat());
                     }
                 }
-                rspirv::spirv::Op::TypeFloat => {
-                    if let rr::Operand::LiteralInt32(32) = type_global_value.operands[0] {
+                // Other widths are not supported by uniforms, so their types are skipped
+                rspirv::spirv::Op::TypeInt => match (
+                    type_global_value.operands.first(),
+                    type_global_value.operands.get(1),
+                ) {
+                    (Some(rr::Operand::LiteralInt32(32)), Some(rr::Operand::LiteralInt32(0))) => {
+                        types.insert(id, ItemOrArrayType::atom(AtomType::UInt));
+                    }
+                    (Some(rr::Operand::LiteralInt32(32)), Some(rr::Operand::LiteralInt32(_))) => {
+                        types.insert(id, ItemOrArrayType::atom(AtomType::Int));
+                    }
+                    _ => {}
+                },
+                rspirv::spirv::Op::TypeFloat => match type_global_value.operands.first() {
+                    Some(rr::Operand::LiteralInt32(32)) => {
                         types.insert(id, ItemOrArrayType::atom(AtomType::Float));
-                    } else if let rr::Operand::LiteralInt32(64) = type_global_value.operands[0] {
+                    }
+                    Some(rr::Operand::LiteralInt32(64)) => {
                         types.insert(id, ItemOrArrayType::atom(AtomType::Double));
-                    } else {
-                        panic!("unsupported float width");
                     }
-                }
+                    _ => {}
+                },
                 rspirv::spirv::Op::TypeBool => {
                     // TODO: Check TypeBool syntax
                     types.insert(id, ItemOrArrayType::atom(AtomType::Bool));
                 }
                 rspirv::spirv::Op::TypeVector => {
-                    if let rr::Operand::IdRef(type_id) = type_global_value.operands[0] {
-                        if let rr::Operand::LiteralInt32(components) = type_global_value.operands[1]
-                        {
-                            types.insert(id, ItemOrArrayType::vector(types[&type_id], components));
+                    if let (
+                        Some(rr::Operand::IdRef(type_id)),
+                        Some(rr::Operand::LiteralInt32(components)),
+                    ) = (
+                        type_global_value.operands.first(),
+                        type_global_value.operands.get(1),
+                    ) {
+                        if let Some(component_type) = types.get(type_id) {
+                            let ty = ItemOrArrayType::vector(*component_type, *components)
+                                .map_err(|error| invalid(&names, id, error.to_string()))?;
+                            types.insert(id, ty);
                         }
                     }
                 }
                 rspirv::spirv::Op::TypeMatrix => {
-                    if let rr::Operand::IdRef(type_id) = type_global_value.operands[0] {
-                        if let rr::Operand::LiteralInt32(n) = type_global_value.operands[1] {
-                            if let ItemOrArrayType::Item(GenericType::Vector(VectorType {
-                                base_type,
-                                components,
-                            })) = types[&type_id]
-                            {
-                                if base_type.is_float_type() {
-                                    if components == n {
-                                        types.insert(
-                                            id,
-                                            ItemOrArrayType::matrix(
-                                                ItemOrArrayType::atom(base_type),
-                                                components,
-                                            ),
-                                        );
-                                    } else {
-                                        // TODO: Support rectangular matrices
-                                    }
+                    if let (Some(rr::Operand::IdRef(type_id)), Some(rr::Operand::LiteralInt32(n))) = (
+                        type_global_value.operands.first(),
+                        type_global_value.operands.get(1),
+                    ) {
+                        if let Some(ItemOrArrayType::Item(GenericType::Vector(VectorType {
+                            base_type,
+                            components,
+                        }))) = types.get(type_id).copied()
+                        {
+                            if base_type.is_float_type() {
+                                if components == *n {
+                                    let ty = ItemOrArrayType::matrix(
+                                        ItemOrArrayType::atom(base_type),
+                                        components,
+                                    )
+                                    .map_err(|error| invalid(&names, id, error.to_string()))?;
+                                    types.insert(id, ty);
+                                } else {
+                                    // TODO: Support rectangular matrices
                                 }
                             }
                         }
                     }
                 }
                 rspirv::spirv::Op::TypeArray => {
-                    if let rr::Operand::IdRef(type_id) = type_global_value.operands[0] {
-                        if let rr::Operand::IdRef(constant_id) = type_global_value.operands[1] {
-                            if types.get(&type_id).is_some() {
-                                types.insert(
-                                    id,
-                                    ItemOrArrayType::array(
-                                        types[&type_id],
-                                        constants[&constant_id],
-                                    ),
-                                );
-                            } else {
-                                println!(
-                                    "cargo:warning=failed to discover array element type for {}",
-                                    type_id
-                                );
+                    if let (
+                        Some(rr::Operand::IdRef(type_id)),
+                        Some(rr::Operand::IdRef(constant_id)),
+                    ) = (
+                        type_global_value.operands.first(),
+                        type_global_value.operands.get(1),
+                    ) {
+                        // Arrays of unsupported or opaque types and arrays sized by
+                        // specialization constants are not supported, so their types are skipped
+                        if let (Some(element_type), Some(length)) =
+                            (types.get(type_id), constants.get(constant_id))
+                        {
+                            if let Ok(ty) = ItemOrArrayType::array(*element_type, *length) {
+                                types.insert(id, ty);
                             }
-                        } else {
-                            panic!("failed to get components");
                         }
                     } else {
-                        panic!("failed to get type_id");
+                        return Err(invalid(&names, id, "malformed OpTypeArray".to_owned()));
                     }
                 }
                 rspirv::spirv::Op::TypeSampledImage | rspirv::spirv::Op::TypeImage => {
@@ -192,64 +248,98 @@ impl SpirVBackend {
             }
         }
 
-        // Enumerate locations
+        // Enumerate locations, ignoring decorations of unnamed ids
         for annotation in &module.annotations {
             if let rspirv::spirv::Op::Decorate = annotation.class.opcode {
-                if let rr::Operand::Decoration(rspirv::spirv::Decoration::Location) =
-                    annotation.operands[1]
-                {
-                    if let rr::Operand::IdRef(id) = annotation.operands[0] {
-                        if let rr::Operand::LiteralInt32(location) = annotation.operands[2] {
-                            names.get_mut(&id).unwrap().location = location;
-                        }
-                    }
-                } else if let rr::Operand::Decoration(rspirv::spirv::Decoration::Binding) =
-                    annotation.operands[1]
-                {
-                    if let rr::Operand::IdRef(id) = annotation.operands[0] {
-                        if let rr::Operand::LiteralInt32(binding) = annotation.operands[2] {
-                            names.get_mut(&id).unwrap().binding = Some(binding as i32);
+                if let (
+                    Some(rr::Operand::IdRef(id)),
+                    Some(rr::Operand::Decoration(decoration)),
+                    Some(rr::Operand::LiteralInt32(value)),
+                ) = (
+                    annotation.operands.first(),
+                    annotation.operands.get(1),
+                    annotation.operands.get(2),
+                ) {
+                    if let Some(uniform) = names.get_mut(id) {
+                        match decoration {
+                            rspirv::spirv::Decoration::Location => uniform.location = *value,
+                            rspirv::spirv::Decoration::Binding => {
+                                uniform.binding = Some(*value as i32)
+                            }
+                            _ => {}
                         }
                     }
                 }
             }
         }
 
+        // Find which variables are used by the entry points
+        let used_variables = find_used_variables(module);
+
         // Find global uniform variables and assign types
         let mut type_pointers = std::collections::HashMap::new();
+        let mut variables = Vec::new();
 
         for type_global_value in &module.types_global_values {
             match type_global_value.class.opcode {
                 rspirv::spirv::Op::TypePointer => {
-                    if let rr::Operand::IdRef(type_id) = type_global_value.operands[1] {
-                        type_pointers.insert(type_global_value.result_id.unwrap(), type_id);
+                    let result_id = type_global_value.result_id.ok_or_else(|| {
+                        invalid(&names, 0, "OpTypePointer without a result id".to_owned())
+                    })?;
+
+                    if let Some(rr::Operand::IdRef(type_id)) = type_global_value.operands.get(1) {
+                        type_pointers.insert(result_id, *type_id);
                     } else {
-                        panic!("failed to get type_id");
+                        return Err(invalid(
+                            &names,
+                            result_id,
+                            "malformed OpTypePointer".to_owned(),
+                        ));
                     }
                 }
                 rspirv::spirv::Op::Variable => {
-                    if let rr::Operand::StorageClass(rspirv::spirv::StorageClass::UniformConstant) =
-                        type_global_value.operands[0]
+                    if let Some(rr::Operand::StorageClass(
+                        rspirv::spirv::StorageClass::UniformConstant,
+                    )) = type_global_value.operands.first()
                     {
-                        let result_id = type_global_value.result_id.unwrap();
+                        let result_id = type_global_value.result_id.ok_or_else(|| {
+                            invalid(&names, 0, "OpVariable without a result id".to_owned())
+                        })?;
+
+                        let ty = type_global_value
+                            .result_type
+                            .and_then(|tp| type_pointers.get(&tp))
+                            .ok_or_else(|| {
+                                invalid(
+                                    &names,
+                                    result_id,
+                                    "variable type is not a pointer type".to_owned(),
+                                )
+                            })?;
+
+                        // Unnamed variables can't be wrapped
                         if let Some(v) = names.get_mut(&result_id) {
-                            let tp = type_global_value.result_type.unwrap();
+                            // Uniforms of unsupported types are reported without a type
+                            variables.push(result_id);
+                            v.location_name = (v.name.clone() + "_location").to_snake_case();
 
-                            match types.get(&type_pointers[&tp]) {
-                                Some(ty) => {
-                                    v.ty = Some(*ty);
-                                    v.location_name =
-                                        (v.name.clone() + "_location").to_snake_case();
-                                }
-                                None => {
-                                    println!(
-                                    "cargo:warning={}: unsupported type, it will not be wrapped",
-                                    v.name
-                                );
+                            if let Some(ty) = types.get(ty) {
+                                v.ty = Some(*ty);
+                                v.used = used_variables
+                                    .as_ref()
+                                    .map(|used| used.contains(&result_id));
+
+                                // Constant initializer, for plain values
+                                if let (
+                                    ItemOrArrayType::Item(item),
+                                    Some(rr::Operand::IdRef(initializer)),
+                                ) = (ty, type_global_value.operands.get(1))
+                                {
+                                    v.default = values.get(initializer).cloned().filter(|value| {
+                                        value.len() == item.component_count() as usize
+                                    });
                                 }
                             }
-                        } else {
-                            panic!("failed to get result_id");
                         }
                     }
                 }
@@ -257,10 +347,9 @@ impl SpirVBackend {
             }
         }
 
-        let mut v = names
-            .drain()
-            .map(|(_k, v)| v)
-            .filter(|v| v.ty.is_some())
+        let mut v = variables
+            .iter()
+            .filter_map(|id| names.remove(id))
             .collect::<Vec<_>>();
 
         v.sort_by_key(|item| item.location);
@@ -268,6 +357,100 @@ impl SpirVBackend {
     }
 }
 
+/// Build an invalid SPIR-V error for the given id, including its debug name if it has one
+fn invalid(
+    names: &std::collections::HashMap<rspirv::spirv::Word, FoundUniform>,
+    id: rspirv::spirv::Word,
+    message: String,
+) -> crate::Error {
+    let symbol = match names.get(&id) {
+        Some(uniform) => format!("%{} ({})", id, uniform.name),
+        None => format!("%{}", id),
+    };
+
+    crate::Error::InvalidSpirV(symbol, message)
+}
+
+/// Find the global variables statically used by the entry points of a module
+///
+/// A variable is used if it is referenced by an instruction (load, access chain, function call
+/// argument, etc.) of a function reachable from an entry point. Starting with SPIR-V 1.4, entry
+/// point interfaces also list all the global variables they use.
+///
+/// # Returns
+///
+/// The set of used variable ids, or `None` if the module has no entry points.
+fn find_used_variables(
+    module: &rspirv::dr::Module,
+) -> Option<std::collections::HashSet<rspirv::spirv::Word>> {
+    if module.entry_points.is_empty() {
+        return None;
+    }
+
+    let functions: std::collections::HashMap<_, _> = module
+        .functions
+        .iter()
+        .filter_map(|function| Some((function.def.as_ref()?.result_id?, function)))
+        .collect();
+
+    let interface_lists_globals = module
+        .header
+        .as_ref()
+        .map(|header| header.version() >= (1, 4))
+        .unwrap_or(false);
+
+    let mut used = std::collections::HashSet::new();
+    let mut pending = Vec::new();
+
+    for entry_point in &module.entry_points {
+        if let Some(rr::Operand::IdRef(function_id)) = entry_point.operands.get(1) {
+            pending.push(*function_id);
+        }
+
+        // Operands are the execution model, the function, the name and the interface
+        if interface_lists_globals {
+            for operand in entry_point.operands.iter().skip(3) {
+                if let rr::Operand::IdRef(id) = operand {
+                    used.insert(*id);
+                }
+            }
+        }
+    }
+
+    // Walk the call graph from the entry points
+    let mut visited = std::collections::HashSet::new();
+    while let Some(function_id) = pending.pop() {
+        if !visited.insert(function_id) {
+            continue;
+        }
+
+        let function = match functions.get(&function_id) {
+            Some(function) => function,
+            None => continue,
+        };
+
+        for instruction in function.blocks.iter().flat_map(|block| &block.instructions) {
+            if let rspirv::spirv::Op::FunctionCall = instruction.class.opcode {
+                if let Some(rr::Operand::IdRef(callee)) = instruction.operands.first() {
+                    pending.push(*callee);
+                }
+            }
+
+            used.extend(
+                instruction
+                    .operands
+                    .iter()
+                    .filter_map(|operand| match operand {
+                        rr::Operand::IdRef(id) => Some(*id),
+                        _ => None,
+                    }),
+            );
+        }
+    }
+
+    Some(used)
+}
+
 impl SpirVReflectBackend for SpirVBackend {
     fn reflect<'s>(&self, input: &rspirv::dr::Module) -> crate::Result<Vec<FoundUniform>> {
         Ok(self.find_uniforms(input)?)
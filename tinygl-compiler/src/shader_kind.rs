@@ -19,19 +19,17 @@ impl Into<shaderc::ShaderKind> for ShaderKind {
 }
 
 impl ShaderKind {
-    pub fn from_path(p: impl AsRef<Path>) -> Option<Self> {
-        if let Some(ext) = p.as_ref().extension() {
-            return Some(match ext.to_str() {
-                Some("vert") => Self::Vertex,
-                Some("comp") => Self::Compute,
-                Some("frag") => Self::Fragment,
+    pub fn from_path(p: impl AsRef<Path>) -> crate::Result<Self> {
+        match p.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("vert") => Ok(Self::Vertex),
+            Some("comp") => Ok(Self::Compute),
+            Some("frag") => Ok(Self::Fragment),
 
-                // TODO: Add other shader types
-                _ => panic!("{}: unknown shader type", p.as_ref().to_string_lossy()),
-            });
+            // TODO: Add other shader types
+            _ => Err(crate::Error::CouldNotDetermineType(
+                p.as_ref().to_string_lossy().into_owned(),
+            )),
         }
-
-        None
     }
 
     pub fn constant_name(&self) -> &'static str {
@@ -45,8 +43,8 @@ impl ShaderKind {
     pub fn extension(&self) -> &'static str {
         match self {
             Self::Vertex => "vert",
-            Self::Fragment => "comp",
-            Self::Compute => "frag",
+            Self::Fragment => "frag",
+            Self::Compute => "comp",
         }
     }
 }
@@ -119,21 +119,27 @@ impl fmt::Display for GenericType {
 }
 
 impl GenericType {
-    fn vector(inner_type: Self, components: u32) -> Self {
+    fn vector(inner_type: Self, components: u32) -> crate::Result<Self> {
         match inner_type {
             Self::Atom(atom_type) if components > 1 => {
-                Self::Vector(VectorType::new(atom_type, components))
+                Ok(Self::Vector(VectorType::new(atom_type, components)))
             }
-            _ => panic!("unsupported type combination"),
+            _ => Err(crate::Error::UnsupportedTypeCombination(format!(
+                "vector of {} {}",
+                components, inner_type
+            ))),
         }
     }
 
-    fn matrix(inner_type: Self, n: u32) -> Self {
+    fn matrix(inner_type: Self, n: u32) -> crate::Result<Self> {
         match inner_type {
             Self::Atom(atom_type) if n > 1 && atom_type.is_float_type() => {
-                Self::Matrix(MatrixType::new(atom_type, n))
+                Ok(Self::Matrix(MatrixType::new(atom_type, n)))
             }
-            _ => panic!("unsupported type combination"),
+            _ => Err(crate::Error::UnsupportedTypeCombination(format!(
+                "{}x{} matrix of {}",
+                n, n, inner_type
+            ))),
         }
     }
 }
@@ -161,33 +167,82 @@ impl ItemOrArrayType {
         Self::Item(GenericType::Atom(atom_type))
     }
 
-    pub fn vector(vector_type: Self, components: u32) -> Self {
+    pub fn vector(vector_type: Self, components: u32) -> crate::Result<Self> {
         match vector_type {
-            Self::Item(inner_type) => Self::Item(GenericType::vector(inner_type, components)),
-            _ => panic!(
-                "unsupported type combination: {:?}[{}]",
-                vector_type, components
-            ),
+            Self::Item(inner_type) => Ok(Self::Item(GenericType::vector(inner_type, components)?)),
+            _ => Err(crate::Error::UnsupportedTypeCombination(format!(
+                "vector of {} {}",
+                components, vector_type
+            ))),
         }
     }
 
-    pub fn matrix(matrix_type: Self, components: u32) -> Self {
+    pub fn matrix(matrix_type: Self, components: u32) -> crate::Result<Self> {
         match matrix_type {
-            Self::Item(inner_type) => Self::Item(GenericType::matrix(inner_type, components)),
-            _ => panic!(
-                "unsupported type combination: {:?}[{}]",
-                matrix_type, components
-            ),
+            Self::Item(inner_type) => Ok(Self::Item(GenericType::matrix(inner_type, components)?)),
+            _ => Err(crate::Error::UnsupportedTypeCombination(format!(
+                "{}x{} matrix of {}",
+                components, components, matrix_type
+            ))),
         }
     }
 
-    pub fn array(inner_type: Self, components: u32) -> Self {
+    pub fn array(inner_type: Self, components: u32) -> crate::Result<Self> {
         match inner_type {
-            Self::Item(inner) => Self::Array(inner, components),
-            _ => panic!(
-                "unsupported type combination: {:?}[{}]",
-                inner_type, components
-            ),
+            Self::Item(inner) => Ok(Self::Array(inner, components)),
+            _ => Err(crate::Error::UnsupportedTypeCombination(format!(
+                "array of {} {}",
+                components, inner_type
+            ))),
+        }
+    }
+}
+
+impl GenericType {
+    /// Return the number of scalar components of this type
+    pub fn component_count(&self) -> u32 {
+        match self {
+            Self::Atom(_) => 1,
+            Self::Vector(vector_type) => vector_type.components,
+            Self::Matrix(matrix_type) => matrix_type.n * matrix_type.n,
+        }
+    }
+}
+
+/// Scalar component of a constant value
+#[derive(Debug, Copy, Clone)]
+pub enum ConstantValue {
+    Int(i32),
+    Float(f32),
+    Double(f64),
+    UInt(u32),
+    Bool(bool),
+}
+
+// Floats are compared by their representation, so constants can be used as keys
+impl PartialEq for ConstantValue {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Int(a), Self::Int(b)) => a == b,
+            (Self::Float(a), Self::Float(b)) => a.to_bits() == b.to_bits(),
+            (Self::Double(a), Self::Double(b)) => a.to_bits() == b.to_bits(),
+            (Self::UInt(a), Self::UInt(b)) => a == b,
+            (Self::Bool(a), Self::Bool(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for ConstantValue {}
+
+impl fmt::Display for ConstantValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Int(value) => write!(f, "{}", value),
+            Self::Float(value) => write!(f, "{}", value),
+            Self::Double(value) => write!(f, "{}", value),
+            Self::UInt(value) => write!(f, "{}u", value),
+            Self::Bool(value) => write!(f, "{}", value),
         }
     }
 }
@@ -4,7 +4,7 @@ fn test_codegen() -> tinygl_compiler::Result<()> {
     use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind};
 
     // Initialize shaderc compiler
-    let mut compiler = Compiler::new(true, None)?.with_shaderc();
+    let mut compiler = Compiler::new(true, None)?.with_shaderc()?;
 
     // Initialize reflection backend
     let reflector = reflect::SpirVBackend::new();
@@ -25,3 +25,935 @@ fn test_codegen() -> tinygl_compiler::Result<()> {
 
     Ok(())
 }
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_hot_reload() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind, WrappedShaderDetails,
+    };
+
+    // Record sources for reloading
+    let mut compiler = Compiler::new(true, None)?;
+    compiler.set_hot_reload(true);
+
+    let reflector = reflect::NullBackend::new();
+
+    // Shaders loaded from files can be reloaded
+    let vert = GlslObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.vert"),
+        None,
+    )?
+    .reflect(&reflector)?;
+    let frag = GlslObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/uv.frag"),
+        None,
+    )?
+    .reflect(&reflector)?;
+
+    let vert = compiler.wrap_shader(vert, false)?;
+    let frag = compiler.wrap_shader(frag, false)?;
+    assert!(vert.reload_info().is_some());
+    assert!(frag.reload_info().is_some());
+
+    let program = compiler.wrap_program(&[&vert, &frag], "quad")?;
+    let tokens = program.generate()?.to_string();
+    assert!(tokens.contains("fn reload"));
+    assert!(tokens.contains("fn source_paths"));
+
+    // Generated shaders can't
+    let generated =
+        GlslObject::from_str(include_str!("../../shaders/uv.frag"), ShaderKind::Fragment)?
+            .reflect(&reflector)?;
+    assert!(compiler
+        .wrap_shader(generated, false)?
+        .reload_info()
+        .is_none());
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_uniform_values() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::*, types::*, Compiler, ShaderKind,
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    let frag = GlslObject::from_str(include_str!("../../shaders/uv.frag"), ShaderKind::Fragment)?;
+    let frag = ReflectedObject::new(
+        frag,
+        vec![
+            FoundUniform {
+                name: "uAlpha".into(),
+                ty: Some(ItemOrArrayType::atom(AtomType::Float)),
+                location_name: "u_alpha_location".into(),
+                ..Default::default()
+            },
+            FoundUniform {
+                name: "uTex".into(),
+                ty: Some(ItemOrArrayType::Image { format: None }),
+                binding: Some(2),
+                location_name: "u_tex_location".into(),
+                ..Default::default()
+            },
+        ],
+    );
+
+    let frag = compiler.wrap_shader(frag, false)?;
+    let program = compiler.wrap_program(&[&frag], "quad")?;
+    let tokens = program.generate()?.to_string();
+
+    // Value struct with public fields and a dirty-tracking apply
+    assert!(tokens.contains("pub struct QuadProgramUniforms"));
+    assert!(tokens.contains("pub u_alpha : f32"));
+    assert!(tokens.contains("pub fn apply"));
+
+    // Samplers default to their binding
+    assert!(tokens.contains("u_tex : 2u32"));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_uniform_set_bindings() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::*, types::*, Compiler, ShaderKind,
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    let sampler = |binding| FoundUniform {
+        name: "uTex".into(),
+        ty: Some(ItemOrArrayType::Image { format: None }),
+        binding,
+        location_name: "u_tex_location".into(),
+        ..Default::default()
+    };
+
+    // Same uniform, with an explicit binding in one program only
+    let with_binding = ReflectedObject::new(
+        GlslObject::from_str("#version 460\n", ShaderKind::Fragment)?,
+        vec![sampler(Some(1))],
+    );
+    let without_binding = ReflectedObject::new(
+        GlslObject::from_str("#version 460 core\n", ShaderKind::Fragment)?,
+        vec![sampler(None)],
+    );
+
+    let with_binding = compiler.wrap_shader(with_binding, false)?;
+    let without_binding = compiler.wrap_shader(without_binding, false)?;
+    let a = compiler.wrap_program(&[&with_binding], "a")?;
+    let b = compiler.wrap_program(&[&without_binding], "b")?;
+
+    let tokens = compiler
+        .wrap_uniforms(&[&a, &b], "set")?
+        .generate()?
+        .to_string();
+
+    // Mismatched bindings are exposed as an Option
+    assert!(tokens.contains("fn get_u_tex_binding (& self) -> Option < u32 > ;"));
+    assert!(tokens.contains("Some (AProgram :: get_u_tex_binding (self))"));
+
+    // Programs of the set can be held without generics
+    assert!(tokens.contains("pub enum SetUniformSetProgram"));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_module_tree() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen, model::*, reflect, Compiler, WrappedShaderDetails};
+
+    // Two shaders with the same file name in different directories
+    let root = std::env::temp_dir().join(format!("tinygl-module-tree-{}", std::process::id()));
+    let source = include_str!("../../shaders/uv.frag");
+    for dir in &["lighting", "post"] {
+        std::fs::create_dir_all(root.join("shaders").join(dir))?;
+        std::fs::write(root.join("shaders").join(dir).join("blur.frag"), source)?;
+    }
+
+    let mut compiler = Compiler::new(true, None)?;
+    let reflector = reflect::NullBackend::new();
+
+    let load = |compiler: &mut Compiler, dir: &str| -> tinygl_compiler::Result<_> {
+        let object = GlslObject::from_path(root.join("shaders").join(dir).join("blur.frag"), None)?
+            .reflect(&reflector)?;
+        compiler.wrap_shader(object, false)
+    };
+
+    // Without a shader root, shaders are generated at the root
+    let lighting = load(&mut compiler, "lighting")?;
+    assert!(lighting.module_path().is_empty());
+    codegen::write(root.join("flat.rs"), &[&lighting])?;
+
+    let flat_rs = std::fs::read_to_string(root.join("flat.rs"))?;
+    assert!(flat_rs.starts_with("pub struct BlurFragShader"));
+    assert!(!flat_rs.contains("pub mod"));
+
+    // With a shader root, they mirror the directory layout
+    compiler.set_shader_root(root.join("shaders"))?;
+    let lighting = load(&mut compiler, "lighting")?;
+    let post = load(&mut compiler, "post")?;
+    assert_eq!(lighting.module_path(), &["lighting", "blur_frag"]);
+
+    let program = compiler.wrap_program(&[&lighting, &post], "blur")?;
+    codegen::write_tree(root.join("out"), &[&lighting, &post, &program])?;
+
+    assert!(root.join("out/lighting/blur_frag.rs").is_file());
+    assert!(root.join("out/post/blur_frag.rs").is_file());
+
+    let mod_rs = std::fs::read_to_string(root.join("out/mod.rs"))?;
+    assert!(mod_rs.contains("pub struct BlurProgram"));
+    assert!(mod_rs.contains("self :: post :: blur_frag :: BlurFragShader"));
+
+    std::fs::remove_dir_all(&root)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "uniform-value")]
+#[test]
+fn test_uniform_value_setters() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::*, types::*, Compiler, ShaderKind,
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    let frag = GlslObject::from_str(include_str!("../../shaders/uv.frag"), ShaderKind::Fragment)?;
+    let frag = ReflectedObject::new(
+        frag,
+        vec![
+            FoundUniform {
+                name: "uVec".into(),
+                ty: Some(ItemOrArrayType::vector(
+                    ItemOrArrayType::atom(AtomType::Float),
+                    3,
+                )?),
+                location_name: "u_vec_location".into(),
+                ..Default::default()
+            },
+            FoundUniform {
+                name: "uMat".into(),
+                ty: Some(ItemOrArrayType::matrix(
+                    ItemOrArrayType::atom(AtomType::Float),
+                    4,
+                )?),
+                location_name: "u_mat_location".into(),
+                ..Default::default()
+            },
+        ],
+    );
+
+    let frag = compiler.wrap_shader(frag, false)?;
+    let program = compiler.wrap_program(&[&frag], "quad")?;
+    let tokens = frag.generate()?.to_string() + &program.generate()?.to_string();
+
+    // Setters accept any UniformValue, and matrices are always column-major
+    assert!(tokens.contains("value : impl :: tinygl :: wrappers :: UniformValue < [f32 ; 3] >"));
+    assert!(
+        tokens.contains("value : impl :: tinygl :: wrappers :: UniformValue < [[f32 ; 4] ; 4] >")
+    );
+    assert!(!tokens.contains("AsRef"));
+    assert!(!tokens.contains("transpose"));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_generated_code_compiles() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::*, types::*, Compiler, ShaderKind,
+    };
+
+    let uniform = |name: &str, ty, binding| FoundUniform {
+        name: name.into(),
+        ty: Some(ty),
+        binding,
+        location_name: format!("{}_location", name),
+        ..Default::default()
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+    compiler.set_hot_reload(true);
+
+    let vert = GlslObject::from_str(include_str!("../../shaders/quad.vert"), ShaderKind::Vertex)?
+        .reflect(&NullBackend::new())?;
+    let mut uniforms = vec![
+        uniform("uFloat", ItemOrArrayType::atom(AtomType::Float), None),
+        uniform("uBool", ItemOrArrayType::atom(AtomType::Bool), None),
+        uniform("uDouble", ItemOrArrayType::atom(AtomType::Double), None),
+        uniform(
+            "uVec",
+            ItemOrArrayType::vector(ItemOrArrayType::atom(AtomType::Float), 3)?,
+            None,
+        ),
+        uniform(
+            "uIvec",
+            ItemOrArrayType::vector(ItemOrArrayType::atom(AtomType::Int), 2)?,
+            None,
+        ),
+        uniform(
+            "uMat",
+            ItemOrArrayType::Item(GenericType::Matrix(MatrixType {
+                base_type: AtomType::Float,
+                n: 4,
+            })),
+            None,
+        ),
+        uniform("uTex", ItemOrArrayType::Image { format: None }, Some(1)),
+        uniform(
+            "uFloats",
+            ItemOrArrayType::array(ItemOrArrayType::atom(AtomType::Float), 2)?,
+            None,
+        ),
+        uniform(
+            "uVecs",
+            ItemOrArrayType::array(
+                ItemOrArrayType::vector(ItemOrArrayType::atom(AtomType::Float), 4)?,
+                3,
+            )?,
+            None,
+        ),
+        uniform(
+            "uBools",
+            ItemOrArrayType::array(ItemOrArrayType::atom(AtomType::Bool), 4)?,
+            None,
+        ),
+    ];
+
+    // Initializers and annotations, for the default values and the metadata table
+    uniforms[0].default = Some(vec![ConstantValue::Float(1.5)]);
+    uniforms[0].annotations = UniformAnnotations {
+        range: Some((0., 10.)),
+        label: Some("Float".into()),
+    };
+    uniforms[1].default = Some(vec![ConstantValue::Bool(true)]);
+    uniforms[3].default = Some(vec![ConstantValue::Float(0.5); 3]);
+    uniforms[5].default = Some(
+        (0..16)
+            .map(|i| ConstantValue::Float(if i % 5 == 0 { 1. } else { 0. }))
+            .collect(),
+    );
+
+    let frag = ReflectedObject::new(
+        GlslObject::from_str(include_str!("../../shaders/uv.frag"), ShaderKind::Fragment)?,
+        uniforms,
+    );
+
+    let vert = compiler.wrap_shader(vert, false)?;
+    let frag = compiler.wrap_shader(frag, false)?;
+    let program = compiler.wrap_program(&[&vert, &frag], "quad")?;
+    let set = compiler.wrap_uniforms(&[&program], "quad")?;
+
+    // Boolean arrays are converted to integers before being uploaded
+    let frag_code = frag.generate()?.to_string();
+    assert!(frag_code.contains("program_uniform1iv (program , location , 4 , value . map (| value | value as i32) . as_ptr ())"));
+
+    // Samplers can only be set using signed integers
+    assert!(frag_code.contains("program_uniform1i (program , location , value as i32)"));
+    assert!(program
+        .generate()?
+        .to_string()
+        .contains("program_uniform1i (program . name , location , self . u_tex as i32)"));
+    assert!(!frag_code.contains("program_uniform1ui"));
+
+    // Shaders loaded from files can be reloaded
+    let reload_vert = GlslObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.vert"),
+        None,
+    )?
+    .reflect(&NullBackend::new())?;
+    let reload_frag = GlslObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/uv.frag"),
+        None,
+    )?
+    .reflect(&NullBackend::new())?;
+    let reload_vert = compiler.wrap_shader(reload_vert, false)?;
+    let reload_frag = compiler.wrap_shader(reload_frag, false)?;
+    let reload_program = compiler.wrap_program(&[&reload_vert, &reload_frag], "reload")?;
+
+    check_generated_code(
+        "quad",
+        &[
+            &vert,
+            &frag,
+            &program,
+            &set,
+            &reload_vert,
+            &reload_frag,
+            &reload_program,
+        ],
+        true,
+    );
+
+    Ok(())
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_generated_transpiled_code_compiles() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{model::*, reflect, Compiler, GlslVersion, ShaderKind};
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // WebGL target, with bindings restored after linking
+    let bindings = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .transpile(GlslVersion::V3_00Es)?;
+    let bindings = compiler.wrap_shader(reflect::ReflectedObject::new(bindings, vec![]), false)?;
+    let bindings_program = compiler.wrap_program(&[&bindings], "bindings")?;
+
+    // SPIR-V binary with a GLSL fallback
+    compiler.set_spirv_fallback(Some(GlslVersion::V4_50));
+    let unused = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/unused.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .reflect(&reflect::SpirVBackend::new())?;
+    let unused = compiler.wrap_shader(unused, true)?;
+    let unused_program = compiler.wrap_program(&[&unused], "unused")?;
+
+    check_generated_code("bindings", &[&bindings, &bindings_program], true);
+
+    // SPIR-V is only supported by desktop OpenGL
+    check_generated_code("fallback", &[&unused, &unused_program], false);
+
+    Ok(())
+}
+
+/// Check that generated code compiles in a scratch crate, for desktop and optionally WebGL
+#[cfg(feature = "codegen")]
+fn check_generated_code(
+    name: &str,
+    items: &[&dyn tinygl_compiler::codegen::WrappedItem],
+    webgl: bool,
+) {
+    use std::process::Command;
+
+    let manifest_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"));
+    let root = std::env::temp_dir().join(format!(
+        "tinygl-codegen-check-{}-{}",
+        name,
+        std::process::id()
+    ));
+    std::fs::create_dir_all(root.join("src")).unwrap();
+    std::fs::write(
+        root.join("Cargo.toml"),
+        format!(
+            "[package]\nname = \"codegen-check-{}\"\nversion = \"0.0.0\"\nedition = \"2018\"\n\n\
+             [dependencies]\ntinygl = {{ path = {:?} }}\n\n[workspace]\n",
+            name,
+            manifest_dir.join("../tinygl")
+        ),
+    )
+    .unwrap();
+    std::fs::copy(manifest_dir.join("../Cargo.lock"), root.join("Cargo.lock")).unwrap();
+    std::fs::write(
+        root.join("src/lib.rs"),
+        "#![allow(dead_code)]\nmod shaders {\n    include!(\"shaders.rs\");\n}\n",
+    )
+    .unwrap();
+    tinygl_compiler::codegen::write(root.join("src/shaders.rs"), items).unwrap();
+
+    // WebGL code is checked too, so its target has to be installed
+    let targets: &[_] = if webgl {
+        &[None, Some("wasm32-unknown-unknown")]
+    } else {
+        &[None]
+    };
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        .unwrap_or_default();
+    assert!(
+        !webgl
+            || std::path::Path::new(&sysroot)
+                .join("lib/rustlib/wasm32-unknown-unknown")
+                .exists(),
+        "checking generated code requires the wasm32-unknown-unknown target, install it with \
+         `rustup target add wasm32-unknown-unknown`"
+    );
+
+    for target in targets {
+        let mut cmd = Command::new(env!("CARGO"));
+        cmd.arg("check").current_dir(&root).env(
+            "CARGO_TARGET_DIR",
+            manifest_dir.join("../target/codegen-check"),
+        );
+        if let Some(target) = target {
+            cmd.args(["--target", target]);
+        }
+
+        let output = cmd.output().expect("failed to run cargo");
+        assert!(
+            output.status.success(),
+            "generated code does not compile for {:?}:\n{}",
+            target,
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_restore_bindings() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::ReflectedObject, Compiler, GlslVersion, ShaderKind,
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // WebGL target, which loses the bindings
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .transpile(GlslVersion::V3_00Es)?;
+
+    let frag = compiler.wrap_shader(ReflectedObject::new(frag, vec![]), false)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "bindings")?
+        .generate()?
+        .to_string();
+
+    // The program sets them after linking
+    assert!(tokens.contains("get_uniform_location (gl , \"uTex\") . set_i32 (gl , 2i32)"));
+    assert!(tokens.contains("get_uniform_block_index (gl , \"Params\")"));
+    assert!(tokens.contains("block_binding (gl , 1u32)"));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_flattened_blocks() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::ReflectedObject, Compiler, GlslVersion, ShaderKind,
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // GLSL ES 1.00 has no uniform blocks
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .transpile(GlslVersion::V1_00Es)?;
+
+    let frag = compiler.wrap_shader(ReflectedObject::new(frag, vec![]), false)?;
+    let tokens = frag.generate()?.to_string();
+
+    // The flattened block gets an array setter
+    assert!(tokens.contains("pub fn set_params"));
+    assert!(tokens.contains("value : & [[f32 ; 4] ; 1]"));
+    assert!(tokens.contains("uniform4fv_with_f32_array"));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_incompatible_source() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::ReflectedObject, Compiler, Error, GlslVersion,
+        IncompatibleSourceMode, ShaderKind, TargetType,
+    };
+
+    let load = || -> tinygl_compiler::Result<_> {
+        // GLSL 4.60 source, along with its SPIR-V
+        let object = SpirVObject::from_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+            Some(ShaderKind::Vertex),
+        )?
+        .transpile(GlslVersion::V4_60)?;
+        Ok(ReflectedObject::new(object, vec![]))
+    };
+
+    let mut compiler = Compiler::new(true, Some(TargetType::Glsl(GlslVersion::V3_00Es)))?;
+
+    // The source can't be used for WebGL
+    match compiler.wrap_shader(load()?, false) {
+        Err(Error::IncompatibleSource(_, reasons)) => {
+            assert_eq!(reasons, &[Incompatibility::Version(GlslVersion::V4_60)])
+        }
+        _ => panic!("expected an incompatible source error"),
+    }
+
+    // Unless it is transpiled from SPIR-V
+    compiler.set_incompatible_source_mode(IncompatibleSourceMode::Transpile);
+    let vert = compiler.wrap_shader(load()?, false)?;
+    assert_eq!(
+        vert.source().map(|source| source.header().version),
+        Some(Some(GlslVersion::V3_00Es))
+    );
+    assert!(vert.generate()?.to_string().contains("#version 300 es"));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_spirv_fallback() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect, Compiler, Error, GlslVersion, ShaderKind,
+        WrappedShaderDetails,
+    };
+
+    let load = || {
+        SpirVObject::from_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/unused.spv"),
+            Some(ShaderKind::Fragment),
+        )?
+        .reflect(&reflect::SpirVBackend::new())
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // Only SPIR-V is embedded by default
+    let tokens = compiler.wrap_shader(load()?, true)?.generate()?.to_string();
+    assert!(tokens.contains("BinaryShader"));
+    assert!(!tokens.contains("FallbackShader"));
+
+    // The fallback source keeps the uniform locations of the binary
+    compiler.set_spirv_fallback(Some(GlslVersion::V4_50));
+    let frag = compiler.wrap_shader(load()?, true)?;
+    let fallback = frag.fallback_source().expect("missing fallback source");
+    assert_eq!(fallback.header().version, Some(GlslVersion::V4_50));
+    for uniform in frag.uniforms() {
+        assert!(fallback
+            .as_str()
+            .contains(&format!("layout(location = {}) uniform", uniform.location)));
+    }
+
+    let tokens = frag.generate()?.to_string();
+    assert!(tokens.contains("impl :: tinygl :: wrappers :: FallbackShader < 'static >"));
+    assert!(tokens.contains("fn get_binary"));
+    assert!(tokens.contains("fn get_source"));
+    assert!(!tokens.contains("BinaryShader"));
+
+    // Versions without explicit uniform locations are rejected
+    compiler.set_spirv_fallback(Some(GlslVersion::V3_30));
+    assert!(matches!(
+        compiler.wrap_shader(load()?, true),
+        Err(Error::InvalidFallbackVersion(GlslVersion::V3_30))
+    ));
+
+    compiler.set_spirv_fallback(Some(GlslVersion::V3_20Es));
+    assert!(matches!(
+        compiler.wrap_shader(load()?, true),
+        Err(Error::InvalidFallbackVersion(GlslVersion::V3_20Es))
+    ));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "spirv", feature = "codegen"))]
+#[test]
+fn test_unused_uniforms() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind, UnusedUniformMode,
+    };
+
+    let load = || {
+        SpirVObject::from_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/unused.spv"),
+            Some(ShaderKind::Fragment),
+        )?
+        .reflect(&reflect::SpirVBackend::new())
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // Setters are kept by default
+    let frag = compiler.wrap_shader(load()?, true)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "unused")?
+        .generate()?
+        .to_string();
+    assert!(tokens.contains("pub fn set_u_unused"));
+    assert!(!tokens.contains("deprecated (note"));
+
+    // Deprecated setters
+    compiler.set_unused_uniform_mode(UnusedUniformMode::Deprecate);
+    let frag = compiler.wrap_shader(load()?, true)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "unused")?
+        .generate()?
+        .to_string();
+    assert!(tokens.contains("# [deprecated (note = \"uUnused is not used by the shader\")]"));
+    assert!(tokens.contains("pub fn set_u_unused"));
+    assert!(!frag.generate()?.to_string().contains("uAlpha is not used"));
+
+    // Omitted setters
+    compiler.set_unused_uniform_mode(UnusedUniformMode::Omit);
+    let frag = compiler.wrap_shader(load()?, true)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "unused")?
+        .generate()?
+        .to_string();
+    assert!(!tokens.contains("set_u_unused"));
+    assert!(tokens.contains("pub fn set_u_tint"));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "codegen", feature = "spirv"))]
+#[test]
+fn test_uniform_defaults() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind};
+
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/defaults.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .reflect(&reflect::SpirVBackend::new())?;
+
+    let mut compiler = Compiler::new(true, None)?;
+    let frag = compiler.wrap_shader(frag, true)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "defaults")?
+        .generate()?
+        .to_string();
+
+    assert!(tokens.contains("pub const fn default_u_exposure () -> f32 { 1.5f32 }"));
+    assert!(tokens.contains("[[1f32 , 0f32] , [0f32 , 1f32]]"));
+    assert!(tokens.contains("pub fn reset_uniforms"));
+    assert!(!tokens.contains("default_u_alpha"));
+
+    Ok(())
+}
+
+#[cfg(all(feature = "codegen", feature = "spirv"))]
+#[test]
+fn test_uniform_metadata() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind};
+
+    let source_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/defaults.frag");
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/defaults.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .reflect(&reflect::SpirVBackend::new())?
+    .annotate(&std::fs::read_to_string(source_path)?, source_path)?;
+
+    let mut compiler = Compiler::new(true, None)?;
+    let frag = compiler.wrap_shader(frag, true)?;
+    let tokens = compiler
+        .wrap_program(&[&frag], "defaults")?
+        .generate()?
+        .to_string();
+
+    assert!(
+        tokens.contains("impl :: tinygl :: wrappers :: ProgramUniformMetadata for DefaultsProgram")
+    );
+    assert!(tokens.contains("name : \"uExposure\" , ty : \"float\" , range : Some ((0f64 , 10f64)) , label : Some (\"Exposure\") , default : Some (& [:: tinygl :: wrappers :: UniformComponent :: Float (1.5f32)])"));
+    assert!(tokens.contains(
+        "name : \"uAlpha\" , ty : \"float\" , range : None , label : None , default : None"
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_multi_stage_program() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler};
+
+    let mut compiler = Compiler::new(true, None)?;
+    compiler.set_hot_reload(true);
+
+    let reflector = reflect::NullBackend::new();
+    let stages = GlslObject::stages_from_path(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../shaders/effect.glsl"
+    ))?
+    .into_iter()
+    .map(|stage| stage.reflect(&reflector))
+    .collect::<tinygl_compiler::Result<Vec<_>>>()?;
+    let stages = compiler.wrap_stages(stages, false)?;
+
+    // Stages are named after the file and their kind
+    assert!(stages[0]
+        .generate()?
+        .to_string()
+        .contains("EffectVertShader"));
+    assert!(stages[1]
+        .generate()?
+        .to_string()
+        .contains("EffectFragShader"));
+
+    // The wrapped stages make up a program, which can't be reloaded
+    let tokens = compiler
+        .wrap_program(&stages.shaders(), "effect")?
+        .generate()?
+        .to_string();
+    assert!(tokens.contains("effect_vert_shader : & self :: EffectVertShader"));
+    assert!(tokens.contains("effect_frag_shader : & self :: EffectFragShader"));
+    assert!(!tokens.contains("fn reload"));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_minify_source() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind};
+
+    let load = || {
+        GlslObject::from_str(
+            "#version 300 es
+precision mediump float;
+
+// Scales the input color
+uniform float uScale;
+in vec3 vColor;
+out vec4 fragColor;
+
+vec3 scaled(vec3 color, float amount) {
+    vec3 result = color * amount;
+    return result - -amount;
+}
+
+void main() {
+    /* Apply the scale */
+    vec3 scaledColor = scaled(vColor, uScale);
+    for (int index = 0; index < 2; ++index) {
+        scaledColor *= 0.5;
+    }
+    fragColor = vec4(scaledColor, 1.0);
+}
+",
+            ShaderKind::Fragment,
+        )?
+        .reflect(&reflect::NullBackend::new())
+    };
+
+    let mut compiler = Compiler::new(true, None)?;
+
+    // Sources are embedded as-is by default
+    let frag = compiler.wrap_shader(load()?, false)?;
+    assert!(frag.source().unwrap().as_str().contains("// Scales"));
+
+    compiler.set_minify_source(true);
+    let frag = compiler.wrap_shader(load()?, false)?;
+    let source = frag.source().unwrap().as_str();
+
+    // Comments are removed and locals are renamed
+    assert!(!source.contains("Scales") && !source.contains("Apply"));
+    assert!(!source.contains("scaledColor") && !source.contains("amount"));
+    assert!(source.contains("return c- -b;"));
+
+    // Globals keep their names
+    assert!(source.starts_with("#version 300 es\n"));
+    assert!(source.contains("uniform float uScale;"));
+    assert!(source.contains("in vec3 vColor;out vec4 fragColor;"));
+    assert!(source.contains("vec3 scaled("));
+
+    assert!(frag
+        .generate()?
+        .to_string()
+        .contains("uniform float uScale;"));
+
+    Ok(())
+}
+
+#[cfg(feature = "codegen")]
+#[test]
+fn test_program_binary_cache() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{codegen::WrappedItem, model::*, reflect, Compiler, ShaderKind};
+
+    let mut compiler = Compiler::new(true, None)?;
+    let reflector = reflect::NullBackend::new();
+
+    let source = include_str!("../../shaders/uv.frag");
+    let changed_source = source.replace("uv", "st");
+    let wrap = |compiler: &mut Compiler, source| {
+        let object = GlslObject::from_str(source, ShaderKind::Fragment)?.reflect(&reflector)?;
+        compiler.wrap_shader(object, false)
+    };
+
+    let frag = wrap(&mut compiler, source)?;
+    let same = wrap(&mut compiler, source)?;
+    let changed = wrap(&mut compiler, &changed_source)?;
+
+    let source_hash = |code: &str| {
+        code.split("SOURCE_HASH : u64 = ")
+            .nth(1)
+            .and_then(|rest| rest.split(';').next())
+            .map(str::to_owned)
+    };
+
+    // Shader hashes only depend on the embedded shader
+    let hash = source_hash(&frag.generate()?.to_string());
+    assert!(hash.is_some());
+    assert_eq!(hash, source_hash(&same.generate()?.to_string()));
+    assert_ne!(hash, source_hash(&changed.generate()?.to_string()));
+
+    // Programs can be built through a binary cache on desktop
+    let vert = GlslObject::from_str(include_str!("../../shaders/quad.vert"), ShaderKind::Vertex)?
+        .reflect(&reflector)?;
+    let vert = compiler.wrap_shader(vert, false)?;
+    let program = compiler.wrap_program(&[&vert, &frag], "quad")?;
+    let code = program.generate()?.to_string();
+    assert!(code.contains("pub fn build_with_cache"));
+
+    check_generated_code("binary_cache", &[&vert, &frag, &program], true);
+
+    Ok(())
+}
+
+#[cfg(all(feature = "transpile", feature = "codegen"))]
+#[test]
+fn test_compiler_transpile_options() -> tinygl_compiler::Result<()> {
+    use tinygl_compiler::{
+        codegen::WrappedItem, model::*, reflect::ReflectedObject, Compiler, GlslVersion,
+        IncompatibleSourceMode, ShaderKind, TargetType,
+    };
+
+    let load = || -> tinygl_compiler::Result<_> {
+        let object = SpirVObject::from_path(
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+            Some(ShaderKind::Fragment),
+        )?
+        .transpile(GlslVersion::V4_60)?;
+        Ok(ReflectedObject::new(object, vec![]))
+    };
+
+    let mut compiler = Compiler::new(true, Some(TargetType::Glsl(GlslVersion::V3_00Es)))?;
+    compiler.set_incompatible_source_mode(IncompatibleSourceMode::Transpile);
+
+    // GLSL ES 3.00 keeps uniform blocks by default
+    let tokens = compiler
+        .wrap_shader(load()?, false)?
+        .generate()?
+        .to_string();
+    assert!(!tokens.contains("pub fn set_params"));
+
+    // Codegen follows the options set on the compiler
+    compiler.set_transpile_options(Some(TranspileOptions {
+        flatten_uniform_buffers: true,
+        float_precision: Precision::High,
+        ..TranspileOptions::new(GlslVersion::V3_00Es)
+    }));
+    let frag = compiler.wrap_shader(load()?, false)?;
+    assert!(frag
+        .source()
+        .unwrap()
+        .as_str()
+        .contains("precision highp float;"));
+    assert!(frag.generate()?.to_string().contains("pub fn set_params"));
+
+    Ok(())
+}
@@ -18,6 +18,20 @@ fn test_glsl_from_string() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_unknown_shader_kind() {
+    // Multi-stage sources have no stage extension
+    match GlslObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/effect.glsl"),
+        None,
+    ) {
+        Err(tinygl_compiler::Error::CouldNotDetermineType(path)) => {
+            assert!(path.ends_with("effect.glsl"))
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
+
 #[cfg(feature = "spirv")]
 #[test]
 fn test_spirv_from_bytes() -> Result<()> {
@@ -27,13 +41,81 @@ fn test_spirv_from_bytes() -> Result<()> {
     Ok(())
 }
 
+#[cfg(feature = "spirv")]
+#[test]
+fn test_spirv_strip_debug_info() -> Result<()> {
+    use tinygl_compiler::{reflect, Compiler};
+
+    let mut compiler = Compiler::new(true, None)?;
+    compiler.set_strip_debug_info(true);
+
+    let object = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+        Some(ShaderKind::Vertex),
+    )?;
+    assert!(!object.module().debug_names.is_empty());
+
+    let object = object.reflect(&reflect::SpirVBackend::new())?;
+    let shader = compiler.wrap_shader(object, true)?;
+
+    // Check that the debug info was removed
+    let report = shader.strip_report().expect("missing strip report");
+    assert!(report.stripped_size < report.original_size);
+
+    let stripped = shader.stripped_spirv().expect("missing stripped binary");
+    let bytes: Vec<u8> = stripped.iter().flat_map(|w| w.to_ne_bytes()).collect();
+    let stripped = SpirVObject::from_bytes(&bytes, ShaderKind::Vertex)?;
+    assert!(stripped.module().debug_names.is_empty());
+    assert!(stripped.module().debug_string_source.is_empty());
+
+    // Shaders which don't embed their binary are left alone
+    let object = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+        Some(ShaderKind::Vertex),
+    )?
+    .reflect(&reflect::SpirVBackend::new())?;
+    let shader = compiler.wrap_shader(object, false)?;
+    assert!(shader.strip_report().is_none());
+    assert!(shader.stripped_spirv().is_none());
+
+    Ok(())
+}
+
+#[cfg(feature = "transpile")]
+#[test]
+fn test_glsl_with_spirv() -> Result<()> {
+    let object = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+        Some(ShaderKind::Vertex),
+    )?;
+
+    // Transpile, keeping the SPIR-V binary around
+    let mut object = object.transpile(GlslVersion::V3_00Es)?;
+    object.parse()?;
+
+    assert_eq!(object.version(), Some(GlslVersion::V3_00Es));
+    assert!(!object.module().debug_names.is_empty());
+
+    // Regenerate the GLSL part for another version
+    let object = object.transpile(GlslVersion::V4_60)?;
+    assert!(object.as_str().starts_with("#version 460"));
+
+    // Split into separate objects
+    let (glsl, spirv) = object.split();
+    assert_eq!(glsl.info(), spirv.info());
+    assert!(glsl.as_str().starts_with("#version 460"));
+    assert_eq!(spirv.as_bytes()[0], 0x0723_0203);
+
+    Ok(())
+}
+
 #[cfg(all(feature = "spirv", feature = "backend-shaderc"))]
 #[test]
 fn test_basic_program() -> Result<()> {
     use tinygl_compiler::{reflect, Compiler};
 
     // Initialize shaderc compiler
-    let mut compiler = Compiler::new(true, None)?.with_shaderc();
+    let mut compiler = Compiler::new(true, None)?.with_shaderc()?;
 
     // Initialize reflection backend
     let reflector = reflect::SpirVBackend::new();
@@ -52,3 +134,546 @@ fn test_basic_program() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(all(feature = "spirv", feature = "backend-shaderc"))]
+#[test]
+fn test_compile_all() -> Result<()> {
+    use tinygl_compiler::{reflect, Compiler};
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    let mut compiler = Compiler::new(true, None)?.with_shaderc()?;
+    compiler.set_parallel_jobs(Some(2));
+    assert_send(&compiler);
+
+    let reflector = reflect::SpirVBackend::new();
+    let objects = || -> Result<_> {
+        Ok(vec![
+            GlslObject::from_str(include_str!("../../shaders/quad.vert"), ShaderKind::Vertex)?,
+            GlslObject::from_str(include_str!("../../shaders/uv.frag"), ShaderKind::Fragment)?,
+            GlslObject::from_str(
+                include_str!("../../shaders/uniform_reflect.frag"),
+                ShaderKind::Fragment,
+            )?,
+        ])
+    };
+
+    // Batches give the same results as serial compilation, in order
+    let batch = compiler.compile_and_reflect_all(objects()?, &reflector)?;
+    assert_eq!(batch.len(), 3);
+
+    for (object, reflected) in objects()?.into_iter().zip(&batch) {
+        let serial = object.compile(&mut compiler)?.reflect_spirv(&reflector)?;
+        assert_eq!(serial.info(), reflected.info());
+        assert_eq!(serial.uniforms(), reflected.uniforms());
+        assert_eq!(serial.as_bytes(), reflected.as_bytes());
+    }
+
+    // The first error is reported
+    let invalid = GlslObject::from_str("#version 460\nvoid main() { error }", ShaderKind::Vertex)?;
+    let mut objects = objects()?;
+    objects.insert(1, invalid);
+    assert!(matches!(
+        compiler.compile_all(objects),
+        Err(tinygl_compiler::Error::CompilationError(_, _))
+    ));
+
+    Ok(())
+}
+
+#[cfg(feature = "transpile")]
+#[test]
+fn test_transpile_removed_bindings() -> Result<()> {
+    let object = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+        Some(ShaderKind::Fragment),
+    )?;
+
+    // GLSL ES 3.00 can't declare bindings, so they are recorded instead
+    let object = object.transpile(GlslVersion::V3_00Es)?;
+    assert!(!object.as_str().contains("binding"));
+
+    let removed = object.glsl().removed_bindings();
+    assert!(removed.contains(&RemovedBinding::Sampler {
+        name: "uTex".into(),
+        binding: 2
+    }));
+    assert!(removed.contains(&RemovedBinding::UniformBlock {
+        name: "Params".into(),
+        binding: 1
+    }));
+
+    // Newer versions keep them in the source
+    let object = object.transpile(GlslVersion::V3_10Es)?;
+    assert!(object.as_str().contains("binding = 2"));
+    assert!(object.glsl().removed_bindings().is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "transpile")]
+#[test]
+fn test_transpile_options() -> Result<()> {
+    use tinygl_compiler::types::*;
+
+    // Vertex fixups for WebGL
+    let vert = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+        Some(ShaderKind::Vertex),
+    )?;
+    let vert = vert.transpile(TranspileOptions {
+        flip_vertex_y: true,
+        ..TranspileOptions::new(GlslVersion::V3_00Es)
+    })?;
+    assert!(vert.as_str().contains("gl_Position.y = -gl_Position.y"));
+
+    // Fragment precision
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/bindings.spv"),
+        Some(ShaderKind::Fragment),
+    )?;
+    let frag = frag.transpile(TranspileOptions {
+        float_precision: Precision::High,
+        ..TranspileOptions::new(GlslVersion::V3_00Es)
+    })?;
+    assert!(frag.as_str().contains("precision highp float;"));
+
+    // Uniform blocks are flattened by default for GLSL ES 1.00
+    let frag = frag.transpile(GlslVersion::V1_00Es)?;
+    assert!(frag.as_str().contains("vec4 Params[1];"));
+    assert_eq!(
+        frag.glsl().flattened_blocks(),
+        &[FlattenedBlock {
+            name: "Params".into(),
+            ty: ItemOrArrayType::array(
+                ItemOrArrayType::vector(ItemOrArrayType::atom(AtomType::Float), 4)?,
+                1
+            )?,
+        }]
+    );
+
+    // Flattened blocks have no binding to restore
+    assert_eq!(
+        frag.glsl().removed_bindings(),
+        &[RemovedBinding::Sampler {
+            name: "uTex".into(),
+            binding: 2
+        }]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "transpile")]
+#[test]
+fn test_transpile_foreign() -> Result<()> {
+    use tinygl_compiler::{HlslShaderModel, MslVersion};
+
+    let object = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/quad.spv"),
+        Some(ShaderKind::Vertex),
+    )?;
+
+    let hlsl = object.transpile_hlsl(HlslShaderModel::V5_0)?;
+    assert!(hlsl.as_str().contains("SV_Position"));
+    assert_eq!(hlsl.file_name(), "quad.spv.hlsl");
+
+    let msl = object.transpile_msl(MslVersion::V2_0)?;
+    assert!(msl.as_str().contains("#include <metal_stdlib>"));
+
+    // Results are written next to each other
+    let dir = std::env::temp_dir().join(format!("tinygl-foreign-{}", std::process::id()));
+    let path = msl.write(&dir)?;
+    assert_eq!(path, dir.join("quad.spv.metal"));
+    assert_eq!(std::fs::read_to_string(&path)?, msl.as_str());
+
+    std::fs::remove_dir_all(&dir)?;
+
+    // Stages of multi-stage sources don't overwrite each other
+    struct Precompiled<'a>(&'a SpirVObject<'static>);
+
+    impl GlslCompiler for Precompiled<'_> {
+        fn compile_module(
+            &mut self,
+            _source: &str,
+            _kind: ShaderKind,
+            _source_path: &str,
+        ) -> Result<SpirVModule<'static>> {
+            Ok(self.0.strip_debug_info())
+        }
+    }
+
+    let names = GlslObject::stages_from_path(concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../shaders/effect.glsl"
+    ))?
+    .into_iter()
+    .map(|stage| {
+        let stage = stage.compile(&mut Precompiled(&object))?.into_spirv();
+        Ok(stage.transpile_hlsl(HlslShaderModel::V5_0)?.file_name())
+    })
+    .collect::<Result<Vec<_>>>()?;
+    assert_eq!(names, ["effect.vert.hlsl", "effect.frag.hlsl"]);
+
+    Ok(())
+}
+
+#[test]
+fn test_glsl_header() -> Result<()> {
+    use tinygl_compiler::GlslProfile;
+
+    let header = GlslObject::from_str(
+        "#version 150 compatibility\n#extension GL_ARB_gpu_shader5 : require\n",
+        ShaderKind::Fragment,
+    )?
+    .header();
+
+    assert_eq!(header.version, Some(GlslVersion::V1_50));
+    assert_eq!(header.effective_profile(), Some(GlslProfile::Compatibility));
+    assert_eq!(
+        header.extensions,
+        &[GlslExtension {
+            name: "GL_ARB_gpu_shader5".into(),
+            behavior: ExtensionBehavior::Require
+        }]
+    );
+
+    // Compatibility profile sources need a compatibility context
+    assert_eq!(
+        header.check(GlslVersion::V4_60, GlslProfile::Core),
+        &[Incompatibility::Profile(GlslProfile::Compatibility)]
+    );
+    assert!(header
+        .check(GlslVersion::V4_60, GlslProfile::Compatibility)
+        .is_empty());
+
+    // Desktop sources and extensions are not available on WebGL
+    assert_eq!(
+        header.check(GlslVersion::V3_00Es, GlslProfile::Core),
+        &[
+            Incompatibility::Version(GlslVersion::V1_50),
+            Incompatibility::Extension("GL_ARB_gpu_shader5".into())
+        ]
+    );
+
+    // ES sources run on WebGL 2
+    let header = GlslObject::from_str("#version 100\n", ShaderKind::Fragment)?.header();
+    assert_eq!(header.version, Some(GlslVersion::V1_00Es));
+    assert!(header
+        .check(GlslVersion::V3_00Es, GlslProfile::Core)
+        .is_empty());
+
+    Ok(())
+}
+
+#[cfg(feature = "spirv")]
+#[test]
+fn test_reflect_unused_uniforms() -> Result<()> {
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/unused.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .reflect(&tinygl_compiler::reflect::SpirVBackend::new())?;
+
+    let used: Vec<_> = frag
+        .uniforms()
+        .iter()
+        .map(|u| (u.name.as_str(), u.used))
+        .collect();
+
+    // uTint is only read by a function called from main
+    assert_eq!(
+        used,
+        &[
+            ("uAlpha", Some(true)),
+            ("uUnused", Some(false)),
+            ("uTint", Some(true))
+        ]
+    );
+
+    Ok(())
+}
+
+#[cfg(feature = "spirv")]
+#[test]
+fn test_reflect_uniform_defaults() -> Result<()> {
+    use tinygl_compiler::types::ConstantValue::*;
+
+    let frag = SpirVObject::from_path(
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/defaults.spv"),
+        Some(ShaderKind::Fragment),
+    )?
+    .reflect(&tinygl_compiler::reflect::SpirVBackend::new())?;
+
+    let defaults: Vec<_> = frag
+        .uniforms()
+        .iter()
+        .map(|u| (u.name.as_str(), u.default.clone()))
+        .collect();
+
+    assert_eq!(
+        defaults,
+        &[
+            ("uExposure", Some(vec![Float(1.5)])),
+            ("uTint", Some(vec![Float(1.0), Float(0.5), Float(0.25)])),
+            (
+                "uRotation",
+                Some(vec![Float(1.0), Float(0.0), Float(0.0), Float(1.0)])
+            ),
+            ("uSteps", Some(vec![Int(3)])),
+            ("uEnabled", Some(vec![Bool(true)])),
+            ("uAlpha", None),
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_uniform_annotations() -> Result<()> {
+    use tinygl_compiler::reflect::UniformAnnotations;
+
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/defaults.frag");
+    let annotations = UniformAnnotations::parse(&std::fs::read_to_string(path)?, path)?;
+
+    assert_eq!(annotations["uExposure"].range, Some((0., 10.)));
+    assert_eq!(annotations["uExposure"].label.as_deref(), Some("Exposure"));
+    assert_eq!(annotations["uTint"].label.as_deref(), Some("Tint color"));
+    assert_eq!(annotations["uSteps"].range, Some((1., 8.)));
+    assert!(!annotations.contains_key("uRotation"));
+    assert!(!annotations.contains_key("uAlpha"));
+
+    // Annotations are detached by blank lines, and not found in e-mail addresses
+    let annotations = UniformAnnotations::parse(
+        "// @label(\"A\")\n\nuniform float a;\nuniform float b; // me@label(\"B\")\n",
+        "<string>",
+    )?;
+    assert!(annotations.is_empty());
+
+    // Invalid annotations are reported
+    assert!(matches!(
+        UniformAnnotations::parse("uniform float a; // @range(1)", "<string>"),
+        Err(tinygl_compiler::Error::InvalidAnnotation(_, _))
+    ));
+
+    // Malformed annotations can be skipped, unterminated unknown ones are ignored
+    let (annotations, errors) = UniformAnnotations::parse_lenient(
+        "// @label Exposure\n// @range(0, 1)\nuniform float a;\n// see @foo(\nuniform float b;\n",
+        "<string>",
+    );
+    assert_eq!(annotations["a"].range, Some((0., 1.)));
+    assert_eq!(annotations["a"].label, None);
+    assert!(!annotations.contains_key("b"));
+    assert_eq!(errors.len(), 1);
+    assert_eq!(
+        errors[0].to_string(),
+        "<string>:1: invalid annotation: @label expects a string"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_malformed_annotations() -> Result<()> {
+    use tinygl_compiler::{reflect, Compiler, TargetType};
+
+    let source = "// @range is clamped below\nuniform float uValue;\nvoid main() {}\n";
+    let reflect = || -> Result<_> {
+        GlslObject::from_str(source, ShaderKind::Fragment)?.reflect(&reflect::NullBackend::new())
+    };
+
+    // Malformed annotations are skipped by default
+    let object = reflect()?;
+    assert_eq!(object.annotation_errors().len(), 1);
+    let mut compiler = Compiler::new(true, Some(TargetType::Glsl(GlslVersion::V3_30)))?;
+    compiler.wrap_shader(object, false)?;
+
+    // Strict annotations fail the build
+    compiler.set_strict_annotations(true);
+    assert!(matches!(
+        compiler.wrap_shader(reflect()?, false),
+        Err(tinygl_compiler::Error::InvalidAnnotation(_, _))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_multi_stage_pragmas() -> Result<()> {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../shaders/effect.glsl");
+    let original = std::fs::read_to_string(path)?;
+    let stages = GlslObject::stages_from_path(path)?;
+
+    let kinds: Vec<_> = stages.iter().map(|stage| stage.info().kind).collect();
+    assert_eq!(kinds, &[ShaderKind::Vertex, ShaderKind::Fragment]);
+    assert!(stages.iter().all(|stage| stage.info().multi_stage));
+
+    // Lines are kept in place, and other stages are blanked out
+    for stage in &stages {
+        let lines: Vec<_> = stage.as_str().lines().collect();
+        assert_eq!(lines.len(), original.lines().count());
+        assert_eq!(lines[0], "#version 460 core");
+        assert_eq!(lines[2], "layout(location = 0) uniform float uAlpha;");
+    }
+
+    let vert: Vec<_> = stages[0].as_str().lines().collect();
+    assert_eq!(vert[9], "    uv = position * 0.5 + 0.5;");
+    assert!(!stages[0].as_str().contains("fragColor"));
+
+    let frag: Vec<_> = stages[1].as_str().lines().collect();
+    assert_eq!(frag[18], "    fragColor = vec4(uv, 0.0, uAlpha);");
+    assert!(!stages[1].as_str().contains("gl_Position"));
+
+    Ok(())
+}
+
+#[test]
+fn test_multi_stage_defines() -> Result<()> {
+    let stages = GlslObject::stages_from_str(
+        "#version 300 es\n#ifdef VERTEX_SHADER\nvoid main() {}\n#endif\n#if defined(FRAGMENT_SHADER)\nvoid main() {}\n#endif\n",
+    )?;
+
+    assert_eq!(stages.len(), 2);
+    assert_eq!(stages[0].info().kind, ShaderKind::Vertex);
+    assert!(stages[0]
+        .as_str()
+        .starts_with("#version 300 es\n#define VERTEX_SHADER\n#line 2\n#ifdef VERTEX_SHADER\n"));
+    assert_eq!(stages[1].info().kind, ShaderKind::Fragment);
+    assert!(stages[1]
+        .as_str()
+        .starts_with("#version 300 es\n#define FRAGMENT_SHADER\n#line 2\n"));
+
+    // Before GLSL 3.30, #line sets the number of the directive itself
+    let stages = GlslObject::stages_from_str("#version 150\n#ifdef VERTEX_SHADER\n#endif\n")?;
+    assert!(stages[0].as_str().contains("#line 1\n"));
+
+    assert!(matches!(
+        GlslObject::stages_from_str("#version 460\n#pragma stage(geometry)\n"),
+        Err(tinygl_compiler::Error::UnknownShaderStage(_, _))
+    ));
+    assert!(matches!(
+        GlslObject::stages_from_str("#version 460\nvoid main() {}\n"),
+        Err(tinygl_compiler::Error::NoShaderStages(_))
+    ));
+
+    Ok(())
+}
+
+#[test]
+fn test_lint() -> Result<()> {
+    let source = "#version 460 core
+layout(location = 0) in vec2 uv;
+layout(location = 0) out vec4 fragColor;
+layout(binding = 0) uniform sampler2DArray uLayers;
+layout(binding = 1) uniform sampler2D uTex;
+uniform int uCount;
+
+float half_of(float value) {
+    return value / 2;
+}
+
+void main() {
+    float sum = 0;
+    for (int i = 0; i < uCount; i++) {
+        sum += texture2D(uTex, uv).r * i;
+    }
+
+    int steps = 4;
+    fragColor = vec4(max(sum, 1), half_of(1.0), float(steps), fma(sum, 2.0, 1.0));
+}
+";
+
+    let object = GlslObject::from_str(source, ShaderKind::Fragment)?;
+    let warnings = |target| -> Result<Vec<String>> {
+        Ok(object
+            .lint(ShaderKind::Fragment, target)?
+            .iter()
+            .map(ToString::to_string)
+            .collect())
+    };
+
+    // Desktop targets accept everything
+    assert!(warnings(GlslVersion::V3_30)?.is_empty());
+
+    assert_eq!(
+        warnings(GlslVersion::V3_00Es)?,
+        &[
+            "implicit conversion from int to float for the return value in `half_of` [implicit_conversion]",
+            "implicit conversion from int to float for `sum` in `main` [implicit_conversion]",
+            "`texture2D` used in `main` is not available in GLSL 3.00 es [unavailable_builtin]",
+            "implicit conversion from int to float for the arguments of `max` in `main` [implicit_conversion]",
+            "`fma` used in `main` is not available in GLSL 3.00 es [unavailable_builtin]",
+            "float has no default precision in fragment shaders, `uv` in a global declaration needs a precision qualifier [missing_precision]",
+            "sampler2DArray has no default precision, `uLayers` in a global declaration needs a precision qualifier [missing_precision]",
+        ]
+    );
+
+    // Loops are restricted in GLSL ES 1.00
+    let es1 = warnings(GlslVersion::V1_00Es)?;
+    assert!(es1.contains(&"for loop over `i` in `main` is not supported by GLSL 1.00 es, the index must be compared to a constant expression [non_constant_loop_index]".to_owned()));
+    assert!(!es1.iter().any(|warning| warning.contains("texture2D")));
+
+    // Precision statements and constant loops are portable
+    let object = GlslObject::from_str(
+        "#version 300 es
+precision mediump float;
+out vec4 fragColor;
+const int STEPS = 4;
+void main() {
+    fragColor = vec4(0.0);
+    for (int i = 0; i < STEPS; i += 2) {
+        fragColor += vec4(float(i) * 0.25);
+    }
+}
+",
+        ShaderKind::Fragment,
+    )?;
+    for target in [GlslVersion::V1_00Es, GlslVersion::V3_00Es] {
+        assert!(object.lint(ShaderKind::Fragment, target)?.is_empty());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_lint_levels() -> Result<()> {
+    use tinygl_compiler::{reflect, Compiler, Error, LintLevel};
+
+    let load = || {
+        GlslObject::from_str(
+            "#version 300 es
+precision mediump float;
+out vec4 fragColor;
+void main() {
+    fragColor = vec4(1) * 2;
+}
+",
+            ShaderKind::Fragment,
+        )?
+        .reflect(&reflect::NullBackend::new())
+    };
+
+    // Desktop shaders are only linted with an explicit target
+    let mut compiler = Compiler::new(true, None)?;
+    compiler.set_lint_level(Lint::ImplicitConversion, LintLevel::Deny);
+    compiler.wrap_shader(load()?, false)?;
+
+    compiler.set_lint_target(Some(GlslVersion::V3_00Es));
+    match compiler.wrap_shader(load()?, false) {
+        Err(Error::LintFailed(_, warnings)) => {
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].lint, Lint::ImplicitConversion);
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    // Allowed lints are not reported
+    compiler.set_lint_level(Lint::ImplicitConversion, LintLevel::Allow);
+    compiler.wrap_shader(load()?, false)?;
+    let source = load()?;
+    assert!(compiler
+        .lint(source.as_source().unwrap(), ShaderKind::Fragment)?
+        .is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,148 @@
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+use tinygl_compiler::{model::*, reflect::*, ShaderKind};
+
+const FIXTURES: &[&[u8]] = &[
+    include_bytes!("../../shaders/quad.spv"),
+    include_bytes!("../../shaders/bindings.spv"),
+    include_bytes!("../../shaders/defaults.spv"),
+    include_bytes!("../../shaders/unused.spv"),
+];
+
+/// Opcodes handled by the reflector, to make random modules more interesting
+const OPCODES: &[u32] = &[
+    5,  // OpName
+    20, // OpTypeBool
+    21, // OpTypeInt
+    22, // OpTypeFloat
+    23, // OpTypeVector
+    24, // OpTypeMatrix
+    25, // OpTypeImage
+    27, // OpTypeSampledImage
+    28, // OpTypeArray
+    32, // OpTypePointer
+    41, // OpConstantTrue
+    43, // OpConstant
+    44, // OpConstantComposite
+    59, // OpVariable
+    71, // OpDecorate
+];
+
+#[derive(Debug, Clone)]
+enum Mutation {
+    Replace(Index, u32),
+    FlipBit(Index, u32),
+    Remove(Index),
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        (any::<Index>(), any::<u32>()).prop_map(|(index, word)| Mutation::Replace(index, word)),
+        (any::<Index>(), 0u32..32).prop_map(|(index, bit)| Mutation::FlipBit(index, bit)),
+        any::<Index>().prop_map(Mutation::Remove),
+    ]
+}
+
+fn instruction() -> impl Strategy<Value = Vec<u32>> {
+    let opcode = prop_oneof![
+        3 => proptest::sample::select(OPCODES),
+        1 => 0u32..400,
+    ];
+    // Small operands are likely to reference other ids
+    let operand = prop_oneof![3 => 0u32..64, 1 => any::<u32>()];
+
+    (opcode, prop::collection::vec(operand, 0..6)).prop_map(|(opcode, operands)| {
+        let word_count = operands.len() as u32 + 1;
+        std::iter::once(word_count << 16 | opcode)
+            .chain(operands)
+            .collect()
+    })
+}
+
+fn to_words(binary: &[u8]) -> Vec<u32> {
+    binary
+        .chunks_exact(4)
+        .map(|word| u32::from_ne_bytes([word[0], word[1], word[2], word[3]]))
+        .collect()
+}
+
+fn to_bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_ne_bytes()).collect()
+}
+
+/// Load and reflect a SPIR-V module, invalid modules may be rejected but must not panic
+fn reflect(binary: &[u8]) {
+    if let Ok(object) = SpirVObject::from_bytes(binary, ShaderKind::Fragment) {
+        let _ = object.reflect(&SpirVBackend::new());
+    }
+}
+
+proptest! {
+    #[test]
+    fn test_reflect_mutated_spirv(
+        fixture in proptest::sample::select(FIXTURES),
+        mutations in prop::collection::vec(mutation(), 1..8),
+    ) {
+        let mut words = to_words(fixture);
+
+        for mutation in mutations {
+            match mutation {
+                Mutation::Replace(index, word) => {
+                    let index = index.index(words.len());
+                    words[index] = word;
+                }
+                Mutation::FlipBit(index, bit) => {
+                    let index = index.index(words.len());
+                    words[index] ^= 1 << bit;
+                }
+                Mutation::Remove(index) => {
+                    if !words.is_empty() {
+                        words.remove(index.index(words.len()));
+                    }
+                }
+            }
+        }
+
+        reflect(&to_bytes(&words));
+    }
+
+    #[test]
+    fn test_reflect_truncated_spirv(
+        fixture in proptest::sample::select(FIXTURES),
+        len in any::<Index>(),
+    ) {
+        reflect(&fixture[..len.index(fixture.len())]);
+    }
+
+    #[test]
+    fn test_reflect_random_spirv(
+        instructions in prop::collection::vec(instruction(), 0..48),
+    ) {
+        // Valid header: magic number, version 1.0, generator, id bound and schema
+        let mut words = vec![0x0723_0203, 0x0001_0000, 0, 64, 0];
+        words.extend(instructions.into_iter().flatten());
+
+        reflect(&to_bytes(&words));
+    }
+}
+
+#[test]
+fn test_reflect_invalid_spirv() -> tinygl_compiler::Result<()> {
+    // A uniform whose type is not a pointer
+    let mut words = vec![0x0723_0203, 0x0001_0000, 0, 16, 0];
+    words.extend([4 << 16 | 5, 1, u32::from_le_bytes(*b"uCol"), 0]); // OpName %1 "uCol"
+    words.extend([3 << 16 | 22, 2, 32]); // %2 = OpTypeFloat 32
+    words.extend([4 << 16 | 59, 2, 1, 0]); // %1 = OpVariable %2 UniformConstant
+
+    let object = SpirVObject::from_bytes(&to_bytes(&words), ShaderKind::Fragment)?;
+    match object.reflect(&SpirVBackend::new()) {
+        Err(tinygl_compiler::Error::ReflectionFailed(_, error)) => match *error {
+            tinygl_compiler::Error::InvalidSpirV(symbol, _) => assert_eq!(symbol, "%1 (uCol)"),
+            other => panic!("unexpected error: {}", other),
+        },
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+
+    Ok(())
+}
@@ -13,7 +13,7 @@ fn find<'p>(
 #[test]
 #[allow(unused_variables)]
 fn uniform_reflect() -> Result<()> {
-    let mut compiler = Compiler::new(true, None)?.with_shaderc();
+    let mut compiler = Compiler::new(true, None)?.with_shaderc()?;
 
     let backend = reflect::SpirVBackend::new();
 
@@ -30,6 +30,8 @@ impl std::error::Error for OpenGlErrorCode {}
 pub enum Error {
     #[error("OpenGL error: {0}")]
     OpenGlError(#[from] OpenGlErrorCode),
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
     #[error("failed to create buffer: {0}")]
     BufferCreationFailed(#[source] OpenGlErrorCode),
     #[error("failed to create framebuffer: {0}")]
@@ -38,6 +40,8 @@ pub enum Error {
     RenderbufferCreationFailed(#[source] OpenGlErrorCode),
     #[error("failed to create shader: {0}")]
     ShaderCreationFailed(#[source] OpenGlErrorCode),
+    #[error("failed to preprocess shader: {0}")]
+    ShaderPreprocessingFailed(String),
     #[error("failed to compile shader: {0}")]
     ShaderCompilationFailed(String),
     #[error("failed to create texture: {0}")]
@@ -46,6 +50,10 @@ pub enum Error {
     ProgramCreationFailed(#[source] OpenGlErrorCode),
     #[error("failed to link program: {0}")]
     ProgramLinkFailed(String),
+    #[error("invalid name {0:?}: names can't contain NUL characters")]
+    InvalidName(String),
+    #[error("{0} is not supported by this backend")]
+    UnsupportedOperation(&'static str),
     #[error("query creation failed: {0}")]
     QueryCreationFailed(#[source] OpenGlErrorCode),
     #[error("vertex array creation failed: {0}")]
@@ -43,6 +43,7 @@ pub type VertexArrayName = types::GLuint;
 
 pub struct Context {
     gl: Gl,
+    spirv_support: std::cell::Cell<Option<bool>>,
 }
 
 extern "system" fn tinygl_debug_message_callback<F>(
@@ -86,6 +87,7 @@ impl Context {
     {
         let gl = Self {
             gl: Gl::load_with(loader_function),
+            spirv_support: std::cell::Cell::new(None),
         };
 
         // Setup logging on the context
@@ -156,6 +158,79 @@ impl Context {
         );
     }
 
+    /// Return `true` if SPIR-V shaders can be loaded in this context
+    ///
+    /// This requires OpenGL 4.6 or the GL_ARB_gl_spirv extension, and the `opengl46` feature.
+    /// The result is detected on the first call, unless it was set using `set_spirv_support`.
+    ///
+    /// # Safety
+    ///
+    /// Requires this context to be current.
+    pub unsafe fn supports_spirv(&self) -> bool {
+        if let Some(supported) = self.spirv_support.get() {
+            return supported;
+        }
+
+        let supported = self.detect_spirv_support();
+        self.spirv_support.set(Some(supported));
+        supported
+    }
+
+    /// Override the detection of SPIR-V support, e.g. for drivers with broken SPIR-V support
+    ///
+    /// # Parameters
+    ///
+    /// * `supported`: `true` if SPIR-V shaders should be loaded in this context
+    pub fn set_spirv_support(&self, supported: bool) {
+        self.spirv_support.set(Some(supported));
+    }
+
+    /// Return `true` if this context has at least the given version, or the given extension
+    ///
+    /// # Parameters
+    ///
+    /// * `version`: major and minor version which includes the feature
+    /// * `extension`: name of the extension which provides the feature
+    ///
+    /// # Safety
+    ///
+    /// Requires this context to be current.
+    pub unsafe fn supports_version_or_extension(
+        &self,
+        version: (i32, i32),
+        extension: &str,
+    ) -> bool {
+        let mut major = 0;
+        let mut minor = 0;
+        self.gl.get_integerv(MAJOR_VERSION, &mut major);
+        self.gl.get_integerv(MINOR_VERSION, &mut minor);
+        if (major, minor) >= version {
+            return true;
+        }
+
+        let mut num_extensions = 0;
+        self.gl.get_integerv(NUM_EXTENSIONS, &mut num_extensions);
+        (0..num_extensions as u32).any(|index| {
+            let name = self.gl.get_stringi(EXTENSIONS, index);
+            !name.is_null()
+                && std::ffi::CStr::from_ptr(name as *const _).to_bytes() == extension.as_bytes()
+        })
+    }
+
+    #[cfg(feature = "opengl46")]
+    unsafe fn detect_spirv_support(&self) -> bool {
+        if !self.gl.SpecializeShader.is_loaded() {
+            return false;
+        }
+
+        self.supports_version_or_extension((4, 6), "GL_ARB_gl_spirv")
+    }
+
+    #[cfg(not(feature = "opengl46"))]
+    unsafe fn detect_spirv_support(&self) -> bool {
+        false
+    }
+
     pub unsafe fn check_last_error(&self) -> crate::Result<()> {
         match self.get_error() {
             NO_ERROR => Ok(()),
@@ -641,3 +716,12 @@ impl std::ops::DerefMut for Context {
         &mut self.gl
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_context_is_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<super::Context>();
+    }
+}
@@ -665,6 +665,34 @@ impl Context {
             .unwrap_or(false)
     }
 
+    /// Run `f` with `program` in use, then restore the previously used program
+    ///
+    /// WebGL has no `programUniform*` functions, so uniforms can only be set on the program in
+    /// use. This lets them be set without changing the current program for the caller.
+    ///
+    /// # Parameters
+    ///
+    /// * `program`: program to use while running `f`
+    /// * `f`: function to run
+    pub fn with_program<R>(&self, program: ProgramName, f: impl FnOnce() -> R) -> R {
+        use wasm_bindgen::JsCast;
+
+        let previous = self
+            .gl
+            .get_parameter(CURRENT_PROGRAM)
+            .ok()
+            .and_then(|value| value.dyn_into::<WebGlProgram>().ok());
+
+        if previous.as_ref() == Some(program) {
+            return f();
+        }
+
+        self.gl.use_program(Some(program));
+        let result = f();
+        self.gl.use_program(previous.as_ref());
+        result
+    }
+
     pub unsafe fn bind_buffer(&self, target: u32, buffer: Option<&wrappers::Buffer>) {
         self.gl.bind_buffer(target, buffer.map(|b| b.name()));
     }
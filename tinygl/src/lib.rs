@@ -4,6 +4,8 @@ pub use error::*;
 pub mod gl;
 pub use gl::Context;
 
+pub mod preprocessor;
+
 pub mod wrappers;
 
 pub mod prelude {
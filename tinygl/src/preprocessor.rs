@@ -0,0 +1,1040 @@
+//! Runtime GLSL preprocessing
+//!
+//! This resolves `#include` directives against a virtual filesystem and injects `#define`
+//! directives, for shaders which are generated or downloaded at runtime. It doesn't need shaderc
+//! or access to the filesystem, so it can also be used on WebGL.
+//!
+//! Other directives are left to the OpenGL implementation. Conditionals are only followed to
+//! skip the includes of disabled branches: `#ifdef`, `#ifndef` and `#if` directives testing
+//! `defined` macros are evaluated against the injected definitions and the macros defined so
+//! far. Branches depending on other expressions, or on macros predefined by the implementation
+//! such as `GL_ES`, may be enabled: their includes are expanded, but `#pragma once` doesn't
+//! apply to them.
+//!
+//! Since the expanded source is a single string, a [`LineMap`] is kept to translate the line
+//! numbers of compilation errors back to the included files. `#line` directives would make these
+//! line numbers wrong, so they are removed.
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+/// Maximum include depth, unless set using `Preprocessor::set_max_include_depth`
+const DEFAULT_MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Virtual filesystem for resolving `#include` directives
+///
+/// Paths are `/`-separated and relative to the root of the filesystem, without any `.` or `..`
+/// component. This is implemented for maps from paths to sources, and for functions.
+pub trait VirtualFs {
+    /// Read the source at the given path
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: normalized path of the source
+    ///
+    /// # Returns
+    ///
+    /// The source, or `None` if there is no source at this path.
+    fn read(&self, path: &str) -> Option<Cow<'_, str>>;
+}
+
+impl<S: std::hash::BuildHasher> VirtualFs for HashMap<String, String, S> {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        self.get(path).map(|source| Cow::Borrowed(source.as_str()))
+    }
+}
+
+impl<S: std::hash::BuildHasher> VirtualFs for HashMap<&str, &str, S> {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        self.get(path).map(|source| Cow::Borrowed(*source))
+    }
+}
+
+impl VirtualFs for BTreeMap<String, String> {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        self.get(path).map(|source| Cow::Borrowed(source.as_str()))
+    }
+}
+
+impl VirtualFs for BTreeMap<&str, &str> {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        self.get(path).map(|source| Cow::Borrowed(*source))
+    }
+}
+
+impl<F: Fn(&str) -> Option<String>> VirtualFs for F {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        self(path).map(Cow::Owned)
+    }
+}
+
+/// Virtual filesystem reading sources from a directory
+///
+/// Paths of the virtual filesystem are relative to the directory.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct DirectoryFs {
+    root: std::path::PathBuf,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl DirectoryFs {
+    /// Create a new directory filesystem
+    ///
+    /// # Parameters
+    ///
+    /// * `root`: path to the directory
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl VirtualFs for DirectoryFs {
+    fn read(&self, path: &str) -> Option<Cow<'_, str>> {
+        std::fs::read_to_string(self.root.join(path))
+            .ok()
+            .map(Cow::Owned)
+    }
+}
+
+/// Preprocess a GLSL source file, resolving includes like tinygl-compiler at build time
+///
+/// All includes are resolved relative to the including file first, and may refer to parent
+/// directories.
+///
+/// # Parameters
+///
+/// * `path`: path to the GLSL source file
+#[cfg(not(target_arch = "wasm32"))]
+pub fn preprocess_file(path: impl AsRef<std::path::Path>) -> crate::Result<PreprocessedSource> {
+    // Root the virtual filesystem at the root of the real one, so includes can refer to parent
+    // directories
+    let path = std::fs::canonicalize(path)?;
+    let root = path.ancestors().last().unwrap_or(&path);
+    let virtual_path = path
+        .strip_prefix(root)
+        .ok()
+        .and_then(|path| path.to_str())
+        .map(|path| path.replace('\\', "/"))
+        .ok_or_else(|| {
+            crate::Error::ShaderPreprocessingFailed(format!("{}: invalid path", path.display()))
+        })?;
+
+    let fs = DirectoryFs::new(root);
+    let mut preprocessor = Preprocessor::new(&fs);
+    preprocessor.set_relative_system_includes(true);
+    preprocessor.process(&virtual_path)
+}
+
+/// Origins of the lines of a preprocessed source
+#[derive(Debug, Default, Clone)]
+pub struct LineMap {
+    files: Vec<String>,
+    lines: Vec<Option<(usize, usize)>>,
+}
+
+impl LineMap {
+    /// Return the paths of the files which were included in the source, starting with the
+    /// preprocessed file itself
+    pub fn files(&self) -> &[String] {
+        &self.files
+    }
+
+    /// Find the origin of a line of the preprocessed source
+    ///
+    /// # Parameters
+    ///
+    /// * `line`: line number in the preprocessed source, starting at 1
+    ///
+    /// # Returns
+    ///
+    /// The path of the file the line comes from and its line number in this file, or `None` if
+    /// the line was added by the preprocessor.
+    pub fn origin(&self, line: usize) -> Option<(&str, usize)> {
+        let (file, line) = (*self.lines.get(line.checked_sub(1)?)?)?;
+        Some((&self.files[file], line))
+    }
+
+    /// Replace the line numbers of a compilation log with the origins of the lines
+    ///
+    /// References of the form `0:12` and `0(12)` used by most OpenGL implementations are
+    /// rewritten as `path:line` and `path(line)`. Other lines are left untouched.
+    ///
+    /// # Parameters
+    ///
+    /// * `log`: shader info log
+    pub fn map_log(&self, log: &str) -> String {
+        let mut output = String::with_capacity(log.len());
+
+        for l in log.lines() {
+            let mut rest = l;
+            while let Some((start, end, file, line, parens)) = self.find_reference(rest) {
+                output.push_str(&rest[..start]);
+                if parens {
+                    write!(output, "{}({})", file, line).ok();
+                } else {
+                    write!(output, "{}:{}", file, line).ok();
+                }
+                rest = &rest[end..];
+            }
+
+            output.push_str(rest);
+            output.push('\n');
+        }
+
+        output
+    }
+
+    /// Find the first line reference of a log line which can be mapped
+    fn find_reference(&self, l: &str) -> Option<(usize, usize, &str, usize, bool)> {
+        let bytes = l.as_bytes();
+        let digits = |start: usize| {
+            start
+                + bytes[start..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count()
+        };
+
+        let mut start = 0;
+        while start < bytes.len() {
+            // Source string numbers start at a word boundary
+            if !bytes[start].is_ascii_digit()
+                || (start > 0 && bytes[start - 1].is_ascii_alphanumeric())
+            {
+                start += 1;
+                continue;
+            }
+
+            let string_end = digits(start);
+            let parens = match bytes.get(string_end) {
+                Some(b':') => false,
+                Some(b'(') => true,
+                _ => {
+                    start = string_end;
+                    continue;
+                }
+            };
+
+            let line_end = digits(string_end + 1);
+            let end = if parens {
+                (bytes.get(line_end) == Some(&b')')).then(|| line_end + 1)
+            } else {
+                Some(line_end)
+            };
+
+            let origin = end.filter(|_| line_end > string_end + 1).and_then(|end| {
+                let line = l[string_end + 1..line_end].parse().ok()?;
+                Some((end, self.origin(line)?))
+            });
+
+            match origin {
+                Some((end, (file, line))) => return Some((start, end, file, line, parens)),
+                None => start = string_end,
+            }
+        }
+
+        None
+    }
+}
+
+/// Result of preprocessing a GLSL source
+#[derive(Debug, Clone)]
+pub struct PreprocessedSource {
+    source: String,
+    line_map: LineMap,
+}
+
+impl PreprocessedSource {
+    /// Return the preprocessed source
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Return the origins of the lines of the preprocessed source
+    pub fn line_map(&self) -> &LineMap {
+        &self.line_map
+    }
+
+    pub fn into_source(self) -> String {
+        self.source
+    }
+}
+
+/// Runtime GLSL preprocessor
+///
+/// The `TINYGL` macro is defined by default, to match the definitions of tinygl-compiler.
+pub struct Preprocessor<'fs> {
+    fs: &'fs dyn VirtualFs,
+    defines: Vec<(String, String)>,
+    max_include_depth: usize,
+    relative_system_includes: bool,
+}
+
+impl<'fs> Preprocessor<'fs> {
+    /// Create a new preprocessor
+    ///
+    /// # Parameters
+    ///
+    /// * `fs`: virtual filesystem used to resolve includes
+    pub fn new(fs: &'fs dyn VirtualFs) -> Self {
+        Self {
+            fs,
+            defines: vec![(
+                "TINYGL".to_owned(),
+                env!("CARGO_PKG_VERSION_MAJOR").to_owned(),
+            )],
+            max_include_depth: DEFAULT_MAX_INCLUDE_DEPTH,
+            relative_system_includes: false,
+        }
+    }
+
+    /// Define a macro in preprocessed sources
+    ///
+    /// Definitions are inserted after the `#version` directive, in the order they were added.
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: name of the macro
+    /// * `value`: value of the macro, may be empty
+    pub fn define(&mut self, name: impl Into<String>, value: impl Into<String>) -> &mut Self {
+        let name = name.into();
+        let value = value.into();
+
+        match self.defines.iter_mut().find(|(n, _)| *n == name) {
+            Some(define) => define.1 = value,
+            None => self.defines.push((name, value)),
+        }
+
+        self
+    }
+
+    /// Remove a macro definition, including the default `TINYGL` definition
+    ///
+    /// # Parameters
+    ///
+    /// * `name`: name of the macro
+    pub fn undefine(&mut self, name: &str) -> &mut Self {
+        self.defines.retain(|(n, _)| n != name);
+        self
+    }
+
+    /// Return the maximum include depth
+    pub fn max_include_depth(&self) -> usize {
+        self.max_include_depth
+    }
+
+    /// Set the maximum include depth, after which includes are assumed to be recursive
+    ///
+    /// # Parameters
+    ///
+    /// * `max_include_depth`: maximum include depth
+    pub fn set_max_include_depth(&mut self, max_include_depth: usize) {
+        self.max_include_depth = max_include_depth;
+    }
+
+    /// Return `true` if `<name>` includes are resolved relative to the including file first
+    pub fn relative_system_includes(&self) -> bool {
+        self.relative_system_includes
+    }
+
+    /// Set whether `<name>` includes are resolved relative to the including file first, like
+    /// `"name"` includes
+    ///
+    /// This matches the resolution of includes by tinygl-compiler at build time.
+    ///
+    /// # Parameters
+    ///
+    /// * `relative_system_includes`: `true` to resolve `<name>` includes relative to the
+    ///   including file first
+    pub fn set_relative_system_includes(&mut self, relative_system_includes: bool) {
+        self.relative_system_includes = relative_system_includes;
+    }
+
+    /// Preprocess a source from the virtual filesystem
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: path of the source in the virtual filesystem
+    pub fn process(&self, path: &str) -> crate::Result<PreprocessedSource> {
+        let path = normalize(path).ok_or_else(|| invalid_path(path))?;
+        let source = self.fs.read(&path).ok_or_else(|| {
+            crate::Error::ShaderPreprocessingFailed(format!("{}: file not found", path))
+        })?;
+
+        self.process_source(&path, &source)
+    }
+
+    /// Preprocess a source which is not part of the virtual filesystem
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: path of the source, used to resolve relative includes and in error messages
+    /// * `source`: GLSL source
+    pub fn process_source(&self, path: &str, source: &str) -> crate::Result<PreprocessedSource> {
+        let path = normalize(path).ok_or_else(|| invalid_path(path))?;
+        let mut state = State {
+            macros: self
+                .defines
+                .iter()
+                .map(|(name, _)| (name.clone(), Some(true)))
+                .collect(),
+            ..Default::default()
+        };
+
+        // Without a #version directive, definitions go first
+        let has_version = source.lines().any(|l| {
+            directive(l)
+                .map(|(name, _)| name == "version")
+                .unwrap_or(false)
+        });
+        if !has_version {
+            state.write_defines(&self.defines);
+        }
+
+        self.expand(&path, source, 0, &mut state)?;
+
+        Ok(PreprocessedSource {
+            source: state.output,
+            line_map: state.line_map,
+        })
+    }
+
+    fn expand(
+        &self,
+        path: &str,
+        source: &str,
+        depth: usize,
+        state: &mut State,
+    ) -> crate::Result<()> {
+        if state.once.contains(path) {
+            return Ok(());
+        }
+
+        let files = &mut state.line_map.files;
+        let file = match files.iter().position(|file| file == path) {
+            Some(file) => file,
+            None => {
+                files.push(path.to_owned());
+                files.len() - 1
+            }
+        };
+
+        for (index, l) in source.lines().enumerate() {
+            let error = |message: String| {
+                crate::Error::ShaderPreprocessingFailed(format!(
+                    "{}:{}: {}",
+                    path,
+                    index + 1,
+                    message
+                ))
+            };
+
+            // Includes of disabled branches are skipped, so they don't need to exist
+            let active = state.active();
+
+            match directive(l) {
+                Some(("extension", rest)) if rest.starts_with("GL_GOOGLE_include_directive") => {
+                    continue;
+                }
+                // Line numbers are mapped using the line map instead
+                Some(("line", _)) => {
+                    continue;
+                }
+                Some(("ifdef", rest)) => state.push_condition(state.is_defined(first_word(rest))),
+                Some(("ifndef", rest)) => {
+                    state.push_condition(state.is_defined(first_word(rest)).map(|defined| !defined))
+                }
+                Some(("if", rest)) => state.push_condition(state.evaluate(rest)),
+                Some(("elif", rest)) => {
+                    let condition = state.evaluate(rest);
+                    if let Some(conditional) = state.conditionals.last_mut() {
+                        conditional.elif(condition);
+                    }
+                }
+                Some(("else", _)) => {
+                    if let Some(conditional) = state.conditionals.last_mut() {
+                        conditional.elif(Some(true));
+                    }
+                }
+                Some(("endif", _)) => {
+                    state.conditionals.pop();
+                }
+                Some(("define", rest)) if active != Some(false) => {
+                    state.macros.insert(first_word(rest).to_owned(), active);
+                }
+                Some(("undef", rest)) if active != Some(false) => {
+                    state
+                        .macros
+                        .insert(first_word(rest).to_owned(), active.map(|_| false));
+                }
+                Some(("pragma", "once")) | Some(("include", _)) if active == Some(false) => {
+                    continue;
+                }
+                Some(("pragma", "once")) => {
+                    // A branch which may be disabled can't prevent later includes
+                    if active == Some(true) {
+                        state.once.insert(path.to_owned());
+                    }
+
+                    continue;
+                }
+                Some(("include", rest)) => {
+                    if depth >= self.max_include_depth {
+                        return Err(error("maximum include depth exceeded".to_owned()));
+                    }
+
+                    let (included_path, included) = self.resolve(path, rest).map_err(error)?;
+                    self.expand(&included_path, &included, depth + 1, state)?;
+                    continue;
+                }
+                _ => {}
+            }
+
+            state.write_line(l, Some((file, index + 1)));
+
+            if depth == 0 && matches!(directive(l), Some(("version", _))) {
+                state.write_defines(&self.defines);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Resolve the target of an `#include` directive
+    ///
+    /// `"name"` includes are resolved relative to the including file first, then to the root.
+    /// `<name>` includes are resolved relative to the root, unless relative system includes are
+    /// enabled.
+    fn resolve(&self, parent: &str, target: &str) -> Result<(String, Cow<'fs, str>), String> {
+        let (name, relative) = if let Some(rest) = target.strip_prefix('"') {
+            (rest.split('"').next().filter(|_| rest.contains('"')), true)
+        } else if let Some(rest) = target.strip_prefix('<') {
+            (rest.split('>').next().filter(|_| rest.contains('>')), false)
+        } else {
+            (None, false)
+        };
+
+        let name = name
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| format!("malformed #include {}", target))?;
+
+        let mut candidates = Vec::new();
+        if relative || self.relative_system_includes {
+            let directory = parent.rfind('/').map(|end| &parent[..end]).unwrap_or("");
+            candidates.push(format!("{}/{}", directory, name));
+        }
+        candidates.push(name.to_owned());
+
+        candidates
+            .iter()
+            .filter_map(|candidate| normalize(candidate))
+            .find_map(|candidate| {
+                let source = self.fs.read(&candidate)?;
+                Some((candidate, source))
+            })
+            .ok_or_else(|| format!("cannot find include {}", name))
+    }
+}
+
+/// Conditional block, `None` conditions are unknown
+struct Conditional {
+    /// Condition of the current branch
+    current: Option<bool>,
+    /// `true` if a previous or the current branch was enabled
+    taken: Option<bool>,
+}
+
+impl Conditional {
+    /// Enter an `#elif` branch, or an `#else` branch if the condition is `true`
+    fn elif(&mut self, condition: Option<bool>) {
+        self.current = match self.taken {
+            Some(true) => Some(false),
+            Some(false) => condition,
+            None => condition.filter(|condition| !condition),
+        };
+
+        self.taken = match (self.taken, condition) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), condition) => condition,
+            (None, _) => None,
+        };
+    }
+}
+
+#[derive(Default)]
+struct State {
+    output: String,
+    line_map: LineMap,
+    once: HashSet<String>,
+    conditionals: Vec<Conditional>,
+    macros: HashMap<String, Option<bool>>,
+}
+
+impl State {
+    /// Return `true` if the current line is enabled, `None` if it is unknown
+    fn active(&self) -> Option<bool> {
+        let mut active = Some(true);
+
+        for conditional in &self.conditionals {
+            match conditional.current {
+                Some(false) => return Some(false),
+                None => active = None,
+                Some(true) => {}
+            }
+        }
+
+        active
+    }
+
+    fn push_condition(&mut self, condition: Option<bool>) {
+        self.conditionals.push(Conditional {
+            current: condition,
+            taken: condition,
+        });
+    }
+
+    /// Return `true` if a macro is defined, `None` if it is unknown
+    fn is_defined(&self, name: &str) -> Option<bool> {
+        match self.macros.get(name) {
+            Some(defined) => *defined,
+            // Macros predefined by the implementation are unknown
+            None if name.starts_with("GL_") || name.starts_with("__") => None,
+            None => Some(false),
+        }
+    }
+
+    /// Evaluate the condition of an `#if` directive, `None` if it is not only made of a
+    /// `defined` operator or an integer
+    fn evaluate(&self, condition: &str) -> Option<bool> {
+        let condition = condition.split("//").next().unwrap_or("").trim();
+
+        if let Some(rest) = condition.strip_prefix('!') {
+            return self.evaluate(rest).map(|value| !value);
+        }
+
+        if let Some(rest) = condition.strip_prefix("defined") {
+            let rest = rest.trim_start();
+            let name = match rest.strip_prefix('(') {
+                Some(rest) => rest.strip_suffix(')')?.trim(),
+                None => rest,
+            };
+
+            if !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return self.is_defined(name);
+            }
+
+            return None;
+        }
+
+        condition.parse::<i64>().ok().map(|value| value != 0)
+    }
+
+    fn write_line(&mut self, l: &str, origin: Option<(usize, usize)>) {
+        self.output.push_str(l);
+        self.output.push('\n');
+        self.line_map.lines.push(origin);
+    }
+
+    fn write_defines(&mut self, defines: &[(String, String)]) {
+        for (name, value) in defines {
+            if value.is_empty() {
+                self.write_line(&format!("#define {}", name), None);
+            } else {
+                self.write_line(&format!("#define {} {}", name, value), None);
+            }
+        }
+    }
+}
+
+/// Split a preprocessor directive into its name and its trimmed arguments
+fn directive(l: &str) -> Option<(&str, &str)> {
+    let rest = l.trim_start().strip_prefix('#')?.trim_start();
+    let end = rest
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(rest.len());
+    Some((&rest[..end], rest[end..].trim()))
+}
+
+/// Return the first word of the arguments of a directive
+fn first_word(rest: &str) -> &str {
+    rest.split(|c: char| c.is_whitespace() || c == '/' || c == '(')
+        .next()
+        .unwrap_or("")
+}
+
+/// Normalize a path of the virtual filesystem, or return `None` if it escapes the root
+fn normalize(path: &str) -> Option<String> {
+    let mut components = Vec::new();
+
+    for component in path.split('/') {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop()?;
+            }
+            _ => components.push(component),
+        }
+    }
+
+    Some(components.join("/"))
+}
+
+fn invalid_path(path: &str) -> crate::Error {
+    crate::Error::ShaderPreprocessingFailed(format!("{}: invalid path", path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(files: &[(&'static str, &'static str)], path: &str) -> crate::Result<String> {
+        let fs: HashMap<&str, &str> = files.iter().copied().collect();
+        let mut preprocessor = Preprocessor::new(&fs);
+        preprocessor.undefine("TINYGL");
+        preprocessor
+            .process(path)
+            .map(PreprocessedSource::into_source)
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("a/b/c.glsl").as_deref(), Some("a/b/c.glsl"));
+        assert_eq!(normalize("/a//./b/").as_deref(), Some("a/b"));
+        assert_eq!(normalize("a/b/../../c.glsl").as_deref(), Some("c.glsl"));
+        assert_eq!(normalize("a/../../c.glsl"), None);
+        assert_eq!(normalize("..").as_deref(), None);
+    }
+
+    #[test]
+    fn test_resolve() {
+        let fs: HashMap<&str, &str> = [
+            ("shaders/main.frag", ""),
+            ("shaders/common.glsl", "relative"),
+            ("common.glsl", "root"),
+            ("lib/noise.glsl", "noise"),
+        ]
+        .iter()
+        .copied()
+        .collect();
+        let preprocessor = Preprocessor::new(&fs);
+
+        let resolve = |target| {
+            preprocessor
+                .resolve("shaders/main.frag", target)
+                .map(|(path, source)| (path, source.into_owned()))
+        };
+
+        // Quoted includes are relative to the including file first
+        assert_eq!(
+            resolve("\"common.glsl\""),
+            Ok(("shaders/common.glsl".to_owned(), "relative".to_owned()))
+        );
+        assert_eq!(
+            resolve("<common.glsl>"),
+            Ok(("common.glsl".to_owned(), "root".to_owned()))
+        );
+        assert_eq!(
+            resolve("\"../lib/noise.glsl\""),
+            Ok(("lib/noise.glsl".to_owned(), "noise".to_owned()))
+        );
+        assert_eq!(
+            resolve("\"lib/noise.glsl\""),
+            Ok(("lib/noise.glsl".to_owned(), "noise".to_owned()))
+        );
+
+        assert!(resolve("\"missing.glsl\"").is_err());
+        assert!(resolve("\"common.glsl").is_err());
+        assert!(resolve("<>").is_err());
+        assert!(resolve("common.glsl").is_err());
+
+        // System includes can be resolved like quoted includes
+        let mut preprocessor = Preprocessor::new(&fs);
+        preprocessor.set_relative_system_includes(true);
+        assert_eq!(
+            preprocessor
+                .resolve("shaders/main.frag", "<common.glsl>")
+                .map(|(path, source)| (path, source.into_owned())),
+            Ok(("shaders/common.glsl".to_owned(), "relative".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_defines() -> crate::Result<()> {
+        let fs: HashMap<&str, &str> = [("main.frag", "#version 300 es\nvoid main() {}\n")]
+            .iter()
+            .copied()
+            .collect();
+        let mut preprocessor = Preprocessor::new(&fs);
+        preprocessor.define("QUALITY", "2").define("DEBUG", "");
+
+        let source = preprocessor.process("main.frag")?;
+        assert_eq!(
+            source.source(),
+            format!(
+                "#version 300 es\n#define TINYGL {}\n#define QUALITY 2\n#define DEBUG\nvoid main() {{}}\n",
+                env!("CARGO_PKG_VERSION_MAJOR")
+            )
+        );
+
+        // Definitions don't map to any file
+        assert_eq!(source.line_map().origin(1), Some(("main.frag", 1)));
+        assert_eq!(source.line_map().origin(2), None);
+        assert_eq!(source.line_map().origin(5), Some(("main.frag", 2)));
+        assert_eq!(source.line_map().origin(6), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pragma_once() -> crate::Result<()> {
+        let source = process(
+            &[
+                (
+                    "main.frag",
+                    "#include \"a.glsl\"\n#include \"b.glsl\"\n#include \"a.glsl\"\n",
+                ),
+                ("a.glsl", "#pragma once\nint a;\n"),
+                ("b.glsl", "#include \"a.glsl\"\nint b;\n"),
+            ],
+            "main.frag",
+        )?;
+
+        assert_eq!(source, "int a;\nint b;\n");
+
+        // Files without the pragma are included every time
+        let source = process(
+            &[
+                ("main.frag", "#include \"a.glsl\"\n#include \"a.glsl\"\n"),
+                ("a.glsl", "int a;\n"),
+            ],
+            "main.frag",
+        )?;
+
+        assert_eq!(source, "int a;\nint a;\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conditionals() -> crate::Result<()> {
+        let once = ("once.glsl", "#pragma once\nint once;\n");
+
+        // Includes of disabled branches don't need to exist, and don't count for #pragma once
+        let source = process(
+            &[
+                (
+                    "main.frag",
+                    "#ifdef FEATURE\n#include \"missing.glsl\"\n#include \"once.glsl\"\n#endif\n\
+                     #include \"once.glsl\"\n",
+                ),
+                once,
+            ],
+            "main.frag",
+        )?;
+        assert_eq!(source, "#ifdef FEATURE\n#endif\nint once;\n");
+
+        // Macros defined by the source and by the preprocessor are followed
+        let source = process(
+            &[
+                (
+                    "main.frag",
+                    "#define FEATURE 1\n#if defined(FEATURE)\n#include \"once.glsl\"\n\
+                     #else\n#include \"missing.glsl\"\n#endif\n\
+                     #ifndef FEATURE\n#include \"missing.glsl\"\n#elif 1\n#include \"once.glsl\"\n#endif\n",
+                ),
+                once,
+            ],
+            "main.frag",
+        )?;
+        assert_eq!(
+            source,
+            "#define FEATURE 1\n#if defined(FEATURE)\nint once;\n#else\n#endif\n\
+             #ifndef FEATURE\n#elif 1\n#endif\n"
+        );
+
+        // Implementation macros and other expressions are unknown, includes are expanded
+        // without applying #pragma once
+        let source = process(
+            &[
+                (
+                    "main.frag",
+                    "#ifdef GL_ES\n#include \"once.glsl\"\n#endif\n\
+                     #if QUALITY > 1\n#include \"once.glsl\"\n#endif\n#include \"once.glsl\"\n",
+                ),
+                once,
+            ],
+            "main.frag",
+        )?;
+        assert_eq!(
+            source,
+            "#ifdef GL_ES\nint once;\n#endif\n#if QUALITY > 1\nint once;\n#endif\nint once;\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_directive() -> crate::Result<()> {
+        let fs: HashMap<&str, &str> = [("main.frag", "float a;\n#line 100\nfloat b;\n")]
+            .iter()
+            .copied()
+            .collect();
+        let mut preprocessor = Preprocessor::new(&fs);
+        preprocessor.undefine("TINYGL");
+        let source = preprocessor.process("main.frag")?;
+
+        // Renumbered lines can't be mapped, so #line directives are removed
+        assert_eq!(source.source(), "float a;\nfloat b;\n");
+        assert_eq!(
+            source.line_map().map_log("ERROR: 0:2: 'b' : redefinition"),
+            "ERROR: main.frag:3: 'b' : redefinition\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_depth() {
+        let files = [
+            ("main.frag", "#include \"loop.glsl\"\n"),
+            ("loop.glsl", "#include \"loop.glsl\"\n"),
+        ];
+
+        match process(&files, "main.frag") {
+            Err(crate::Error::ShaderPreprocessingFailed(message)) => {
+                assert_eq!(message, "loop.glsl:1: maximum include depth exceeded")
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let fs: HashMap<&str, &str> = [
+            ("main.frag", "#include \"a.glsl\"\n"),
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "int b;\n"),
+        ]
+        .iter()
+        .copied()
+        .collect();
+        let mut preprocessor = Preprocessor::new(&fs);
+
+        preprocessor.set_max_include_depth(1);
+        assert!(preprocessor.process("main.frag").is_err());
+
+        preprocessor.set_max_include_depth(2);
+        assert!(preprocessor.process("main.frag").is_ok());
+    }
+
+    #[test]
+    fn test_line_map() -> crate::Result<()> {
+        let fs: HashMap<&str, &str> = [
+            (
+                "main.frag",
+                "#version 300 es\n#include \"common.glsl\"\nvoid main() {}\n",
+            ),
+            ("common.glsl", "float a;\nfloat b;\n"),
+        ]
+        .iter()
+        .copied()
+        .collect();
+        let source = Preprocessor::new(&fs).process("main.frag")?;
+        let line_map = source.line_map();
+
+        assert_eq!(line_map.files(), ["main.frag", "common.glsl"]);
+        assert_eq!(line_map.origin(0), None);
+        assert_eq!(line_map.origin(3), Some(("common.glsl", 1)));
+        assert_eq!(line_map.origin(4), Some(("common.glsl", 2)));
+        assert_eq!(line_map.origin(5), Some(("main.frag", 3)));
+        assert_eq!(line_map.origin(6), None);
+
+        // Both reference styles are rewritten, other numbers are left alone
+        assert_eq!(
+            line_map.map_log("ERROR: 0:4: 'b' : redefinition\n0(5) : error C1008: undefined\n"),
+            "ERROR: common.glsl:2: 'b' : redefinition\nmain.frag(3) : error C1008: undefined\n"
+        );
+        assert_eq!(
+            line_map.map_log("ERROR: 2 compilation errors. No code generated."),
+            "ERROR: 2 compilation errors. No code generated.\n"
+        );
+
+        // Unmapped lines and partial references are kept
+        assert_eq!(line_map.map_log("0:2: warning"), "0:2: warning\n");
+        assert_eq!(line_map.map_log("0(4 error"), "0(4 error\n");
+        assert_eq!(line_map.map_log("vec4:3"), "vec4:3\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_reference() {
+        let line_map = LineMap {
+            files: vec!["main.frag".to_owned()],
+            lines: vec![Some((0, 10)), Some((0, 20))],
+        };
+
+        assert_eq!(
+            line_map.find_reference("x 0:2: y"),
+            Some((2, 5, "main.frag", 20, false))
+        );
+        assert_eq!(
+            line_map.find_reference("0(1) : y"),
+            Some((0, 4, "main.frag", 10, true))
+        );
+
+        // The first reference which can be mapped is returned
+        assert_eq!(
+            line_map.find_reference("0:3 then 0:1"),
+            Some((9, 12, "main.frag", 10, false))
+        );
+        assert_eq!(line_map.find_reference("a0:1 0:"), None);
+        assert_eq!(line_map.find_reference(""), None);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_directory_fs() -> crate::Result<()> {
+        let root = std::env::temp_dir().join(format!("tinygl-preprocessor-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("shaders"))?;
+        std::fs::write(
+            root.join("shaders/main.frag"),
+            "#include \"../common.glsl\"\n",
+        )?;
+        std::fs::write(root.join("common.glsl"), "int a;\n")?;
+
+        let fs = DirectoryFs::new(&root);
+        let mut preprocessor = Preprocessor::new(&fs);
+        preprocessor.undefine("TINYGL");
+        let source = preprocessor.process("shaders/main.frag");
+
+        std::fs::remove_dir_all(&root)?;
+        assert_eq!(source?.source(), "int a;\n");
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[test]
+    fn test_preprocess_file() -> crate::Result<()> {
+        let root =
+            std::env::temp_dir().join(format!("tinygl-preprocess-file-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("shaders"))?;
+        std::fs::write(
+            root.join("shaders/main.frag"),
+            "#version 300 es\n#include <common.glsl>\n#include \"../lib.glsl\"\n",
+        )?;
+        std::fs::write(root.join("shaders/common.glsl"), "int a;\n")?;
+        std::fs::write(root.join("lib.glsl"), "int b;\n")?;
+
+        // Reloaded shaders resolve system includes relative to the including file, like at build
+        // time
+        let source = preprocess_file(root.join("shaders/main.frag"));
+
+        std::fs::remove_dir_all(&root)?;
+        let source = source?;
+        assert!(source.source().ends_with("int a;\nint b;\n"));
+        assert!(source.line_map().files()[1].ends_with("shaders/common.glsl"));
+
+        Ok(())
+    }
+}
@@ -34,6 +34,12 @@ pub use uniform_block::*;
 mod uniform_location;
 pub use uniform_location::*;
 
+mod uniform_metadata;
+pub use uniform_metadata::*;
+
+mod uniform_value;
+pub use uniform_value::*;
+
 mod vertex_array;
 pub use vertex_array::*;
 
@@ -1,7 +1,10 @@
 //! Wrappers prelude for extension traits
 
 pub use super::program::ProgramCommonExt;
+pub use super::program::ProgramInterfaceExt;
 #[cfg(all(not(target_arch = "wasm32"), feature = "opengl46"))]
 pub use super::shader::BinaryShader;
+#[cfg(not(target_arch = "wasm32"))]
+pub use super::shader::FallbackShader;
 pub use super::shader::ShaderCommon;
 pub use super::shader::SourceShader;
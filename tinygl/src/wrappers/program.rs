@@ -1,8 +1,16 @@
+#[cfg(not(target_arch = "wasm32"))]
+mod program_binary_cache;
+#[cfg(not(target_arch = "wasm32"))]
+pub use program_binary_cache::*;
+
 mod program_common;
 pub use program_common::*;
 
 mod program_common_ext;
 pub use program_common_ext::*;
 
+mod program_interface;
+pub use program_interface::*;
+
 mod runtime_program;
 pub use runtime_program::*;
@@ -0,0 +1,281 @@
+use std::path::PathBuf;
+
+use crate::Context;
+
+use super::{ProgramCommon, RuntimeProgram};
+
+/// Storage backend for [`ProgramBinaryCache`]
+pub trait ProgramBinaryStorage {
+    /// Return the data stored under `key`, or `None` if there is nothing stored
+    fn load(&self, key: &str) -> Option<Vec<u8>>;
+
+    /// Store `data` under `key`, replacing any previously stored data
+    fn store(&self, key: &str, data: &[u8]) -> std::io::Result<()>;
+}
+
+/// Program binary storage as files in a directory
+pub struct DirectoryStorage {
+    path: PathBuf,
+}
+
+impl DirectoryStorage {
+    /// Create a new directory storage
+    ///
+    /// # Parameters
+    ///
+    /// * `path`: path to the directory, created on the first store if it does not exist
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+impl ProgramBinaryStorage for DirectoryStorage {
+    fn load(&self, key: &str) -> Option<Vec<u8>> {
+        std::fs::read(self.path.join(key)).ok()
+    }
+
+    fn store(&self, key: &str, data: &[u8]) -> std::io::Result<()> {
+        std::fs::create_dir_all(&self.path)?;
+
+        // Write to a temporary file first, so an interrupted write can't leave a truncated binary
+        let path = self.path.join(key);
+        let temp_path = path.with_extension("tmp");
+        std::fs::write(&temp_path, data)?;
+        std::fs::rename(temp_path, path)
+    }
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// FNV-1a hash, which is stable across builds unlike the std hashers
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+/// Return the storage key for a program
+///
+/// # Parameters
+///
+/// * `driver_hash`: hash of the driver the program is linked by
+/// * `inputs`: hashes of the shaders of the program
+fn program_key(driver_hash: u64, inputs: &[u64]) -> String {
+    let hash = inputs.iter().fold(
+        fnv1a(FNV_OFFSET_BASIS, &driver_hash.to_le_bytes()),
+        |hash, input| fnv1a(hash, &input.to_le_bytes()),
+    );
+
+    format!("{:016x}", hash)
+}
+
+/// Size of the binary format stored before each binary
+const HEADER_LENGTH: usize = 4;
+
+/// Prefix a program binary with its format, for storage
+fn encode_binary(format: u32, binary: &[u8]) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LENGTH + binary.len());
+    data.extend_from_slice(&format.to_le_bytes());
+    data.extend_from_slice(binary);
+    data
+}
+
+/// Split stored data into the binary format and the binary, `None` if it is truncated
+fn decode_binary(data: &[u8]) -> Option<(u32, &[u8])> {
+    if data.len() <= HEADER_LENGTH {
+        return None;
+    }
+
+    let (format, binary) = data.split_at(HEADER_LENGTH);
+    Some((
+        u32::from_le_bytes([format[0], format[1], format[2], format[3]]),
+        binary,
+    ))
+}
+
+/// Cache of linked program binaries
+///
+/// Binaries are keyed by a hash of the shader inputs and of the `VENDOR`, `RENDERER` and
+/// `VERSION` strings of the context, so a driver update doesn't try to load stale binaries.
+/// Generated programs use a cache in their `build_with_cache` method.
+pub struct ProgramBinaryCache {
+    storage: Box<dyn ProgramBinaryStorage>,
+    driver_hash: u64,
+    supported: bool,
+}
+
+impl ProgramBinaryCache {
+    /// Create a new program binary cache for the given context
+    ///
+    /// If the driver does not support any program binary format, the cache never loads or stores
+    /// anything.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: context the cached programs are built for
+    /// * `storage`: storage backend for the program binaries
+    pub fn new(gl: &Context, storage: impl ProgramBinaryStorage + 'static) -> Self {
+        unsafe {
+            let driver_hash = [crate::gl::VENDOR, crate::gl::RENDERER, crate::gl::VERSION]
+                .iter()
+                .fold(FNV_OFFSET_BASIS, |hash, name| {
+                    let string = gl.get_string(*name);
+                    let bytes = if string.is_null() {
+                        &[][..]
+                    } else {
+                        std::ffi::CStr::from_ptr(string as *const _).to_bytes_with_nul()
+                    };
+
+                    fnv1a(hash, bytes)
+                });
+
+            let mut formats = 0;
+            gl.get_integerv(crate::gl::NUM_PROGRAM_BINARY_FORMATS, &mut formats);
+
+            Self {
+                storage: Box::new(storage),
+                driver_hash,
+                supported: formats > 0,
+            }
+        }
+    }
+
+    /// Return `true` if the driver supports program binaries
+    pub fn is_supported(&self) -> bool {
+        self.supported
+    }
+
+    /// Return the storage key for a program
+    ///
+    /// # Parameters
+    ///
+    /// * `inputs`: hashes of the shaders of the program, e.g. their `SOURCE_HASH` constants
+    pub fn key(&self, inputs: &[u64]) -> String {
+        program_key(self.driver_hash, inputs)
+    }
+
+    /// Load a program from its cached binary
+    ///
+    /// Returns `None` if there is no cached binary, or if the driver rejected it.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: context to create the program in
+    /// * `inputs`: hashes of the shaders of the program
+    pub fn load(&self, gl: &Context, inputs: &[u64]) -> Option<RuntimeProgram> {
+        if !self.supported {
+            return None;
+        }
+
+        let key = self.key(inputs);
+        let data = self.storage.load(&key)?;
+        let (format, binary) = decode_binary(&data)?;
+
+        match RuntimeProgram::build_binary(gl, format, binary) {
+            Ok(program) => Some(program),
+            Err(error) => {
+                log::debug!("ignoring cached program binary {}: {}", key, error);
+                None
+            }
+        }
+    }
+
+    /// Store the binary of a linked program
+    ///
+    /// The program should be linked with the `PROGRAM_BINARY_RETRIEVABLE_HINT` flag set. Failures
+    /// are only logged, since the program can still be linked from its shaders next time.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: context the program was linked in
+    /// * `inputs`: hashes of the shaders of the program
+    /// * `program`: linked program
+    pub fn store(&self, gl: &Context, inputs: &[u64], program: &impl ProgramCommon) {
+        if !self.supported {
+            return;
+        }
+
+        let key = self.key(inputs);
+        let data = unsafe {
+            let mut length = 0;
+            gl.get_programiv(
+                program.name(),
+                crate::gl::PROGRAM_BINARY_LENGTH,
+                &mut length,
+            );
+
+            if length <= 0 {
+                log::warn!("no binary available for program {}", key);
+                return;
+            }
+
+            let mut binary = vec![0u8; length as usize];
+            let mut format = 0;
+            gl.get_program_binary(
+                program.name(),
+                length,
+                &mut length,
+                &mut format,
+                binary.as_mut_ptr() as *mut _,
+            );
+
+            binary.truncate(length.max(0) as usize);
+            encode_binary(format, &binary)
+        };
+
+        if let Err(error) = self.storage.store(&key, &data) {
+            log::warn!("failed to store program binary {}: {}", key, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_key() {
+        let key = program_key(1, &[2, 3]);
+        assert_eq!(key.len(), 16);
+        assert!(key.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(key, program_key(1, &[2, 3]));
+
+        // Keys change with the driver, the shaders and their order
+        assert_ne!(key, program_key(4, &[2, 3]));
+        assert_ne!(key, program_key(1, &[2, 4]));
+        assert_ne!(key, program_key(1, &[3, 2]));
+        assert_ne!(key, program_key(1, &[2]));
+    }
+
+    #[test]
+    fn test_binary_header() {
+        let data = encode_binary(0x1234_5678, &[1, 2, 3]);
+        assert_eq!(data.len(), HEADER_LENGTH + 3);
+        assert_eq!(decode_binary(&data), Some((0x1234_5678, &[1u8, 2, 3][..])));
+
+        // Truncated data and empty binaries can't be loaded
+        for length in 0..=HEADER_LENGTH {
+            assert_eq!(decode_binary(&data[..length]), None);
+        }
+    }
+
+    #[test]
+    fn test_directory_storage() -> std::io::Result<()> {
+        let path = std::env::temp_dir().join(format!("tinygl-binary-cache-{}", std::process::id()));
+        let storage = DirectoryStorage::new(path.join("cache"));
+
+        // The directory is created on the first store
+        assert_eq!(storage.load("key"), None);
+        storage.store("key", &[1, 2, 3, 4, 5])?;
+        assert_eq!(storage.load("key"), Some(vec![1, 2, 3, 4, 5]));
+
+        // Stored data is replaced, including with data shorter than a header
+        storage.store("key", &[6, 7])?;
+        assert_eq!(storage.load("key"), Some(vec![6, 7]));
+        assert_eq!(storage.load("key").as_deref().and_then(decode_binary), None);
+        assert_eq!(storage.load("other"), None);
+
+        std::fs::remove_dir_all(&path)
+    }
+}
@@ -0,0 +1,615 @@
+use crate::Context;
+
+use super::ProgramCommon;
+
+/// Active uniform of a linked program
+#[derive(Debug, Default, Clone)]
+pub struct ActiveUniform {
+    /// Index of the uniform in the program interface
+    pub index: u32,
+    /// Name of the uniform, as reported by the driver
+    pub name: String,
+    /// GL type of the uniform, e.g. `FLOAT_VEC4`
+    pub ty: u32,
+    /// Number of array elements, 1 for non-array uniforms
+    pub size: usize,
+    /// Location of the uniform. `None` for block members, and always `None` on WebGL which
+    /// does not expose numeric locations.
+    pub location: Option<i32>,
+    /// Index of the uniform block this uniform belongs to
+    pub block_index: Option<u32>,
+    /// Offset of the uniform in its uniform block
+    pub offset: Option<usize>,
+}
+
+/// Active vertex attribute or fragment output of a linked program
+#[derive(Debug, Default, Clone)]
+pub struct ActiveVariable {
+    /// Index of the variable in the program interface
+    pub index: u32,
+    /// Name of the variable, as reported by the driver
+    pub name: String,
+    /// GL type of the variable, e.g. `FLOAT_VEC4`
+    pub ty: u32,
+    /// Number of array elements, 1 for non-array variables
+    pub size: usize,
+    /// Location of the variable, `None` for built-in variables
+    pub location: Option<i32>,
+}
+
+/// Active uniform block or shader storage block of a linked program
+#[derive(Debug, Default, Clone)]
+pub struct ActiveBlock {
+    /// Index of the block in the program interface
+    pub index: u32,
+    /// Name of the block, as reported by the driver
+    pub name: String,
+    /// Buffer binding point the block is currently bound to
+    pub binding: u32,
+    /// Minimum buffer size required to back the block, in bytes
+    pub data_size: usize,
+    /// Number of active variables in the block
+    pub active_variables: usize,
+}
+
+/// Active subroutine of a linked program
+#[derive(Debug, Default, Clone)]
+pub struct ActiveSubroutine {
+    /// Shader stage declaring the subroutine, e.g. `FRAGMENT_SHADER`
+    pub shader_kind: u32,
+    /// Index of the subroutine in the stage's subroutine interface
+    pub index: u32,
+    /// Name of the subroutine
+    pub name: String,
+}
+
+/// Program interface queries, to enumerate what a linked program exposes
+///
+/// Desktop contexts use the program interface queries of OpenGL 4.3 or
+/// `GL_ARB_program_interface_query` when available. Otherwise, uniforms, attributes and uniform
+/// blocks are enumerated with the OpenGL 3.x queries, and the other methods return
+/// [`crate::Error::UnsupportedOperation`], as they do on WebGL.
+pub trait ProgramInterfaceExt {
+    /// Return all active uniforms, including uniform block members
+    fn get_active_uniforms(&self, gl: &Context) -> crate::Result<Vec<ActiveUniform>>;
+
+    /// Return all active vertex attributes
+    fn get_active_attributes(&self, gl: &Context) -> crate::Result<Vec<ActiveVariable>>;
+
+    /// Return all active fragment outputs
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::UnsupportedOperation`] on WebGL and without program interface
+    /// queries.
+    fn get_active_outputs(&self, gl: &Context) -> crate::Result<Vec<ActiveVariable>>;
+
+    /// Return all active uniform blocks
+    fn get_active_uniform_blocks(&self, gl: &Context) -> crate::Result<Vec<ActiveBlock>>;
+
+    /// Return all active shader storage blocks
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::UnsupportedOperation`] on WebGL and without program interface
+    /// queries.
+    fn get_active_storage_blocks(&self, gl: &Context) -> crate::Result<Vec<ActiveBlock>>;
+
+    /// Return the active subroutines of every shader stage
+    ///
+    /// # Errors
+    ///
+    /// Returns [`crate::Error::UnsupportedOperation`] on WebGL and without program interface
+    /// queries.
+    fn get_active_subroutines(&self, gl: &Context) -> crate::Result<Vec<ActiveSubroutine>>;
+}
+
+// Errors returned by queries which are not supported
+const OUTPUTS_UNSUPPORTED: crate::Error =
+    crate::Error::UnsupportedOperation("fragment output introspection");
+const STORAGE_BLOCKS_UNSUPPORTED: crate::Error =
+    crate::Error::UnsupportedOperation("shader storage block introspection");
+const SUBROUTINES_UNSUPPORTED: crate::Error =
+    crate::Error::UnsupportedOperation("subroutine introspection");
+
+/// Return `true` if the program interface queries of OpenGL 4.3 can be used
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn supports_program_interface(gl: &Context) -> bool {
+    gl.GetProgramInterfaceiv.is_loaded()
+        && gl.GetProgramResourceiv.is_loaded()
+        && gl.GetProgramResourceName.is_loaded()
+        && gl.supports_version_or_extension((4, 3), "GL_ARB_program_interface_query")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn optional(value: i32) -> Option<i32> {
+    if value < 0 {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Query the name and `properties` of every active resource in `interface`
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_resources(
+    gl: &Context,
+    program: u32,
+    interface: u32,
+    properties: &[u32],
+) -> Vec<(u32, String, Vec<i32>)> {
+    let mut count = 0;
+    gl.get_program_interfaceiv(program, interface, crate::gl::ACTIVE_RESOURCES, &mut count);
+
+    let mut max_name_length = 0;
+    gl.get_program_interfaceiv(
+        program,
+        interface,
+        crate::gl::MAX_NAME_LENGTH,
+        &mut max_name_length,
+    );
+
+    let mut buf = vec![0u8; max_name_length.max(1) as usize];
+    (0..count.max(0) as u32)
+        .map(|index| {
+            // An empty property list is an INVALID_VALUE error
+            let mut values = vec![0i32; properties.len()];
+            if !properties.is_empty() {
+                gl.get_program_resourceiv(
+                    program,
+                    interface,
+                    index,
+                    properties.len() as _,
+                    properties.as_ptr(),
+                    values.len() as _,
+                    std::ptr::null_mut(),
+                    values.as_mut_ptr(),
+                );
+            }
+
+            let mut len = 0;
+            gl.get_program_resource_name(
+                program,
+                interface,
+                index,
+                buf.len() as _,
+                &mut len,
+                buf.as_mut_ptr() as _,
+            );
+
+            let name = String::from_utf8_lossy(&buf[..len.max(0) as usize]).to_string();
+            (index, name, values)
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_variables(gl: &Context, program: u32, interface: u32) -> Vec<ActiveVariable> {
+    get_resources(
+        gl,
+        program,
+        interface,
+        &[crate::gl::TYPE, crate::gl::ARRAY_SIZE, crate::gl::LOCATION],
+    )
+    .into_iter()
+    .map(|(index, name, values)| ActiveVariable {
+        index,
+        name,
+        ty: values[0] as _,
+        size: values[1].max(1) as _,
+        location: optional(values[2]),
+    })
+    .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_blocks(gl: &Context, program: u32, interface: u32) -> Vec<ActiveBlock> {
+    get_resources(
+        gl,
+        program,
+        interface,
+        &[
+            crate::gl::BUFFER_BINDING,
+            crate::gl::BUFFER_DATA_SIZE,
+            crate::gl::NUM_ACTIVE_VARIABLES,
+        ],
+    )
+    .into_iter()
+    .map(|(index, name, values)| ActiveBlock {
+        index,
+        name,
+        binding: values[0] as _,
+        data_size: values[1] as _,
+        active_variables: values[2] as _,
+    })
+    .collect()
+}
+
+/// Query the name, type and size of every active uniform or attribute with the OpenGL 3.x
+/// functions
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_active_infos(
+    gl: &Context,
+    program: u32,
+    attributes: bool,
+) -> Vec<(u32, String, u32, usize)> {
+    let (count_pname, length_pname) = if attributes {
+        (
+            crate::gl::ACTIVE_ATTRIBUTES,
+            crate::gl::ACTIVE_ATTRIBUTE_MAX_LENGTH,
+        )
+    } else {
+        (
+            crate::gl::ACTIVE_UNIFORMS,
+            crate::gl::ACTIVE_UNIFORM_MAX_LENGTH,
+        )
+    };
+
+    let mut count = 0;
+    gl.get_programiv(program, count_pname, &mut count);
+
+    let mut max_name_length = 0;
+    gl.get_programiv(program, length_pname, &mut max_name_length);
+
+    let mut buf = vec![0u8; max_name_length.max(1) as usize];
+    (0..count.max(0) as u32)
+        .map(|index| {
+            let mut len = 0;
+            let mut size = 0;
+            let mut ty = 0;
+            let get_active = if attributes {
+                crate::gl::Gl::get_active_attrib
+            } else {
+                crate::gl::Gl::get_active_uniform
+            };
+            get_active(
+                gl,
+                program,
+                index,
+                buf.len() as _,
+                &mut len,
+                &mut size,
+                &mut ty,
+                buf.as_mut_ptr() as _,
+            );
+
+            let name = String::from_utf8_lossy(&buf[..len.max(0) as usize]).to_string();
+            (index, name, ty, size.max(1) as _)
+        })
+        .collect()
+}
+
+/// Query the location of an active variable, `None` if it has none
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_location(
+    gl: &Context,
+    program: u32,
+    name: &str,
+    get_location: unsafe fn(&crate::gl::Gl, u32, *const crate::gl::types::GLchar) -> i32,
+) -> Option<i32> {
+    // Names reported by the driver never contain NUL characters
+    let name = std::ffi::CString::new(name).ok()?;
+    optional(get_location(gl, program, name.as_ptr()))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_legacy_uniforms(gl: &Context, program: u32) -> Vec<ActiveUniform> {
+    let infos = get_active_infos(gl, program, false);
+    let indices: Vec<_> = infos.iter().map(|(index, _, _, _)| *index).collect();
+
+    // Block indices and offsets are -1 for uniforms outside of blocks
+    let get_parameter = |pname| {
+        let mut values = vec![-1; indices.len()];
+        if !indices.is_empty() {
+            gl.get_active_uniformsiv(
+                program,
+                indices.len() as _,
+                indices.as_ptr(),
+                pname,
+                values.as_mut_ptr(),
+            );
+        }
+        values
+    };
+
+    let block_indices = get_parameter(crate::gl::UNIFORM_BLOCK_INDEX);
+    let offsets = get_parameter(crate::gl::UNIFORM_OFFSET);
+
+    infos
+        .into_iter()
+        .zip(block_indices.into_iter().zip(offsets))
+        .map(
+            |((index, name, ty, size), (block_index, offset))| ActiveUniform {
+                index,
+                location: get_location(gl, program, &name, crate::gl::Gl::get_uniform_location),
+                name,
+                ty,
+                size,
+                block_index: optional(block_index).map(|index| index as _),
+                offset: optional(offset).map(|offset| offset as _),
+            },
+        )
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_legacy_attributes(gl: &Context, program: u32) -> Vec<ActiveVariable> {
+    get_active_infos(gl, program, true)
+        .into_iter()
+        .map(|(index, name, ty, size)| ActiveVariable {
+            index,
+            location: get_location(gl, program, &name, crate::gl::Gl::get_attrib_location),
+            name,
+            ty,
+            size,
+        })
+        .collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+unsafe fn get_legacy_uniform_blocks(gl: &Context, program: u32) -> Vec<ActiveBlock> {
+    let mut count = 0;
+    gl.get_programiv(program, crate::gl::ACTIVE_UNIFORM_BLOCKS, &mut count);
+
+    let mut max_name_length = 0;
+    gl.get_programiv(
+        program,
+        crate::gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH,
+        &mut max_name_length,
+    );
+
+    let mut buf = vec![0u8; max_name_length.max(1) as usize];
+    (0..count.max(0) as u32)
+        .map(|index| {
+            let get_parameter = |pname| {
+                let mut value = 0;
+                gl.get_active_uniform_blockiv(program, index, pname, &mut value);
+                value
+            };
+
+            let mut len = 0;
+            gl.get_active_uniform_block_name(
+                program,
+                index,
+                buf.len() as _,
+                &mut len,
+                buf.as_mut_ptr() as _,
+            );
+
+            ActiveBlock {
+                index,
+                name: String::from_utf8_lossy(&buf[..len.max(0) as usize]).to_string(),
+                binding: get_parameter(crate::gl::UNIFORM_BLOCK_BINDING) as _,
+                data_size: get_parameter(crate::gl::UNIFORM_BLOCK_DATA_SIZE) as _,
+                active_variables: get_parameter(crate::gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS) as _,
+            }
+        })
+        .collect()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn get_program_count(gl: &Context, program: &crate::gl::Program, pname: u32) -> u32 {
+    gl.get_program_parameter(program, pname)
+        .as_f64()
+        .unwrap_or(0.) as _
+}
+
+impl<T: ProgramCommon> ProgramInterfaceExt for T {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_uniforms(&self, gl: &Context) -> crate::Result<Vec<ActiveUniform>> {
+        if !unsafe { supports_program_interface(gl) } {
+            return Ok(unsafe { get_legacy_uniforms(gl, self.name()) });
+        }
+
+        Ok(unsafe {
+            get_resources(
+                gl,
+                self.name(),
+                crate::gl::UNIFORM,
+                &[
+                    crate::gl::TYPE,
+                    crate::gl::ARRAY_SIZE,
+                    crate::gl::LOCATION,
+                    crate::gl::BLOCK_INDEX,
+                    crate::gl::OFFSET,
+                ],
+            )
+        }
+        .into_iter()
+        .map(|(index, name, values)| ActiveUniform {
+            index,
+            name,
+            ty: values[0] as _,
+            size: values[1].max(1) as _,
+            location: optional(values[2]),
+            block_index: optional(values[3]).map(|index| index as _),
+            offset: optional(values[4]).map(|offset| offset as _),
+        })
+        .collect())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_uniforms(&self, gl: &Context) -> crate::Result<Vec<ActiveUniform>> {
+        use wasm_bindgen::JsCast;
+
+        let program = self.name();
+        let count = get_program_count(gl, program, crate::gl::ACTIVE_UNIFORMS);
+        let indices = js_sys::Uint32Array::from(&(0..count).collect::<Vec<_>>()[..]);
+
+        // Block indices and offsets are -1 for uniforms outside of blocks
+        let get_parameter = |pname| -> Vec<Option<i32>> {
+            gl.get_active_uniforms(program, &indices, pname)
+                .unchecked_into::<js_sys::Array>()
+                .iter()
+                .map(|value| value.as_f64().map(|value| value as i32))
+                .map(|value| value.filter(|value| *value >= 0))
+                .collect()
+        };
+
+        let block_indices = get_parameter(crate::gl::UNIFORM_BLOCK_INDEX);
+        let offsets = get_parameter(crate::gl::UNIFORM_OFFSET);
+
+        Ok((0..count)
+            .filter_map(|index| {
+                let info = gl.get_active_uniform(program, index)?;
+
+                Some(ActiveUniform {
+                    index,
+                    name: info.name(),
+                    ty: info.type_(),
+                    size: info.size().max(1) as _,
+                    location: None,
+                    block_index: block_indices
+                        .get(index as usize)
+                        .copied()
+                        .flatten()
+                        .map(|index| index as _),
+                    offset: offsets
+                        .get(index as usize)
+                        .copied()
+                        .flatten()
+                        .map(|offset| offset as _),
+                })
+            })
+            .collect())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_attributes(&self, gl: &Context) -> crate::Result<Vec<ActiveVariable>> {
+        unsafe {
+            Ok(if supports_program_interface(gl) {
+                get_variables(gl, self.name(), crate::gl::PROGRAM_INPUT)
+            } else {
+                get_legacy_attributes(gl, self.name())
+            })
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_attributes(&self, gl: &Context) -> crate::Result<Vec<ActiveVariable>> {
+        let program = self.name();
+        let count = get_program_count(gl, program, crate::gl::ACTIVE_ATTRIBUTES);
+
+        Ok((0..count)
+            .filter_map(|index| {
+                let info = gl.get_active_attrib(program, index)?;
+                let location = gl.get_attrib_location(program, &info.name());
+
+                Some(ActiveVariable {
+                    index,
+                    name: info.name(),
+                    ty: info.type_(),
+                    size: info.size().max(1) as _,
+                    location: if location < 0 { None } else { Some(location) },
+                })
+            })
+            .collect())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_outputs(&self, gl: &Context) -> crate::Result<Vec<ActiveVariable>> {
+        unsafe {
+            if !supports_program_interface(gl) {
+                return Err(OUTPUTS_UNSUPPORTED);
+            }
+
+            Ok(get_variables(gl, self.name(), crate::gl::PROGRAM_OUTPUT))
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_outputs(&self, _gl: &Context) -> crate::Result<Vec<ActiveVariable>> {
+        Err(OUTPUTS_UNSUPPORTED)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_uniform_blocks(&self, gl: &Context) -> crate::Result<Vec<ActiveBlock>> {
+        unsafe {
+            Ok(if supports_program_interface(gl) {
+                get_blocks(gl, self.name(), crate::gl::UNIFORM_BLOCK)
+            } else {
+                get_legacy_uniform_blocks(gl, self.name())
+            })
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_uniform_blocks(&self, gl: &Context) -> crate::Result<Vec<ActiveBlock>> {
+        let program = self.name();
+        let count = get_program_count(gl, program, crate::gl::ACTIVE_UNIFORM_BLOCKS);
+
+        let get_parameter = |index, pname| {
+            gl.get_active_uniform_block_parameter(program, index, pname)
+                .ok()
+                .and_then(|value| value.as_f64())
+                .unwrap_or(0.)
+        };
+
+        Ok((0..count)
+            .map(|index| ActiveBlock {
+                index,
+                name: gl
+                    .get_active_uniform_block_name(program, index)
+                    .unwrap_or_default(),
+                binding: get_parameter(index, crate::gl::UNIFORM_BLOCK_BINDING) as _,
+                data_size: get_parameter(index, crate::gl::UNIFORM_BLOCK_DATA_SIZE) as _,
+                active_variables: get_parameter(index, crate::gl::UNIFORM_BLOCK_ACTIVE_UNIFORMS)
+                    as _,
+            })
+            .collect())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_storage_blocks(&self, gl: &Context) -> crate::Result<Vec<ActiveBlock>> {
+        unsafe {
+            if !supports_program_interface(gl) {
+                return Err(STORAGE_BLOCKS_UNSUPPORTED);
+            }
+
+            Ok(get_blocks(gl, self.name(), crate::gl::SHADER_STORAGE_BLOCK))
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_storage_blocks(&self, _gl: &Context) -> crate::Result<Vec<ActiveBlock>> {
+        Err(STORAGE_BLOCKS_UNSUPPORTED)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn get_active_subroutines(&self, gl: &Context) -> crate::Result<Vec<ActiveSubroutine>> {
+        if !unsafe { supports_program_interface(gl) } {
+            return Err(SUBROUTINES_UNSUPPORTED);
+        }
+
+        Ok([
+            (crate::gl::VERTEX_SHADER, crate::gl::VERTEX_SUBROUTINE),
+            (
+                crate::gl::TESS_CONTROL_SHADER,
+                crate::gl::TESS_CONTROL_SUBROUTINE,
+            ),
+            (
+                crate::gl::TESS_EVALUATION_SHADER,
+                crate::gl::TESS_EVALUATION_SUBROUTINE,
+            ),
+            (crate::gl::GEOMETRY_SHADER, crate::gl::GEOMETRY_SUBROUTINE),
+            (crate::gl::FRAGMENT_SHADER, crate::gl::FRAGMENT_SUBROUTINE),
+            (crate::gl::COMPUTE_SHADER, crate::gl::COMPUTE_SUBROUTINE),
+        ]
+        .iter()
+        .flat_map(|(shader_kind, interface)| {
+            unsafe { get_resources(gl, self.name(), *interface, &[]) }
+                .into_iter()
+                .map(move |(index, name, _)| ActiveSubroutine {
+                    shader_kind: *shader_kind,
+                    index,
+                    name,
+                })
+        })
+        .collect())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn get_active_subroutines(&self, _gl: &Context) -> crate::Result<Vec<ActiveSubroutine>> {
+        Err(SUBROUTINES_UNSUPPORTED)
+    }
+}
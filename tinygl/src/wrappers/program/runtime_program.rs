@@ -8,6 +8,41 @@ pub struct RuntimeProgram {
 }
 
 impl RuntimeProgram {
+    /// Create a program from a binary returned by `glGetProgramBinary`
+    ///
+    /// Drivers may reject binaries created by other drivers or versions, in which case this
+    /// returns `Error::ProgramLinkFailed` and the program has to be linked from its shaders.
+    ///
+    /// # Parameters
+    ///
+    /// * `format`: binary format returned along with the binary
+    /// * `binary`: program binary
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_binary(gl: &Context, format: u32, binary: &[u8]) -> crate::Result<Self> {
+        unsafe {
+            let mut program = RuntimeProgram {
+                name: gl.create_program().ok_or_else(|| {
+                    crate::Error::ProgramCreationFailed(OpenGlErrorCode(gl.get_error()))
+                })?,
+            };
+
+            gl.program_binary(
+                program.name(),
+                format,
+                binary.as_ptr() as *const _,
+                binary.len() as i32,
+            );
+
+            if !gl.get_program_link_status(program.name()) {
+                let error = gl.get_program_info_log(program.name());
+                program.drop(gl);
+                return Err(crate::Error::ProgramLinkFailed(error.unwrap_or_default()));
+            }
+
+            Ok(program)
+        }
+    }
+
     pub fn into_inner(self) -> crate::gl::Program {
         self.name
     }
@@ -28,6 +63,11 @@ impl ProgramCommon for RuntimeProgram {
 pub struct RuntimeProgramBuilder<'a> {
     gl: &'a Context,
     shaders: Vec<&'a dyn ShaderCommon>,
+    attrib_locations: Vec<(u32, String)>,
+    frag_data_locations: Vec<(u32, String)>,
+    transform_feedback_varyings: Option<(Vec<String>, u32)>,
+    separable: bool,
+    binary_retrievable_hint: bool,
 }
 
 impl<'a> RuntimeProgramBuilder<'a> {
@@ -35,6 +75,11 @@ impl<'a> RuntimeProgramBuilder<'a> {
         Self {
             gl,
             shaders: Vec::with_capacity(2),
+            attrib_locations: Vec::new(),
+            frag_data_locations: Vec::new(),
+            transform_feedback_varyings: None,
+            separable: false,
+            binary_retrievable_hint: false,
         }
     }
 
@@ -43,7 +88,189 @@ impl<'a> RuntimeProgramBuilder<'a> {
         self
     }
 
+    /// Bind a vertex shader input to an attribute location before linking
+    ///
+    /// # Parameters
+    ///
+    /// * `index`: attribute location
+    /// * `name`: name of the vertex shader input
+    pub fn bind_attrib_location(mut self, index: u32, name: impl Into<String>) -> Self {
+        self.attrib_locations.push((index, name.into()));
+        self
+    }
+
+    /// Bind a fragment shader output to a draw buffer before linking
+    ///
+    /// Not supported by WebGL, where `build` fails with `Error::UnsupportedOperation`.
+    ///
+    /// # Parameters
+    ///
+    /// * `color_number`: index of the draw buffer
+    /// * `name`: name of the fragment shader output
+    pub fn bind_frag_data_location(mut self, color_number: u32, name: impl Into<String>) -> Self {
+        self.frag_data_locations.push((color_number, name.into()));
+        self
+    }
+
+    /// Set the outputs recorded by transform feedback
+    ///
+    /// # Parameters
+    ///
+    /// * `varyings`: names of the recorded outputs
+    /// * `buffer_mode`: `INTERLEAVED_ATTRIBS` to record all outputs in a single buffer, or
+    ///   `SEPARATE_ATTRIBS` to record each output in its own buffer
+    pub fn transform_feedback_varyings<S: Into<String>>(
+        mut self,
+        varyings: impl IntoIterator<Item = S>,
+        buffer_mode: u32,
+    ) -> Self {
+        self.transform_feedback_varyings =
+            Some((varyings.into_iter().map(Into::into).collect(), buffer_mode));
+        self
+    }
+
+    /// Set the `PROGRAM_SEPARABLE` flag, for binding the program to a pipeline object
+    ///
+    /// Requires OpenGL 4.1 or `GL_ARB_separate_shader_objects`, and is not supported by WebGL.
+    /// Otherwise, `build` fails with `Error::UnsupportedOperation`.
+    pub fn separable(mut self, separable: bool) -> Self {
+        self.separable = separable;
+        self
+    }
+
+    /// Set the `PROGRAM_BINARY_RETRIEVABLE_HINT` flag, for retrieving the linked binary
+    ///
+    /// Requires OpenGL 4.1 or `GL_ARB_get_program_binary`, and is not supported by WebGL.
+    /// Otherwise, `build` fails with `Error::UnsupportedOperation`.
+    pub fn binary_retrievable_hint(mut self, binary_retrievable_hint: bool) -> Self {
+        self.binary_retrievable_hint = binary_retrievable_hint;
+        self
+    }
+
+    /// Check that names can be passed to OpenGL
+    fn check_names(&self) -> crate::Result<()> {
+        let names = self
+            .attrib_locations
+            .iter()
+            .chain(&self.frag_data_locations)
+            .map(|(_, name)| name)
+            .chain(self.transform_feedback_varyings.iter().flat_map(|(v, _)| v));
+
+        for name in names {
+            if name.contains('\0') {
+                return Err(crate::Error::InvalidName(name.clone()));
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn check_support(&self) -> crate::Result<()> {
+        // Both flags are set with glProgramParameteri, from OpenGL 4.1 or their extensions
+        let supported = |extension| unsafe {
+            self.gl.ProgramParameteri.is_loaded()
+                && self.gl.supports_version_or_extension((4, 1), extension)
+        };
+
+        if self.separable && !supported("GL_ARB_separate_shader_objects") {
+            Err(crate::Error::UnsupportedOperation("PROGRAM_SEPARABLE"))
+        } else if self.binary_retrievable_hint && !supported("GL_ARB_get_program_binary") {
+            Err(crate::Error::UnsupportedOperation(
+                "PROGRAM_BINARY_RETRIEVABLE_HINT",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn check_support(&self) -> crate::Result<()> {
+        if !self.frag_data_locations.is_empty() {
+            Err(crate::Error::UnsupportedOperation(
+                "bind_frag_data_location",
+            ))
+        } else if self.separable {
+            Err(crate::Error::UnsupportedOperation("PROGRAM_SEPARABLE"))
+        } else if self.binary_retrievable_hint {
+            Err(crate::Error::UnsupportedOperation(
+                "PROGRAM_BINARY_RETRIEVABLE_HINT",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    unsafe fn pre_link(&self, program: crate::gl::ProgramName) {
+        // Names were validated by check_names
+        let cstr = |name: &str| std::ffi::CString::new(name).unwrap_or_default();
+
+        for (index, name) in &self.attrib_locations {
+            let name = cstr(name);
+            self.gl.bind_attrib_location(program, *index, name.as_ptr());
+        }
+
+        for (color_number, name) in &self.frag_data_locations {
+            let name = cstr(name);
+            self.gl
+                .bind_frag_data_location(program, *color_number, name.as_ptr());
+        }
+
+        if let Some((varyings, buffer_mode)) = &self.transform_feedback_varyings {
+            let names: Vec<_> = varyings.iter().map(|name| cstr(name)).collect();
+            let pointers: Vec<_> = names.iter().map(|name| name.as_ptr()).collect();
+
+            self.gl.transform_feedback_varyings(
+                program,
+                pointers.len() as i32,
+                pointers.as_ptr(),
+                *buffer_mode,
+            );
+        }
+
+        if self.separable {
+            self.gl.program_parameteri(
+                program,
+                crate::gl::PROGRAM_SEPARABLE,
+                crate::gl::TRUE as i32,
+            );
+        }
+
+        if self.binary_retrievable_hint {
+            self.gl.program_parameteri(
+                program,
+                crate::gl::PROGRAM_BINARY_RETRIEVABLE_HINT,
+                crate::gl::TRUE as i32,
+            );
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    unsafe fn pre_link(&self, program: crate::gl::ProgramName) {
+        for (index, name) in &self.attrib_locations {
+            self.gl.bind_attrib_location(program, *index, name);
+        }
+
+        if let Some((varyings, buffer_mode)) = &self.transform_feedback_varyings {
+            let varyings: js_sys::Array = varyings
+                .iter()
+                .map(|name| wasm_bindgen::JsValue::from_str(name))
+                .collect();
+
+            self.gl
+                .transform_feedback_varyings(program, &varyings, *buffer_mode);
+        }
+    }
+
+    /// Link the program
+    ///
+    /// Fails with `Error::InvalidName` if a name contains a NUL character, and with
+    /// `Error::UnsupportedOperation` if a flag is not supported by the context.
     pub fn build(self) -> crate::Result<RuntimeProgram> {
+        self.check_names()?;
+        self.check_support()?;
+
         unsafe {
             let mut program = RuntimeProgram {
                 name: self.gl.create_program().ok_or_else(|| {
@@ -56,6 +283,9 @@ impl<'a> RuntimeProgramBuilder<'a> {
                 self.gl.attach_shader(program.name(), shader.name());
             }
 
+            // Bindings and flags only take effect when linking
+            self.pre_link(program.name());
+
             // Link program
             self.gl.link_program(program.name());
 
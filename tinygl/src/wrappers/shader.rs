@@ -5,6 +5,11 @@ mod binary_shader;
 #[cfg(all(not(target_arch = "wasm32"), feature = "opengl46"))]
 pub use binary_shader::BinaryShader;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod fallback_shader;
+#[cfg(not(target_arch = "wasm32"))]
+pub use fallback_shader::FallbackShader;
+
 mod runtime_shader;
 pub use runtime_shader::*;
 
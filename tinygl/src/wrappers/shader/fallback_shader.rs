@@ -0,0 +1,49 @@
+use crate::Context;
+
+use super::ShaderCommon;
+
+/// Build a shader from its SPIR-V binary if the context supports it, from its source otherwise
+///
+/// If the SPIR-V binary fails to load, the source is used instead.
+///
+/// # Parameters
+///
+/// * `gl`: current OpenGL context
+/// * `binary`: SPIR-V binary of the shader
+/// * `source`: GLSL source of the shader, with the same uniform locations as the binary
+/// * `kind`: shader stage
+pub fn build_fallback_shader(
+    gl: &Context,
+    binary: &[u8],
+    source: &str,
+    kind: u32,
+) -> crate::Result<crate::gl::Shader> {
+    #[cfg(feature = "opengl46")]
+    if unsafe { gl.supports_spirv() } {
+        match super::binary_shader::build_bin_shader(gl, binary, kind) {
+            Ok(shader) => return Ok(shader),
+            Err(error) => {
+                log::warn!(
+                    "failed to load SPIR-V shader, falling back to GLSL source: {}",
+                    error
+                );
+            }
+        }
+    }
+
+    #[cfg(not(feature = "opengl46"))]
+    let _ = binary;
+
+    super::source_shader::build_src_shader(gl, source, kind)
+}
+
+/// SPIR-V shader wrapper with a GLSL source fallback
+pub trait FallbackShader<'a>: ShaderCommon {
+    fn get_binary() -> &'a [u8];
+
+    fn get_source() -> &'a str;
+
+    fn build(gl: &Context, kind: u32) -> crate::Result<crate::gl::Shader> {
+        build_fallback_shader(gl, Self::get_binary(), Self::get_source(), kind)
+    }
+}
@@ -23,6 +23,50 @@ impl RuntimeShader {
             name: super::source_shader::build_src_shader(gl, src, kind)?,
         })
     }
+
+    /// Build a shader from a source returned by the runtime preprocessor
+    ///
+    /// Line numbers in compilation errors are replaced with the files and lines of the
+    /// original sources.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: current OpenGL context
+    /// * `source`: preprocessed source
+    /// * `kind`: shader stage
+    pub fn build_preprocessed(
+        gl: &Context,
+        source: &crate::preprocessor::PreprocessedSource,
+        kind: u32,
+    ) -> crate::Result<Self> {
+        Self::build_src(gl, source.source(), kind).map_err(|error| match error {
+            crate::Error::ShaderCompilationFailed(log) => {
+                crate::Error::ShaderCompilationFailed(source.line_map().map_log(&log))
+            }
+            error => error,
+        })
+    }
+
+    /// Build a shader from a GLSL source file
+    ///
+    /// `#include` directives are resolved relative to the including file, as done by
+    /// tinygl-compiler at build time. Line numbers in compilation errors are replaced with the
+    /// files and lines of the original sources.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: current OpenGL context
+    /// * `path`: path to the GLSL source file
+    /// * `kind`: shader stage
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn build_path(
+        gl: &Context,
+        path: impl AsRef<std::path::Path>,
+        kind: u32,
+    ) -> crate::Result<Self> {
+        let source = crate::preprocessor::preprocess_file(path)?;
+        Self::build_preprocessed(gl, &source, kind)
+    }
 }
 
 impl ShaderCommon for RuntimeShader {
@@ -0,0 +1,40 @@
+/// Scalar component of the default value of a uniform
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum UniformComponent {
+    Int(i32),
+    Float(f32),
+    Double(f64),
+    UInt(u32),
+    Bool(bool),
+}
+
+/// Description of a uniform of a generated program, for building editors
+///
+/// Ranges and labels are declared by annotations in the shader source, e.g.
+/// `// @range(0, 10) @label("Exposure")` next to the uniform declaration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct UniformMetadata {
+    /// Name of the uniform in the shader source
+    pub name: &'static str,
+    /// GLSL type of the uniform
+    pub ty: &'static str,
+    /// Range of values from the `@range(min, max)` annotation
+    pub range: Option<(f64, f64)>,
+    /// Label from the `@label("...")` annotation
+    pub label: Option<&'static str>,
+    /// Components of the initializer of the uniform, in column-major order
+    pub default: Option<&'static [UniformComponent]>,
+}
+
+impl UniformMetadata {
+    /// Return the label of this uniform, or its name if it has no label
+    pub fn display_name(&self) -> &'static str {
+        self.label.unwrap_or(self.name)
+    }
+}
+
+/// Trait for generated programs which describe their uniforms
+pub trait ProgramUniformMetadata {
+    /// Description of the uniforms of this program
+    const UNIFORMS: &'static [UniformMetadata];
+}
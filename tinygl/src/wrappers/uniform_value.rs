@@ -0,0 +1,257 @@
+//! Conversions from math library types to uniform values
+
+use super::UniformLocation;
+
+/// Conversion of a value to the representation of a uniform type
+///
+/// `T` is the storage type of the uniform, e.g. `[f32; 3]` for a `vec3` or `[[f32; 4]; 4]` for a
+/// `mat4`. Matrices are converted to column-major order, so they never need to be transposed.
+///
+/// This is implemented for arrays, and for the types of `mint`, `glam`, `cgmath` and `nalgebra`
+/// when the corresponding feature is enabled.
+pub trait UniformValue<T> {
+    /// Convert this value to its uniform representation
+    fn uniform_value(self) -> T;
+}
+
+impl<T: Copy, const N: usize> UniformValue<[T; N]> for [T; N] {
+    fn uniform_value(self) -> [T; N] {
+        self
+    }
+}
+
+impl<T, U: Copy + UniformValue<T>> UniformValue<T> for &U {
+    fn uniform_value(self) -> T {
+        (*self).uniform_value()
+    }
+}
+
+macro_rules! impl_scalar_uniform_value {
+    ($($ty:ty),*) => {
+        $(
+            impl UniformValue<$ty> for $ty {
+                fn uniform_value(self) -> $ty {
+                    self
+                }
+            }
+        )*
+    };
+}
+
+impl_scalar_uniform_value!(f32, i32, u32);
+
+/// Wrapper to pass uniform values to the typed setters of [`UniformLocation`]
+struct Value<T>(T);
+
+impl<T> AsRef<T> for Value<T> {
+    fn as_ref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// Uniform types which can be set on a [`UniformLocation`]
+pub trait UniformType: Copy {
+    /// Set the uniform location to this value
+    ///
+    /// # Parameters
+    ///
+    /// * `location`: uniform location to set
+    /// * `gl`: current OpenGL context
+    ///
+    /// # Safety
+    ///
+    /// On WebGL, requires the associated program to be in use.
+    unsafe fn set(self, location: &UniformLocation, gl: &crate::Context);
+}
+
+macro_rules! impl_uniform_type {
+    ($ty:ty => $method:ident) => {
+        impl UniformType for $ty {
+            unsafe fn set(self, location: &UniformLocation, gl: &crate::Context) {
+                location.$method(gl, self)
+            }
+        }
+    };
+    ($ty:ty => $method:ident(array)) => {
+        impl UniformType for $ty {
+            unsafe fn set(self, location: &UniformLocation, gl: &crate::Context) {
+                location.$method(gl, Value(self))
+            }
+        }
+    };
+    ($ty:ty => $method:ident(matrix)) => {
+        impl UniformType for $ty {
+            unsafe fn set(self, location: &UniformLocation, gl: &crate::Context) {
+                location.$method(gl, false, Value(self))
+            }
+        }
+    };
+}
+
+impl_uniform_type!(f32 => set_f32);
+impl_uniform_type!([f32; 2] => set_vec2(array));
+impl_uniform_type!([f32; 3] => set_vec3(array));
+impl_uniform_type!([f32; 4] => set_vec4(array));
+impl_uniform_type!(i32 => set_i32);
+impl_uniform_type!([i32; 2] => set_ivec2(array));
+impl_uniform_type!([i32; 3] => set_ivec3(array));
+impl_uniform_type!([i32; 4] => set_ivec4(array));
+impl_uniform_type!(u32 => set_u32);
+impl_uniform_type!([u32; 2] => set_uvec2(array));
+impl_uniform_type!([u32; 3] => set_uvec3(array));
+impl_uniform_type!([u32; 4] => set_uvec4(array));
+impl_uniform_type!([[f32; 2]; 2] => set_mat2(matrix));
+impl_uniform_type!([[f32; 3]; 3] => set_mat3(matrix));
+impl_uniform_type!([[f32; 4]; 4] => set_mat4(matrix));
+
+impl UniformLocation {
+    /// Set the uniform location to the given value
+    ///
+    /// Unlike the typed setters, this accepts any [`UniformValue`], including the types of math
+    /// libraries. Matrices are uploaded in column-major order.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: current OpenGL context
+    /// * `v`: new uniform value
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn set<T: UniformType>(&self, gl: &crate::Context, v: impl UniformValue<T>) {
+        unsafe { v.uniform_value().set(self, gl) }
+    }
+
+    /// Set the uniform location to the given value
+    ///
+    /// Unlike the typed setters, this accepts any [`UniformValue`], including the types of math
+    /// libraries. Matrices are uploaded in column-major order.
+    ///
+    /// # Parameters
+    ///
+    /// * `gl`: current OpenGL context
+    /// * `v`: new uniform value
+    ///
+    /// # Safety
+    ///
+    /// Requires the associated program to be in use.
+    #[cfg(target_arch = "wasm32")]
+    pub unsafe fn set<T: UniformType>(&self, gl: &crate::Context, v: impl UniformValue<T>) {
+        v.uniform_value().set(self, gl)
+    }
+}
+
+#[cfg(feature = "mint")]
+mod mint_impls {
+    use super::UniformValue;
+
+    macro_rules! impl_vector {
+        ($($name:ident => $n:literal),*) => {
+            $(
+                impl<T> UniformValue<[T; $n]> for mint::$name<T> {
+                    fn uniform_value(self) -> [T; $n] {
+                        self.into()
+                    }
+                }
+            )*
+        };
+    }
+
+    macro_rules! impl_matrix {
+        ($($name:ident => $n:literal),*) => {
+            $(
+                impl<T> UniformValue<[[T; $n]; $n]> for mint::$name<T> {
+                    fn uniform_value(self) -> [[T; $n]; $n] {
+                        self.into()
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_vector!(Vector2 => 2, Vector3 => 3, Vector4 => 4);
+    impl_matrix!(ColumnMatrix2 => 2, ColumnMatrix3 => 3, ColumnMatrix4 => 4);
+}
+
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use super::UniformValue;
+
+    macro_rules! impl_glam {
+        ($($name:ident => $ty:ty),*) => {
+            $(
+                impl UniformValue<$ty> for glam::$name {
+                    fn uniform_value(self) -> $ty {
+                        self.into()
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_glam!(
+        Vec2 => [f32; 2], Vec3 => [f32; 3], Vec3A => [f32; 3], Vec4 => [f32; 4],
+        IVec2 => [i32; 2], IVec3 => [i32; 3], IVec4 => [i32; 4],
+        UVec2 => [u32; 2], UVec3 => [u32; 3], UVec4 => [u32; 4]
+    );
+
+    impl UniformValue<[[f32; 2]; 2]> for glam::Mat2 {
+        fn uniform_value(self) -> [[f32; 2]; 2] {
+            self.to_cols_array_2d()
+        }
+    }
+
+    impl UniformValue<[[f32; 3]; 3]> for glam::Mat3 {
+        fn uniform_value(self) -> [[f32; 3]; 3] {
+            self.to_cols_array_2d()
+        }
+    }
+
+    impl UniformValue<[[f32; 4]; 4]> for glam::Mat4 {
+        fn uniform_value(self) -> [[f32; 4]; 4] {
+            self.to_cols_array_2d()
+        }
+    }
+}
+
+#[cfg(feature = "cgmath")]
+mod cgmath_impls {
+    use super::UniformValue;
+
+    macro_rules! impl_cgmath {
+        ($($name:ident => $ty:ty),*) => {
+            $(
+                impl<T: Copy> UniformValue<$ty> for cgmath::$name<T> {
+                    fn uniform_value(self) -> $ty {
+                        self.into()
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_cgmath!(
+        Vector2 => [T; 2], Vector3 => [T; 3], Vector4 => [T; 4],
+        Point2 => [T; 2], Point3 => [T; 3],
+        Matrix2 => [[T; 2]; 2], Matrix3 => [[T; 3]; 3], Matrix4 => [[T; 4]; 4]
+    );
+}
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_impls {
+    use super::UniformValue;
+
+    macro_rules! impl_nalgebra {
+        ($($name:ident => $ty:ty),*) => {
+            $(
+                impl<T: nalgebra::Scalar + Copy> UniformValue<$ty> for nalgebra::$name<T> {
+                    fn uniform_value(self) -> $ty {
+                        self.into()
+                    }
+                }
+            )*
+        };
+    }
+
+    impl_nalgebra!(
+        Vector2 => [T; 2], Vector3 => [T; 3], Vector4 => [T; 4],
+        Matrix2 => [[T; 2]; 2], Matrix3 => [[T; 3]; 3], Matrix4 => [[T; 4]; 4]
+    );
+}